@@ -0,0 +1,148 @@
+//! accessibility: color palettes, urgency cues and HUD text styling.
+//!
+//! Colors never carry meaning on their own outside of the default palette:
+//! the other palettes also make urgent things bigger and louder in shape.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+/// The color palettes to pick from
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    Default,
+    /// safe for red-green and blue-yellow color blindness,
+    /// with size and shape cues for urgency
+    ColorblindSafe,
+    /// bright colors with dark backings behind the HUD
+    HighContrast,
+}
+
+impl Palette {
+    /// The palette after this one, for cycling through them
+    pub fn next(self) -> Self {
+        match self {
+            Palette::Default => Palette::ColorblindSafe,
+            Palette::ColorblindSafe => Palette::HighContrast,
+            Palette::HighContrast => Palette::Default,
+        }
+    }
+
+    /// The key of the palette's name in the string tables
+    pub fn label_key(self) -> &'static str {
+        match self {
+            Palette::Default => "palette_default",
+            Palette::ColorblindSafe => "palette_colorblind",
+            Palette::HighContrast => "palette_high_contrast",
+        }
+    }
+
+    /// Whether urgency is also shown by size and shape
+    pub fn has_shape_cues(self) -> bool {
+        self != Palette::Default
+    }
+
+    /// The color of something in the given level of urgency
+    pub fn urgency_color(self, urgency: Urgency) -> Color {
+        match (self, urgency) {
+            (Palette::Default, Urgency::Calm) => Color::rgba_u8(0xFF, 0xFF, 0x20, 0xFF),
+            (Palette::Default, Urgency::Warning) => Color::rgba_u8(0xF0, 0xA7, 0x10, 0xFF),
+            (Palette::Default, Urgency::Critical) => Color::rgba_u8(0xFF, 0x20, 0x00, 0xFF),
+            // from the Okabe-Ito palette
+            (Palette::ColorblindSafe, Urgency::Calm) => Color::rgba_u8(0x56, 0xB4, 0xE9, 0xFF),
+            (Palette::ColorblindSafe, Urgency::Warning) => Color::rgba_u8(0xF0, 0xE4, 0x42, 0xFF),
+            (Palette::ColorblindSafe, Urgency::Critical) => Color::rgba_u8(0xD5, 0x5E, 0x00, 0xFF),
+            (Palette::HighContrast, Urgency::Calm) => Color::WHITE,
+            (Palette::HighContrast, Urgency::Warning) => Color::rgba_u8(0xFF, 0xFF, 0x00, 0xFF),
+            (Palette::HighContrast, Urgency::Critical) => Color::rgba_u8(0xFF, 0x40, 0xFF, 0xFF),
+        }
+    }
+
+    /// The colors of the progress bar: background and fill
+    pub fn progress_colors(self) -> (Color, Color) {
+        match self {
+            Palette::Default => (Color::RED, Color::GREEN),
+            Palette::ColorblindSafe => (
+                Color::rgba_u8(0x00, 0x2D, 0x5C, 0xFF),
+                Color::rgba_u8(0xF0, 0xE4, 0x42, 0xFF),
+            ),
+            Palette::HighContrast => (Color::BLACK, Color::WHITE),
+        }
+    }
+}
+
+/// How soon something is going to happen
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Urgency {
+    Calm,
+    Warning,
+    Critical,
+}
+
+impl Urgency {
+    /// The urgency of a bomb with this many ticks left
+    pub fn from_ticks_left(ticks_left: u32) -> Self {
+        match ticks_left {
+            0 => Urgency::Critical,
+            1..=3 => Urgency::Warning,
+            _ => Urgency::Calm,
+        }
+    }
+
+    /// The text and font size of a popup with this urgency.
+    /// With shape cues, urgent popups are bigger and exclaimed.
+    pub fn popup(self, palette: Palette, text: String, font_size: f32) -> (String, f32) {
+        if !palette.has_shape_cues() {
+            return (text, font_size);
+        }
+        match self {
+            Urgency::Calm => (text, font_size),
+            Urgency::Warning => (format!("{}!", text), font_size * 1.25),
+            Urgency::Critical => (format!("!{}!", text), font_size * 1.5),
+        }
+    }
+}
+
+impl Settings {
+    /// How much bigger text in the HUD should be
+    pub fn text_scale(&self) -> f32 {
+        if self.large_text {
+            1.5
+        } else {
+            1.
+        }
+    }
+}
+
+/// Text in the HUD, which follows the text size and contrast settings
+#[derive(Debug, Component)]
+pub struct HudText {
+    /// the font size at normal text size
+    pub base_size: f32,
+}
+
+/// the dark backing behind HUD text in high contrast
+const HUD_BACKING: Color = Color::rgba(0., 0., 0., 0.85);
+
+/// system: apply the text size and contrast settings to the HUD
+pub fn style_hud_text(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut query: Query<(Entity, &HudText, ChangeTrackers<HudText>, &mut Text)>,
+) {
+    for (entity, hud_text, tracker, mut text) in &mut query {
+        if !settings.is_changed() && !tracker.is_added() {
+            continue;
+        }
+        for section in &mut text.sections {
+            section.style.font_size = hud_text.base_size * settings.text_scale();
+        }
+        if settings.palette == Palette::HighContrast {
+            commands.entity(entity).insert(BackgroundColor(HUD_BACKING));
+        } else {
+            commands.entity(entity).remove::<BackgroundColor>();
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! achievements: goals met during a run, unlocked for good.
+//!
+//! All achievements are declared in [`ACHIEVEMENTS`],
+//! each with the goal which unlocks it.
+//! Progress towards the goals is recorded over a run from gameplay events,
+//! and unlocked achievements are stored and shown in a gallery.
+//! Runs which the autopilot played, even in part, do not count.
+
+use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, utils::Duration};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::{
+    animation::FadeOut,
+    autopilot::ComputerPlayer,
+    bomb::BombTick,
+    events::{
+        BombDisarmedEvent, CoffeePickedUpEvent, DynamiteDefusedEvent, GuyHurtEvent,
+        WaveFinishedEvent,
+    },
+    helper::{DelayedComponent, TimeToLive},
+    i18n::LocalizedText,
+    ingame::Wave,
+    menu::spawn_back_button,
+    resume::ResumeRun,
+    scores::{GameScores, Stats},
+    DefaultFont,
+};
+
+/// The key under which unlocked achievements are stored
+const ACHIEVEMENTS_KEY: &str = "achievements";
+
+/// What needs to be done in a single run to unlock an achievement
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Goal {
+    /// disarm a bomb with this many ticks left, or fewer
+    DisarmWithTicksLeft(u32),
+    /// disarm this many bombs
+    Bombs(u32),
+    /// defuse this many dynamites
+    Dynamites(u32),
+    /// drink this many coffees
+    Coffees(u32),
+    /// get through this many waves without taking a blast
+    /// (the first wave does not count)
+    CleanWaves(u32),
+    /// finish the game without taking a single blast
+    NoBlastsTaken,
+    /// finish the game with at least this score
+    Score(i32),
+}
+
+/// An achievement, as declared in [`ACHIEVEMENTS`]
+#[derive(Debug)]
+pub struct Achievement {
+    /// identifies the achievement in storage,
+    /// and its name and description in the string tables
+    /// (`achievement_<id>` and `achievement_<id>_desc`)
+    pub id: &'static str,
+    pub goal: Goal,
+}
+
+impl Achievement {
+    pub fn name_key(&self) -> String {
+        format!("achievement_{}", self.id)
+    }
+
+    pub fn description_key(&self) -> String {
+        format!("achievement_{}_desc", self.id)
+    }
+}
+
+/// All achievements in the game, in the order shown in the gallery
+pub static ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "first_bomb",
+        goal: Goal::Bombs(1),
+    },
+    Achievement {
+        id: "clutch",
+        goal: Goal::DisarmWithTicksLeft(1),
+    },
+    Achievement {
+        id: "caffeinated",
+        goal: Goal::Coffees(5),
+    },
+    Achievement {
+        id: "bomb_squad",
+        goal: Goal::Bombs(10),
+    },
+    Achievement {
+        id: "fuse_snuffer",
+        goal: Goal::Dynamites(50),
+    },
+    Achievement {
+        id: "clean_wave",
+        goal: Goal::CleanWaves(1),
+    },
+    Achievement {
+        id: "untouchable",
+        goal: Goal::NoBlastsTaken,
+    },
+    Achievement {
+        id: "great_score",
+        goal: Goal::Score(400),
+    },
+    Achievement {
+        id: "ludicrous",
+        goal: Goal::Score(512),
+    },
+];
+
+/// The achievements unlocked so far, by id
+#[derive(Debug, Default, Clone, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnlockedAchievements {
+    pub unlocked: BTreeSet<String>,
+}
+
+impl UnlockedAchievements {
+    /// Load unlocked achievements from storage
+    pub fn load() -> Self {
+        crate::storage::load(ACHIEVEMENTS_KEY).unwrap_or_default()
+    }
+
+    pub fn contains(&self, achievement: &Achievement) -> bool {
+        self.unlocked.contains(achievement.id)
+    }
+
+    /// Unlock an achievement and store it,
+    /// returning whether it was not unlocked before
+    fn unlock(&mut self, achievement: &Achievement) -> bool {
+        let new = self.unlocked.insert(achievement.id.to_string());
+        if new {
+            info!("Achievement unlocked: {}", achievement.id);
+            crate::storage::save(ACHIEVEMENTS_KEY, self);
+        }
+        new
+    }
+}
+
+/// What has been done so far in the current run
+#[derive(Debug, Default, Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunRecord {
+    pub bombs: u32,
+    pub dynamites: u32,
+    pub coffees: u32,
+    pub blasts: u32,
+    /// the fewest ticks any bomb had left when it was disarmed
+    closest_call: Option<u32>,
+    blasts_this_wave: u32,
+    clean_waves: u32,
+    /// the final scores, once the game is over
+    final_scores: Option<(i32, u32)>,
+    /// whether the computer played any part of the run
+    pub assisted: bool,
+}
+
+impl Goal {
+    fn reached(self, record: &RunRecord) -> bool {
+        match self {
+            Goal::DisarmWithTicksLeft(ticks) => record.closest_call.is_some_and(|t| t <= ticks),
+            Goal::Bombs(n) => record.bombs >= n,
+            Goal::Dynamites(n) => record.dynamites >= n,
+            Goal::Coffees(n) => record.coffees >= n,
+            Goal::CleanWaves(n) => record.clean_waves >= n,
+            Goal::NoBlastsTaken => record
+                .final_scores
+                .is_some_and(|(_, blasts_taken)| blasts_taken == 0),
+            Goal::Score(n) => record.final_scores.is_some_and(|(score, _)| score >= n),
+        }
+    }
+}
+
+/// system: start recording a new run,
+/// or pick up the record of the run being resumed
+pub fn start_run(mut commands: Commands, resume: Option<Res<ResumeRun>>) {
+    let record = resume
+        .map(|resume| resume.0.record.clone())
+        .unwrap_or_default();
+    commands.insert_resource(record);
+}
+
+/// The gameplay events which count towards achievements
+#[derive(SystemParam)]
+pub struct RunEvents<'w, 's> {
+    bomb_disarmed: EventReader<'w, 's, BombDisarmedEvent>,
+    dynamite_defused: EventReader<'w, 's, DynamiteDefusedEvent>,
+    coffee_picked_up: EventReader<'w, 's, CoffeePickedUpEvent>,
+    guy_hurt: EventReader<'w, 's, GuyHurtEvent>,
+    wave_finished: EventReader<'w, 's, WaveFinishedEvent>,
+}
+
+/// system: keep a record of the run from gameplay events
+pub fn record_run(
+    mut record: ResMut<RunRecord>,
+    mut events: RunEvents,
+    wave: Res<Wave>,
+    scores: Res<GameScores>,
+    query_bombs: Query<&BombTick>,
+    query_game_over: Query<(), Added<Stats>>,
+    computer: ComputerPlayer,
+) {
+    if computer.is_playing() && !record.assisted {
+        record.assisted = true;
+    }
+
+    for BombDisarmedEvent(bomb, _) in events.bomb_disarmed.iter() {
+        record.bombs += 1;
+        if let Ok(tick) = query_bombs.get(*bomb) {
+            let closest = record
+                .closest_call
+                .map_or(tick.ticks_left, |t| t.min(tick.ticks_left));
+            record.closest_call = Some(closest);
+        }
+    }
+    for _ in events.dynamite_defused.iter() {
+        record.dynamites += 1;
+    }
+    for _ in events.coffee_picked_up.iter() {
+        record.coffees += 1;
+    }
+    for _ in events.guy_hurt.iter() {
+        record.blasts += 1;
+        record.blasts_this_wave += 1;
+    }
+    for _ in events.wave_finished.iter() {
+        if wave.0 > 0 && record.blasts_this_wave == 0 {
+            record.clean_waves += 1;
+        }
+        record.blasts_this_wave = 0;
+    }
+
+    // the stats are shown once the game is over
+    if !query_game_over.is_empty() && record.final_scores.is_none() {
+        record.final_scores = Some((scores.score, scores.blasts_taken));
+    }
+}
+
+/// Marker for the toasts telling that an achievement was unlocked
+#[derive(Debug, Component)]
+pub struct AchievementToast;
+
+/// How long an unlock toast stays on screen before fading out
+const TOAST_TIME: Duration = Duration::from_millis(2_500);
+const TOAST_FADE_TIME: Duration = Duration::from_millis(600);
+
+/// system: unlock the achievements whose goals were reached,
+/// with a toast for each one
+pub fn unlock_achievements(
+    mut commands: Commands,
+    record: Res<RunRecord>,
+    mut unlocked: ResMut<UnlockedAchievements>,
+    font: Res<DefaultFont>,
+    query_toasts: Query<(), With<AchievementToast>>,
+) {
+    if !record.is_changed() || record.assisted {
+        return;
+    }
+
+    let mut toasts = query_toasts.iter().count();
+    for achievement in ACHIEVEMENTS {
+        if achievement.goal.reached(&record) && unlocked.unlock(achievement) {
+            spawn_toast(&mut commands, font.0.clone(), achievement, toasts);
+            toasts += 1;
+        }
+    }
+}
+
+/// spawn a toast with the name of the achievement,
+/// below the toasts already on screen
+fn spawn_toast(
+    commands: &mut Commands,
+    font: Handle<Font>,
+    achievement: &Achievement,
+    index: usize,
+) {
+    let top = 56. + index as f32 * 56.;
+    let lines = [
+        (LocalizedText::new("achievement_unlocked"), 18., top),
+        (LocalizedText::new(achievement.name_key()), 24., top + 20.),
+    ];
+
+    for (i, (text, font_size, top)) in lines.into_iter().enumerate() {
+        let mut entity = commands.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: font.clone(),
+                    font_size,
+                    color: Color::rgb(1., 0.85, 0.3),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(top),
+                    left: Val::Px(0.),
+                    right: Val::Px(0.),
+                    ..default()
+                },
+                justify_content: JustifyContent::Center,
+                ..default()
+            }),
+            text,
+            DelayedComponent::new(FadeOut::new(TOAST_FADE_TIME), TOAST_TIME),
+            TimeToLive::new(TOAST_TIME + TOAST_FADE_TIME),
+        ));
+        // count each toast once
+        if i == 0 {
+            entity.insert(AchievementToast);
+        }
+    }
+}
+
+/// system: show the gallery of achievements,
+/// with the ones still locked dimmed
+pub fn setup_gallery(
+    mut commands: Commands,
+    font: Res<DefaultFont>,
+    unlocked: Res<UnlockedAchievements>,
+) {
+    // UI camera
+    commands.spawn(crate::screen::camera_bundle());
+
+    let n_unlocked = ACHIEVEMENTS
+        .iter()
+        .filter(|achievement| unlocked.contains(achievement))
+        .count();
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 36.,
+                        color: Color::WHITE,
+                    },
+                ),
+                LocalizedText::new("achievements"),
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 20.,
+                        color: Color::rgb(0.8, 0.8, 0.8),
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect {
+                        bottom: Val::Px(16.),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                LocalizedText::new("achievements_count")
+                    .with_arg("unlocked", n_unlocked)
+                    .with_arg("total", ACHIEVEMENTS.len()),
+            ));
+
+            for achievement in ACHIEVEMENTS {
+                let (name_color, description_color) = if unlocked.contains(achievement) {
+                    (Color::rgb(1., 0.85, 0.3), Color::WHITE)
+                } else {
+                    (Color::rgb(0.45, 0.45, 0.5), Color::rgb(0.55, 0.55, 0.6))
+                };
+
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font.0.clone(),
+                            font_size: 22.,
+                            color: name_color,
+                        },
+                    )
+                    .with_style(Style {
+                        margin: UiRect {
+                            top: Val::Px(6.),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                    LocalizedText::new(achievement.name_key()),
+                ));
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font.0.clone(),
+                            font_size: 16.,
+                            color: description_color,
+                        },
+                    ),
+                    LocalizedText::new(achievement.description_key()),
+                ));
+            }
+
+            spawn_back_button(parent, font.0.clone());
+        });
+}
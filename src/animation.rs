@@ -2,8 +2,11 @@ use bevy::prelude::*;
 use bevy::time::Stopwatch;
 use bevy::utils::Duration;
 
+use crate::settings::Settings;
+
 /// For things that rotate a bit
-#[derive(Debug, Component)]
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct Rotating {
     pub rpm: f32,
 }
@@ -18,15 +21,18 @@ pub fn make_things_rotate(
 }
 
 /// Component that keeps track of the entitie's baseline scale
-#[derive(Debug, Component)]
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct BaseScale(pub f32);
 
 /// For entities with a "wobbly" effect
-#[derive(Debug, Component)]
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct Wobbly;
 
 pub fn wobble(
     time: Res<Time>,
+    settings: Res<Settings>,
     mut transforms: Query<(&mut Transform, Option<&BaseScale>, With<Wobbly>)>,
 ) {
     let t = time.elapsed_seconds();
@@ -34,14 +40,19 @@ pub fn wobble(
 
     for (mut transform, base_scale, _) in &mut transforms {
         let base = base_scale.map(|scale| scale.0).unwrap_or(1.);
-        let w = 1. + a1.sin() * 0.07;
-        let h = 1. + (a1 + 0.1).cos() * 0.05;
+        // hold still with reduced motion
+        let (w, h) = if settings.reduced_motion {
+            (1., 1.)
+        } else {
+            (1. + a1.sin() * 0.07, 1. + (a1 + 0.1).cos() * 0.05)
+        };
         transform.scale = Vec3::new(base * w, base * h, 1.);
     }
 }
 
 /// An animation timer that runs for N frames then stops at the last frame.
-#[derive(Debug, Component)]
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct OneShotAnimationTimer {
     timer: Timer,
     n_frames: u32,
@@ -78,7 +89,8 @@ pub fn animate_one_shot(
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Reflect, FromReflect)]
+#[reflect(Component)]
 pub struct ToggleVisibility(pub Timer);
 
 impl Default for ToggleVisibility {
@@ -87,12 +99,33 @@ impl Default for ToggleVisibility {
     }
 }
 
-/// system: toggle visibility, so that it looks like it is blinking fast
+/// how opaque blinking sprites are with reduced motion
+const STEADY_BLINK_ALPHA: f32 = 0.5;
+
+/// system: toggle visibility, so that it looks like it is blinking fast.
+/// With reduced motion, sprites are made translucent instead.
 pub fn toggle_visibility(
     time: Res<Time>,
-    mut query: Query<(&mut ToggleVisibility, &mut Visibility)>,
+    settings: Res<Settings>,
+    mut query: Query<(
+        &mut ToggleVisibility,
+        &mut Visibility,
+        Option<&mut TextureAtlasSprite>,
+    )>,
 ) {
-    for (mut timer, mut visibility) in query.iter_mut() {
+    for (mut timer, mut visibility, sprite) in query.iter_mut() {
+        if settings.reduced_motion {
+            if !visibility.is_visible {
+                visibility.is_visible = true;
+            }
+            if let Some(mut sprite) = sprite {
+                if sprite.color.a() != STEADY_BLINK_ALPHA {
+                    sprite.color.set_a(STEADY_BLINK_ALPHA);
+                }
+            }
+            continue;
+        }
+
         timer.0.tick(time.delta());
 
         if timer.0.just_finished() {
@@ -103,18 +136,22 @@ pub fn toggle_visibility(
 
 pub fn detect_toggle_visibility_removal(
     removed: RemovedComponents<ToggleVisibility>,
-    mut query: Query<&mut Visibility>,
+    mut query: Query<(&mut Visibility, Option<&mut TextureAtlasSprite>)>,
 ) {
     for entity in &removed {
-        if let Ok(mut visibility) = query.get_mut(entity) {
+        if let Ok((mut visibility, sprite)) = query.get_mut(entity) {
             visibility.is_visible = true;
+            if let Some(mut sprite) = sprite {
+                sprite.color.set_a(1.);
+            }
         }
     }
 }
 
 /// An animation timer that runs through all frames in the sprite sheet
 /// in a loop.
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct LoopedAnimationTimer(Timer);
 
 impl LoopedAnimationTimer {
@@ -156,7 +193,8 @@ pub fn animate_loops(
 ///
 /// Note: combine this with [`TimeToLive`][crate::helper::TimeToLive]
 /// so that it disappears afterwards.
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Reflect, FromReflect)]
+#[reflect(Component)]
 pub struct FadeOut {
     duration: Duration,
     stopwatch: Stopwatch,
@@ -209,7 +247,8 @@ pub fn fade_out_on_text(time: Res<Time>, mut query: Query<(&mut FadeOut, &mut Te
 }
 
 /// A fade-in effect.
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct FadeIn {
     duration: Duration,
     stopwatch: Stopwatch,
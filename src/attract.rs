@@ -0,0 +1,172 @@
+//! attract mode: after a while idling on the main menu,
+//! a round plays by itself behind the title,
+//! until someone touches the controls.
+
+use bevy::utils::Duration;
+use bevy::{ecs::system::SystemParam, input::mouse::MouseMotion, prelude::*};
+
+use crate::{
+    animation::{spawn_fade_in_black_screen, ToggleVisibility},
+    i18n::LocalizedText,
+    ingame::GameMode,
+    AppState, DefaultFont, DelayedStateChange,
+};
+
+/// How long the main menu idles before the demo starts
+const IDLE_TIME: Duration = Duration::from_secs(8);
+
+/// How long a demo round goes on before going back to the menu
+const DEMO_LENGTH: Duration = Duration::from_secs(60);
+
+/// Present while the round being played is a demo
+#[derive(Debug, Resource)]
+pub struct DemoMode(Timer);
+
+impl Default for DemoMode {
+    fn default() -> Self {
+        DemoMode(Timer::new(DEMO_LENGTH, TimerMode::Once))
+    }
+}
+
+/// Any sign of a player at the controls
+#[derive(SystemParam)]
+pub struct AnyInput<'w, 's> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    mouse_button_input: Res<'w, Input<MouseButton>>,
+    touches: Res<'w, Touches>,
+    mouse_motion: EventReader<'w, 's, MouseMotion>,
+}
+
+impl<'w, 's> AnyInput<'w, 's> {
+    /// Whether anything was pressed or moved since last time
+    pub fn happened(&mut self) -> bool {
+        // always drain the events, so that old motion does not linger
+        let moved = self.mouse_motion.iter().count() > 0;
+        moved
+            || self.keyboard_input.get_just_pressed().next().is_some()
+            || self.mouse_button_input.get_just_pressed().next().is_some()
+            || self.touches.any_just_pressed()
+    }
+}
+
+/// Marker for the title shown over the demo
+#[derive(Debug, Component)]
+pub struct DemoTitle;
+
+/// system: start the demo once the main menu has been left alone for a while
+pub fn start_demo_when_idle(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut input: AnyInput,
+    mut idle: Local<Duration>,
+    transition_entity: Query<Entity, With<DelayedStateChange>>,
+) {
+    if input.happened() || !transition_entity.is_empty() {
+        *idle = Duration::ZERO;
+        return;
+    }
+
+    *idle += time.delta();
+    if *idle >= IDLE_TIME {
+        *idle = Duration::ZERO;
+        info!("Starting demo");
+        commands.insert_resource(DemoMode::default());
+        // the demo shows a single guy
+        commands.insert_resource(GameMode::Classic);
+        let e = spawn_fade_in_black_screen(&mut commands, Duration::from_millis(400));
+        commands.entity(e).insert(DelayedStateChange::new(
+            AppState::InGame,
+            Duration::from_millis(750),
+        ));
+    }
+}
+
+/// system: show the title over the demo
+pub fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    font: Res<DefaultFont>,
+    demo: Option<Res<DemoMode>>,
+) {
+    if demo.is_none() {
+        return;
+    }
+
+    let title: Handle<Image> = asset_server.load("img/cool-title.png");
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items: AlignItems::Center,
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                z_index: ZIndex::Global(800),
+                focus_policy: bevy::ui::FocusPolicy::Pass,
+                ..default()
+            },
+            DemoTitle,
+        ))
+        .with_children(|parent| {
+            parent.spawn(ImageBundle {
+                style: Style {
+                    margin: UiRect {
+                        top: Val::Px(60.),
+                        ..default()
+                    },
+                    ..default()
+                },
+                image_mode: bevy::ui::widget::ImageMode::KeepAspect,
+                image: UiImage(title),
+                focus_policy: bevy::ui::FocusPolicy::Pass,
+                ..default()
+            });
+
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 28.,
+                        color: Color::rgb(1., 1., 0.5),
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect {
+                        bottom: Val::Px(48.),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                LocalizedText::new("press_any_key"),
+                ToggleVisibility(Timer::new(Duration::from_millis(600), TimerMode::Repeating)),
+            ));
+        });
+}
+
+/// system: go back to the main menu on any input,
+/// or when the demo has gone on for long enough
+pub fn leave_demo(
+    time: Res<Time>,
+    demo: Option<ResMut<DemoMode>>,
+    mut input: AnyInput,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let Some(mut demo) = demo else {
+        return;
+    };
+
+    demo.0.tick(time.delta());
+    if input.happened() || demo.0.finished() {
+        let _ = app_state.set(AppState::MainMenu);
+    }
+}
+
+/// system: the demo is over once the round is left
+pub fn end_demo(mut commands: Commands) {
+    commands.remove_resource::<DemoMode>();
+}
@@ -53,5 +53,6 @@ pub struct GameSoundSources {
 pub struct GameMusic(pub Handle<AudioSource>);
 
 /// A sound that something should make when it bounces off the ground.
-#[derive(Component)]
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct BounceAudio(pub Handle<AudioSource>);
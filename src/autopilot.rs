@@ -0,0 +1,346 @@
+//! a computer player which drives the guy around by itself.
+//!
+//! Toggle it with F8, or start the game with `--autopilot`.
+//! It is always on when the game is started with `--headless`.
+//! The autopilot only ever touches the guy's destination and target,
+//! just like a player would, so it plays by the same rules.
+//! Its decisions are reported to the debug log.
+//! It also plays the demo rounds of the attract mode.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    accessibility::HudText,
+    attract::DemoMode,
+    bomb::{BombState, BombTick},
+    coffee::{Coffee, CoffeeEffect},
+    dynamite::{Dynamite, TimeToExplode},
+    events::ExplosiveKind,
+    guy::{GuyDestination, GuyPerformance, GuyState, GuyTarget, GuyWaypoints, PointerControlled},
+    helper::BaseTranslation,
+    i18n::LocalizedText,
+    movement::{MaxSpeed, PlayBounds, SpatialPosition},
+    screen::InField,
+    DefaultFont,
+};
+
+/// Whether the autopilot is driving the guy
+#[derive(Debug, Default, Resource)]
+pub struct Autopilot {
+    pub enabled: bool,
+    /// what it decided last, to only log changes of mind
+    last_decision: Option<Decision>,
+}
+
+impl Autopilot {
+    /// Enabled if the game was started with `--autopilot`,
+    /// or without a window
+    pub fn from_args() -> Self {
+        Autopilot {
+            enabled: std::env::args().any(|arg| arg == "--autopilot")
+                || crate::headless::is_requested(),
+            last_decision: None,
+        }
+    }
+}
+
+/// Whether the computer is playing the current run,
+/// with the autopilot or in a demo
+#[derive(SystemParam)]
+pub struct ComputerPlayer<'w, 's> {
+    autopilot: Res<'w, Autopilot>,
+    demo: Option<Res<'w, DemoMode>>,
+    #[system_param(ignore)]
+    marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl<'w, 's> ComputerPlayer<'w, 's> {
+    pub fn is_playing(&self) -> bool {
+        self.autopilot.enabled || self.demo.is_some()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Decision {
+    /// get away from an explosive about to go off
+    Flee(Entity),
+    /// go for a bomb or dynamite
+    Defuse(Entity),
+    /// grab some coffee on the way
+    Coffee(Entity),
+    /// nothing worth doing, stay out of harm's way
+    Wait,
+}
+
+/// Marker for the text telling that the autopilot is on
+#[derive(Debug, Component)]
+pub struct AutopilotLabel;
+
+/// explosives going off sooner than this (in seconds) are run away from
+const FLEE_TIME: f32 = 0.9;
+/// extra distance to keep from blasts, in pixels
+const BLAST_MARGIN: f32 = 24.;
+/// how much further (in seconds of walking) the guy would go for coffee
+const COFFEE_DETOUR: f32 = 1.5;
+/// how much time (in seconds) to spare before going for coffee
+const COFFEE_SLACK: f32 = 2.5;
+
+/// An explosive which the guy could deal with
+struct Threat {
+    entity: Entity,
+    kind: ExplosiveKind,
+    /// where it explodes
+    position: Vec2,
+    /// where the guy needs to stand to deal with it
+    reach: Vec2,
+    /// seconds until it explodes
+    time_left: f32,
+}
+
+/// system: toggle the autopilot with F8
+pub fn toggle(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    font: Res<DefaultFont>,
+    mut autopilot: ResMut<Autopilot>,
+    query_label: Query<Entity, With<AutopilotLabel>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F8) {
+        autopilot.enabled = !autopilot.enabled;
+        autopilot.last_decision = None;
+        info!("Autopilot {}", if autopilot.enabled { "on" } else { "off" });
+    }
+
+    match (autopilot.enabled, query_label.get_single()) {
+        (true, Err(_)) => {
+            commands.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 20.,
+                        color: Color::rgb(0.6, 1., 0.6),
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(8.),
+                        left: Val::Px(12.),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                AutopilotLabel,
+                HudText { base_size: 20. },
+                InField,
+                LocalizedText::new("autopilot"),
+            ));
+        }
+        (false, Ok(label)) => {
+            commands.entity(label).despawn_recursive();
+        }
+        _ => {}
+    }
+}
+
+/// what the autopilot looks at and drives on the guy
+type GuyControls<'a> = (
+    &'a GuyState,
+    &'a SpatialPosition,
+    &'a BaseTranslation,
+    &'a MaxSpeed,
+    &'a GuyPerformance,
+    Option<&'a CoffeeEffect>,
+    &'a mut GuyDestination,
+    &'a mut GuyWaypoints,
+    &'a mut GuyTarget,
+);
+
+/// system: decide where the guy should go next.
+/// In co-op, only the first player's guy is driven.
+pub fn drive(
+    mut autopilot: ResMut<Autopilot>,
+    demo: Option<Res<DemoMode>>,
+    bounds: Res<PlayBounds>,
+    mut query_guy: Query<GuyControls<'static>, With<PointerControlled>>,
+    query_bombs: Query<(
+        Entity,
+        &BombState,
+        &BombTick,
+        &SpatialPosition,
+        &BaseTranslation,
+    )>,
+    query_dynamite: Query<(Entity, &TimeToExplode, &SpatialPosition), With<Dynamite>>,
+    query_coffee: Query<(Entity, &SpatialPosition), With<Coffee>>,
+) {
+    // the demo is always played by the autopilot
+    if !autopilot.enabled && demo.is_none() {
+        return;
+    }
+    let Ok((
+        state,
+        position,
+        base_translation,
+        max_speed,
+        performance,
+        coffee_effect,
+        mut destination,
+        mut waypoints,
+        mut target,
+    )) = query_guy.get_single_mut()
+    else {
+        return;
+    };
+    // let the guy finish what he is doing
+    if !matches!(state, GuyState::Idle | GuyState::Running) {
+        return;
+    }
+
+    // where the guy is standing, as in `disarming_bomb`
+    let guy_pos = position.0.truncate() + base_translation.0;
+    // where the guy gets hurt from, as in `take_hit`
+    let guy_body = position.0.truncate();
+    let speed = max_speed.0.max(1.);
+
+    let threats: Vec<Threat> = query_bombs
+        .iter()
+        .filter(|(_, state, ..)| **state == BombState::Idle)
+        .map(|(entity, _, tick, pos, base)| Threat {
+            entity,
+            kind: ExplosiveKind::Bomb,
+            position: pos.0.truncate(),
+            reach: pos.0.truncate() - base.0,
+            time_left: tick.time_left(),
+        })
+        .chain(
+            query_dynamite
+                .iter()
+                .map(|(entity, time_to_explode, pos)| Threat {
+                    entity,
+                    kind: ExplosiveKind::Dynamite,
+                    position: pos.0.truncate(),
+                    reach: pos.0.truncate(),
+                    time_left: time_to_explode.remaining_secs(),
+                }),
+        )
+        .collect();
+
+    let blast_reaches = |threat: &Threat, pos: Vec2| {
+        let r = threat.kind.blast_radius() + BLAST_MARGIN;
+        threat.position.distance_squared(pos) < r * r
+    };
+
+    // seconds it takes to deal with a threat, walking included
+    let time_needed = |threat: &Threat| {
+        let walk = guy_pos.distance(threat.reach) / speed;
+        match threat.kind {
+            ExplosiveKind::Bomb => walk + 1. / performance.0.max(0.01),
+            ExplosiveKind::Dynamite => walk,
+        }
+    };
+
+    // 1. get out of the way of anything about to go off
+    let danger = threats
+        .iter()
+        .filter(|threat| threat.time_left < FLEE_TIME && blast_reaches(threat, guy_body))
+        .filter(|threat| time_needed(threat) >= threat.time_left)
+        .min_by(|t1, t2| t1.time_left.total_cmp(&t2.time_left));
+
+    let decision = if let Some(threat) = danger {
+        let away = (guy_body - threat.position)
+            .try_normalize()
+            .unwrap_or(Vec2::X);
+        let r = threat.kind.blast_radius() + BLAST_MARGIN * 2.;
+        let safe = bounds.clamp(threat.position + away * r);
+        if target.0.is_some() {
+            target.0 = None;
+        }
+        if destination.0 != safe {
+            destination.0 = safe;
+        }
+        Decision::Flee(threat.entity)
+    } else {
+        // 2. go for the most urgent explosive which can still be dealt with,
+        // avoiding spots which something else will blow up before we are done
+        let best = threats
+            .iter()
+            .filter_map(|threat| {
+                let needed = time_needed(threat);
+                let slack = threat.time_left - needed;
+                if slack < 0. {
+                    return None;
+                }
+                let overlap = threats
+                    .iter()
+                    .filter(|other| other.entity != threat.entity)
+                    .filter(|other| other.time_left < needed + 0.5)
+                    .filter(|other| blast_reaches(other, threat.reach))
+                    .count();
+                Some((threat, slack + overlap as f32 * 10.))
+            })
+            .min_by(|(_, cost1), (_, cost2)| cost1.total_cmp(cost2));
+
+        // 3. grab coffee when it is close and there is time to spare
+        let coffee = query_coffee
+            .iter()
+            .filter(|_| coffee_effect.is_none())
+            .map(|(entity, pos)| (entity, guy_pos.distance(pos.0.truncate()) / speed))
+            .filter(|(_, walk)| *walk < COFFEE_DETOUR)
+            .min_by(|(_, walk1), (_, walk2)| walk1.total_cmp(walk2))
+            .filter(|(_, walk)| {
+                threats
+                    .iter()
+                    .all(|threat| threat.time_left - time_needed(threat) - walk > COFFEE_SLACK)
+            });
+
+        match (coffee, best) {
+            (Some((entity, _)), _) => {
+                if target.0 != Some(entity) {
+                    target.0 = Some(entity);
+                }
+                Decision::Coffee(entity)
+            }
+            (None, Some((threat, _))) => {
+                if target.0 != Some(threat.entity) {
+                    target.0 = Some(threat.entity);
+                }
+                Decision::Defuse(threat.entity)
+            }
+            (None, None) => {
+                if target.0.is_some() {
+                    target.0 = None;
+                }
+                // step out of any blast radius
+                if let Some(threat) = threats
+                    .iter()
+                    .find(|threat| blast_reaches(threat, guy_body))
+                {
+                    let away = (guy_body - threat.position)
+                        .try_normalize()
+                        .unwrap_or(Vec2::X);
+                    let r = threat.kind.blast_radius() + BLAST_MARGIN * 2.;
+                    let safe = bounds.clamp(threat.position + away * r);
+                    if destination.0 != safe {
+                        destination.0 = safe;
+                    }
+                }
+                Decision::Wait
+            }
+        }
+    };
+
+    if !waypoints.0.is_empty() {
+        waypoints.0.clear();
+    }
+
+    if autopilot.last_decision != Some(decision) {
+        debug!("Autopilot: {:?}", decision);
+        autopilot.last_decision = Some(decision);
+    }
+}
+
+/// system: forget past decisions when leaving the game
+pub fn reset(mut autopilot: ResMut<Autopilot>) {
+    autopilot.last_decision = None;
+}
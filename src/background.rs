@@ -4,48 +4,52 @@ use bevy_ecs_tilemap::{
         get_tilemap_center_transform, TilemapId, TilemapSize, TilemapTexture, TilemapTileSize,
         TilemapType,
     },
-    tiles::{TilePos, TileStorage, TileTextureIndex},
+    tiles::{TileBundle, TilePos, TileStorage, TileTextureIndex, TileVisible},
     TilemapBundle,
 };
 
 #[derive(Debug, Component)]
 pub struct Background;
 
-use crate::helper::Fixed;
+/// Marker for the tilemap layer with decoration tiles,
+/// drawn on top of the ground tiles
+#[derive(Debug, Component)]
+pub struct DecorationLayer;
+
+use crate::{helper::Fixed, level::LevelLayout, screen::FIELD_SIZE};
 
 const TILE_SCALE: f32 = 2.;
 const TILE_SIZE: u32 = 48;
 
+/// the row where the initial ground tiles end and the wall starts
 const TILE_DIVIDE: u32 = 8;
 
 #[derive(Debug, Resource)]
 pub struct BackgroundTilesheet(pub Handle<Image>);
 
-pub fn setup(mut commands: Commands, windows: Res<Windows>, asset_server: Res<AssetServer>) {
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     // load tilesheet image
     let tex_tilesheet: Handle<Image> = asset_server.load("img/tilesheet.png");
 
     // save tilesheet for later use
     commands.insert_resource(BackgroundTilesheet(tex_tilesheet.clone()));
 
-    let window = windows.get_primary().unwrap();
-
     // spawn
-    crate::background::spawn_background(&mut commands, window, tex_tilesheet, 0, 1);
+    crate::background::spawn_background(&mut commands, FIELD_SIZE, tex_tilesheet, 0, 1);
 }
 
 pub fn spawn_background(
     commands: &mut Commands,
-    window: &Window,
+    size: Vec2,
     tilesheet: Handle<Image>,
     upper_tile_index: u32,
     lower_tile_index: u32,
 ) -> Entity {
-    // set up tiled background that covers the whole display
+    // set up tiled background that covers the whole play field
     let tilemap_size = TilemapSize {
-        x: (window.width() / TILE_SCALE / TILE_SIZE as f32).ceil() as u32 + 2,
-        //y: (window.height() / TILE_SIZE as f32).ceil() as u32,
-        y: (window.height() / TILE_SCALE / TILE_SIZE as f32).ceil() as u32 + 4,
+        x: (size.x / TILE_SCALE / TILE_SIZE as f32).ceil() as u32 + 2,
+        //y: (size.y / TILE_SIZE as f32).ceil() as u32,
+        y: (size.y / TILE_SCALE / TILE_SIZE as f32).ceil() as u32 + 4,
     };
 
     // create tilemap entity in advance
@@ -88,7 +92,7 @@ pub fn spawn_background(
             map_type,
             size: tilemap_size,
             storage: tile_storage,
-            texture: TilemapTexture::Single(tilesheet),
+            texture: TilemapTexture::Single(tilesheet.clone()),
             tile_size,
             transform: get_tilemap_center_transform(&tilemap_size, &grid_size, &map_type, -0.75)
                 .with_scale(Vec3::new(TILE_SCALE, TILE_SCALE, 1.)),
@@ -98,34 +102,77 @@ pub fn spawn_background(
         Fixed,
     ));
 
+    // decoration layer, hidden until a level layout says otherwise
+    let decoration_entity = commands.spawn_empty().id();
+    let mut decoration_storage = TileStorage::empty(tilemap_size);
+    for x in 0..tilemap_size.x {
+        for y in 0..tilemap_size.y {
+            let position = TilePos { x, y };
+            let tile_entity = commands
+                .spawn(TileBundle {
+                    position,
+                    tilemap_id: TilemapId(decoration_entity),
+                    visible: TileVisible(false),
+                    ..Default::default()
+                })
+                .id();
+            decoration_storage.set(&position, tile_entity);
+        }
+    }
+
+    commands.entity(decoration_entity).insert((
+        TilemapBundle {
+            grid_size,
+            map_type,
+            size: tilemap_size,
+            storage: decoration_storage,
+            texture: TilemapTexture::Single(tilesheet),
+            tile_size,
+            transform: get_tilemap_center_transform(&tilemap_size, &grid_size, &map_type, -0.7)
+                .with_scale(Vec3::new(TILE_SCALE, TILE_SCALE, 1.)),
+            ..Default::default()
+        },
+        Background,
+        DecorationLayer,
+        Fixed,
+    ));
+
     tilemap_entity
 }
 
+/// Reset the background tiles according to the given level layout.
 pub fn reset_background(
-    mut query_background: Query<(Entity, &mut Transform), With<Background>>,
-    mut query: Query<(&TilemapId, &TilePos, &mut TileTextureIndex)>,
-    upper_tile_index: u32,
-    lower_tile_index: u32,
+    mut query_background: Query<
+        (Entity, &mut Transform, Option<&DecorationLayer>),
+        With<Background>,
+    >,
+    mut query: Query<(
+        &TilemapId,
+        &TilePos,
+        &mut TileTextureIndex,
+        &mut TileVisible,
+    )>,
+    layout: &LevelLayout,
 ) {
-    // traverse the elements of the tilemap, reset texture index
-    let divide = TILE_DIVIDE;
-
-    let background_entity = query_background.get_single().unwrap().0;
-
-    for (tile_pos, mut tile_tex_index) in query
-        .iter_mut()
-        .filter(|(id, ..)| id.0 == background_entity)
-        .map(|(_, pos, tex_i)| (pos, tex_i))
-    {
-        tile_tex_index.0 = if tile_pos.y >= divide {
-            upper_tile_index
-        } else {
-            lower_tile_index
-        };
-    }
-
-    // reset background translation
-    for (_e, mut transform) in &mut query_background {
+    for (background_entity, mut transform, decoration) in &mut query_background {
+        // traverse the elements of the tilemap, reset texture index
+        for (tile_pos, mut tile_tex_index, mut tile_visible) in query
+            .iter_mut()
+            .filter(|(id, ..)| id.0 == background_entity)
+            .map(|(_, pos, tex_i, visible)| (pos, tex_i, visible))
+        {
+            let tile = if decoration.is_some() {
+                layout.decoration_tile(tile_pos.x, tile_pos.y)
+            } else {
+                layout.ground_tile(tile_pos.x, tile_pos.y)
+            };
+            if let Some(tile) = tile {
+                tile_tex_index.0 = tile;
+            }
+            tile_visible.0 = tile.is_some();
+        }
+
+        // reset background translation
         transform.translation.x = 0.;
     }
 }
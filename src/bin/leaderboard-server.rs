@@ -0,0 +1,186 @@
+//! A stand-in for the leaderboard service, for testing the game offline.
+//!
+//! It speaks the protocol described in the game's `leaderboard` module,
+//! keeps submissions in memory, and serves one connection at a time:
+//!
+//! ```sh
+//! cargo run --bin leaderboard-server -- 127.0.0.1:7878
+//! cargo run -- --leaderboard http://127.0.0.1:7878
+//! ```
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use percent_encoding::percent_decode_str;
+use serde_json::Value;
+
+/// The most submissions given out at once
+const MAX_LIMIT: usize = 100;
+
+/// How long a client may take to send its request,
+/// since one idle connection holds up everyone else
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn main() {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:7878".to_string());
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Could not listen on {}: {}", address, e);
+            std::process::exit(1);
+        }
+    };
+    println!("Leaderboard listening on http://{}", address);
+
+    let mut submissions: Vec<Value> = Vec::new();
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| handle(stream, &mut submissions));
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+/// The score of a submission, and how many blasts it took
+fn rank_key(submission: &Value) -> (i64, i64) {
+    let breakdown = &submission["breakdown"];
+    (
+        breakdown["score"].as_i64().unwrap_or(i64::MIN),
+        breakdown["blasts_taken"].as_i64().unwrap_or(0),
+    )
+}
+
+/// The value of a query parameter, decoded
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == name).then(|| percent_decode_str(value).decode_utf8_lossy().into_owned())
+    })
+}
+
+fn handle(stream: TcpStream, submissions: &mut Vec<Value>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let (status, response) = match (method, path) {
+        // preflight from the web build
+        ("OPTIONS", _) => ("204 No Content", String::new()),
+        ("POST", "/scores") => match serde_json::from_slice::<Value>(&body) {
+            Ok(submission) if submission.get("breakdown").is_some() => {
+                println!("{}", submission);
+                submissions.push(submission);
+                // best first, ties going to whoever took fewer blasts
+                submissions.sort_by_key(|submission| {
+                    let (score, blasts) = rank_key(submission);
+                    (std::cmp::Reverse(score), blasts)
+                });
+                ("201 Created", String::new())
+            }
+            _ => ("400 Bad Request", String::new()),
+        },
+        ("GET", "/scores") => {
+            let mode = query_param(query, "mode");
+            let limit = query_param(query, "limit")
+                .and_then(|limit| limit.parse().ok())
+                .unwrap_or(10)
+                .min(MAX_LIMIT);
+            let top: Vec<&Value> = submissions
+                .iter()
+                .filter(|submission| {
+                    mode.as_deref()
+                        .map_or(true, |mode| submission["mode"] == mode)
+                })
+                .take(limit)
+                .collect();
+            ("200 OK", serde_json::to_string(&top).unwrap_or_default())
+        }
+        _ => ("404 Not Found", String::new()),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.0 {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         \r\n{}",
+        status,
+        response.len(),
+        response
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_param_finds_the_value() {
+        let query = "mode=Daily&limit=5";
+        assert_eq!(query_param(query, "mode").as_deref(), Some("Daily"));
+        assert_eq!(query_param(query, "limit").as_deref(), Some("5"));
+    }
+
+    #[test]
+    fn query_param_missing() {
+        assert_eq!(query_param("", "mode"), None);
+        assert_eq!(query_param("limit=5", "mode"), None);
+        // no value given
+        assert_eq!(query_param("mode", "mode"), None);
+        // not a prefix match
+        assert_eq!(query_param("modes=Daily", "mode"), None);
+    }
+
+    #[test]
+    fn query_param_empty_value() {
+        assert_eq!(query_param("mode=&limit=5", "mode").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn query_param_is_decoded() {
+        assert_eq!(
+            query_param("mode=Hot%20Seat", "mode").as_deref(),
+            Some("Hot Seat")
+        );
+    }
+
+    #[test]
+    fn rank_key_of_a_submission() {
+        let submission = serde_json::json!({
+            "breakdown": { "score": 320, "blasts_taken": 2 }
+        });
+        assert_eq!(rank_key(&submission), (320, 2));
+        // malformed submissions sink to the bottom
+        assert_eq!(rank_key(&serde_json::json!({})), (i64::MIN, 0));
+    }
+}
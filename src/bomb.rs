@@ -1,12 +1,15 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, reflect::TypeUuid};
 
 use crate::{
+    accessibility::Urgency,
     animation::{OneShotAnimationTimer, ToggleVisibility},
     audio::{BounceAudio, GameSoundSources},
     events::{BombDisarmedEvent, ExplodedEvent, ExplosiveKind},
     helper::{BaseTranslation, DelayedComponent, TimeToLive},
     movement::{Gravity, SpatialPosition, SpatialVelocity},
     poptext::spawn_popup_text,
+    settings::Settings,
+    targeting::Targetable,
     DefaultFont,
 };
 use bevy::utils::Duration;
@@ -19,10 +22,12 @@ pub struct BombBundle {
     pub bomb_tick: BombTick,
     pub base_translation: BaseTranslation,
     pub sprite_sheet: SpriteSheetBundle,
+    pub targetable: Targetable,
 }
 
 /// Identifies an entity as a bomb and tells its state
-#[derive(Debug, Default, Eq, Hash, PartialEq, Component)]
+#[derive(Debug, Default, Eq, Hash, PartialEq, Component, Reflect)]
+#[reflect(Component)]
 pub enum BombState {
     #[default]
     Idle,
@@ -30,7 +35,8 @@ pub enum BombState {
 }
 
 /// Component that provides bomb ticking feedback
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct BombTick {
     pub ticks_left: u32,
     timer: Timer,
@@ -43,6 +49,11 @@ impl BombTick {
             timer: Timer::from_seconds(1.0, TimerMode::Repeating),
         }
     }
+
+    /// The time left until the bomb explodes, in seconds
+    pub fn time_left(&self) -> f32 {
+        self.ticks_left.saturating_sub(1) as f32 + self.timer.remaining_secs()
+    }
 }
 
 impl Default for BombTick {
@@ -57,6 +68,13 @@ pub struct BombTextureAtlas(Handle<TextureAtlas>);
 #[derive(Debug, Resource, Deref)]
 pub struct BombExplosionTextureAtlas(Handle<TextureAtlas>);
 
+/// The sprite sheets are kept under fixed handles,
+/// so that snapshots of the play field can refer to them
+const BOMB_TEXTURE_ATLAS: HandleUntyped =
+    HandleUntyped::weak_from_u64(TextureAtlas::TYPE_UUID, 0x2d8b_51f0_c63a_e914);
+const BOMB_EXPLOSION_TEXTURE_ATLAS: HandleUntyped =
+    HandleUntyped::weak_from_u64(TextureAtlas::TYPE_UUID, 0x9e47_0a6c_1bd3_5f82);
+
 /// system: setup
 pub fn setup(
     mut commands: Commands,
@@ -66,7 +84,7 @@ pub fn setup(
     // load bomb spritesheet
     let tex_bomb: Handle<Image> = asset_server.load("img/tnt.png");
     let atlas = TextureAtlas::from_grid(tex_bomb, Vec2::new(30.0, 18.0), 4, 1, None, None);
-    let bomb_texture_atlas_handle = texture_atlases.add(atlas);
+    let bomb_texture_atlas_handle = texture_atlases.set(BOMB_TEXTURE_ATLAS, atlas);
 
     // save as resource for later
     commands.insert_resource(BombTextureAtlas(bomb_texture_atlas_handle.clone()));
@@ -74,7 +92,7 @@ pub fn setup(
     // load bomb explosion spritesheet
     let tex_explosion: Handle<Image> = asset_server.load("img/Explosion.png");
     let atlas = TextureAtlas::from_grid(tex_explosion, Vec2::new(96.0, 96.0), 12, 1, None, None);
-    let explosion_texture_atlas_handle = texture_atlases.add(atlas);
+    let explosion_texture_atlas_handle = texture_atlases.set(BOMB_EXPLOSION_TEXTURE_ATLAS, atlas);
 
     // save as resource for later
     commands.insert_resource(BombExplosionTextureAtlas(explosion_texture_atlas_handle));
@@ -118,6 +136,7 @@ pub fn bomb_tick(
     time: Res<Time>,
     audio: Res<Audio>,
     default_font: Res<DefaultFont>,
+    settings: Res<Settings>,
     mut query: Query<(
         Entity,
         &BombState,
@@ -142,11 +161,13 @@ pub fn bomb_tick(
             // TODO sound effect
 
             // popup text with time left
-            let color = match bomb_tick.ticks_left {
-                0 => Color::rgba_u8(0xFF, 0x20, 0x00, 0xFF),
-                1..=3 => Color::rgba_u8(0xF0, 0xA7, 0x10, 0xFF),
-                _ => Color::rgba_u8(0xFF, 0xFF, 0x20, 0xFF),
-            };
+            let urgency = Urgency::from_ticks_left(bomb_tick.ticks_left);
+            let color = settings.palette.urgency_color(urgency);
+            let (text, font_size) = urgency.popup(
+                settings.palette,
+                bomb_tick.ticks_left.to_string(),
+                28. * settings.text_scale(),
+            );
 
             if bomb_tick.ticks_left == 0 {
                 explode(
@@ -164,8 +185,8 @@ pub fn bomb_tick(
                 &mut commands,
                 default_font.0.clone(),
                 transform.translation.truncate(),
-                bomb_tick.ticks_left.to_string(),
-                28.,
+                text,
+                font_size,
                 color,
             );
         }
@@ -180,7 +201,7 @@ pub fn on_disarm_bomb(
     mut event_reader: EventReader<BombDisarmedEvent>,
     mut query: Query<(&mut BombState, &mut TextureAtlasSprite)>,
 ) {
-    for BombDisarmedEvent(bomb_entity) in event_reader.iter() {
+    for BombDisarmedEvent(bomb_entity, _) in event_reader.iter() {
         // find bomb by ID
         match query.get_mut(*bomb_entity) {
             Ok((mut state, mut sprite)) => {
@@ -6,12 +6,16 @@ use crate::events::CoffeePickedUpEvent;
 use crate::guy::{GuyPerformance, GuyState, GUY_BASE_PERFORMANCE, GUY_BASE_SPEED};
 use crate::helper::BaseTranslation;
 use crate::helper::{DelayedComponent, TimeToLive};
+use crate::i18n::LocalizedText;
 use crate::movement::{Gravity, MaxSpeed, SpatialPosition, SpatialVelocity};
+use crate::particles::{ParticleEffect, ParticleEmitter};
+use crate::targeting::Targetable;
 use crate::{poptext, DefaultFont};
 use bevy::prelude::*;
 use bevy::utils::Duration;
 
-#[derive(Default, Component)]
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct Coffee;
 
 #[derive(Default, Bundle)]
@@ -22,6 +26,7 @@ pub struct CoffeeBundle {
     pub velocity: SpatialVelocity,
     pub base_translation: BaseTranslation,
     pub sprite: SpriteBundle,
+    pub targetable: Targetable,
 }
 
 #[derive(Resource, Deref)]
@@ -67,11 +72,17 @@ pub fn spawn_coffee(
             Gravity::default(),
             // blink shortly before it disappears
             DelayedComponent::new(ToggleVisibility::default(), Duration::from_millis(3_000)),
+            // hot coffee!
+            ParticleEmitter::new(ParticleEffect::Steam, Vec3::new(0., 0., 16.)),
         ))
         .id()
 }
 
-/// system: if guy is close to coffee, drink it
+/// How close the guy's feet must get to a coffee to drink it
+pub const DIST_REACH: f32 = 12.;
+const DIST_SQR_REACH: f32 = DIST_REACH * DIST_REACH;
+
+/// system: if a guy is close to coffee, drink it
 pub fn detect_guy_touch_coffee(
     mut commands: Commands,
     font: Res<DefaultFont>,
@@ -89,65 +100,71 @@ pub fn detect_guy_touch_coffee(
     query_coffee: Query<(Entity, &SpatialPosition), With<Coffee>>,
     mut event_writer: EventWriter<CoffeePickedUpEvent>,
 ) {
-    let Ok((guy_entity,
+    // coffees already drunk by another guy in this frame
+    let mut taken: Vec<Entity> = vec![];
+
+    for (
+        guy_entity,
         mut guy_perf,
         mut max_speed,
         guy_state,
         guy_pos,
         base_translation,
-        coffee_effect)
-    ) = query_guy.get_single_mut() else {
-        return;
-    };
-
-    // do not pick up coffee in these states
-    if matches!(
-        guy_state,
-        GuyState::Disarming { .. } | GuyState::Ouch | GuyState::Victorious
-    ) {
-        return;
-    }
-
-    const DIST_REACH: f32 = 12.;
-    const DIST_SQR_REACH: f32 = DIST_REACH * DIST_REACH;
-
-    for (entity, pos) in &query_coffee {
-        let dist_sqr = (guy_pos.0 + base_translation.0.extend(0.)).distance_squared(pos.0);
-        if dist_sqr <= DIST_SQR_REACH {
-            // grab it!
-            event_writer.send(CoffeePickedUpEvent(entity));
-
-            // emit sound effect
-            audio.play(sound_sources.drink.cast_weak());
+        coffee_effect,
+    ) in &mut query_guy
+    {
+        // do not pick up coffee in these states
+        if matches!(
+            guy_state,
+            GuyState::Disarming { .. } | GuyState::Ouch | GuyState::Victorious
+        ) {
+            continue;
+        }
 
-            // if guy is not under the effect of coffee yet
-            if coffee_effect.is_none() {
-                // apply coffee effect
-                guy_perf.0 += GUY_BASE_PERFORMANCE;
-                max_speed.0 += GUY_BASE_SPEED / 2.;
+        for (entity, pos) in &query_coffee {
+            if taken.contains(&entity) {
+                continue;
+            }
+            let dist_sqr = (guy_pos.0 + base_translation.0.extend(0.)).distance_squared(pos.0);
+            if dist_sqr <= DIST_SQR_REACH {
+                // grab it!
+                taken.push(entity);
+                event_writer.send(CoffeePickedUpEvent(entity));
+
+                // emit sound effect
+                audio.play(sound_sources.drink.cast_weak());
+
+                // if guy is not under the effect of coffee yet
+                if coffee_effect.is_none() {
+                    // apply coffee effect
+                    guy_perf.0 += GUY_BASE_PERFORMANCE;
+                    max_speed.0 += GUY_BASE_SPEED / 2.;
+                }
+
+                // insert coffee effec component so that it wears out
+                commands
+                    .entity(guy_entity)
+                    .insert(CoffeeEffect::new(Duration::from_secs(9)));
+
+                let text = poptext::spawn_popup_text(
+                    &mut commands,
+                    font.0.clone(),
+                    pos.0.truncate(),
+                    "",
+                    16.,
+                    Color::WHITE,
+                );
+                commands.entity(text).insert(LocalizedText::new("coffee"));
+
+                commands.entity(entity).despawn();
             }
-
-            // insert coffee effec component so that it wears out
-            commands
-                .entity(guy_entity)
-                .insert(CoffeeEffect::new(Duration::from_secs(9)));
-
-            poptext::spawn_popup_text(
-                &mut commands,
-                font.0.clone(),
-                pos.0.truncate(),
-                "COFFEE!",
-                16.,
-                Color::WHITE,
-            );
-
-            commands.entity(entity).despawn();
         }
     }
 }
 
 /// Component applied on guy after drinking coffee
-#[derive(Component)]
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct CoffeeEffect {
     timer: Timer,
 }
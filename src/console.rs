@@ -0,0 +1,527 @@
+//! a developer console with cheats for testing, opened with the backtick key.
+//!
+//! The commands are:
+//! - `spawn bomb <x> <y> <secs>`: drop a bomb there, going off after so many seconds;
+//! - `spawn dynamite [<x> <y>]`: drop a dynamite there, or throw one as the spawners do;
+//! - `wave <n>`: skip to that wave, dropping whatever was left to spawn;
+//! - `god`: turn on or off whether explosions hurt the guys;
+//! - `timescale <f>`: play slower or faster, 1 being normal speed;
+//! - `seed <n>`: reseed the run, or the next one outside of a game;
+//! - `score <n>`: set the score.
+//!
+//! Commands can also be scripted, one per line,
+//! in a file given with `--console-script <file>`
+//! (blank lines and lines starting with `#` are skipped).
+//! They run in order, and those which need the play field
+//! wait for a game to be started.
+//!
+//! A run in which any command was run, or played in god mode
+//! or at another speed, counts as assisted.
+//!
+//! Only compiled in with the `console` cargo feature.
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use bevy::{ecs::system::CommandQueue, hierarchy::despawn_with_children_recursive, prelude::*};
+
+use crate::{
+    achievements::RunRecord,
+    audio::GameSoundSources,
+    bomb::BombTextureAtlas,
+    dynamite::DynamiteTextureAtlas,
+    events::DynamiteThrownEvent,
+    i18n::LocalizedText,
+    ingame::{Wave, WaveUi},
+    level::{CurrentLevel, LevelLayouts, LevelObstacle},
+    obstacle::Obstacle,
+    scores::{GameScoreUi, GameScores},
+    spawner::{NextSeed, PendingThrow, Rng, RunSeed, Spawner},
+    waves::{wave_level, wave_text, AwaitedWave, WaveFinished, WaveList},
+    AppState, DefaultFont,
+};
+
+/// How many lines of past commands are shown
+const LOG_LINES: usize = 8;
+
+/// The height from which things spawned from the console are dropped
+const DROP_HEIGHT: f32 = 80.;
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_startup_system(load_script)
+            .add_system(type_input)
+            .add_system(run_commands.after(type_input))
+            .add_system(show_panel.after(run_commands))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame).with_system(mark_lasting_cheats),
+            );
+    }
+}
+
+/// The state of the console
+#[derive(Debug, Default, Resource)]
+pub struct Console {
+    open: bool,
+    /// the command being typed
+    input: String,
+    /// the commands run, with their outcome
+    log: Vec<String>,
+    /// the commands yet to be run, in order
+    pending: VecDeque<String>,
+}
+
+impl Console {
+    fn log(&mut self, line: String) {
+        info!("console: {}", line);
+        self.log.push(line);
+        if self.log.len() > LOG_LINES {
+            self.log.remove(0);
+        }
+    }
+}
+
+/// When present, explosions do not hurt the guys
+#[derive(Debug, Resource)]
+pub struct GodMode;
+
+/// When present, time runs faster or slower than normal
+#[derive(Debug, Resource)]
+pub struct TimeScaled;
+
+/// Marker for the panel of the console
+#[derive(Debug, Component)]
+pub struct ConsolePanel;
+
+/// A command given to the console
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Command {
+    SpawnBomb {
+        pos: Vec2,
+        seconds: u32,
+    },
+    /// dropped at the given position, or thrown at random
+    SpawnDynamite(Option<Vec2>),
+    Wave(u16),
+    God,
+    TimeScale(f32),
+    Seed(u64),
+    Score(i32),
+}
+
+/// Parse the next word as the argument with the given name
+fn arg<'a, T: FromStr>(words: &mut impl Iterator<Item = &'a str>, name: &str) -> Result<T, String> {
+    let word = words.next().ok_or_else(|| format!("missing <{}>", name))?;
+    word.parse()
+        .map_err(|_| format!("bad <{}>: `{}`", name, word))
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = match words.next().unwrap_or_default() {
+            "spawn" => match words.next() {
+                Some("bomb") => {
+                    let pos = Vec2::new(arg(&mut words, "x")?, arg(&mut words, "y")?);
+                    let seconds = arg(&mut words, "secs")?;
+                    Command::SpawnBomb { pos, seconds }
+                }
+                Some("dynamite") => {
+                    let mut words = words.by_ref().peekable();
+                    if words.peek().is_some() {
+                        let pos = Vec2::new(arg(&mut words, "x")?, arg(&mut words, "y")?);
+                        Command::SpawnDynamite(Some(pos))
+                    } else {
+                        Command::SpawnDynamite(None)
+                    }
+                }
+                _ => return Err("spawn either `bomb` or `dynamite`".to_string()),
+            },
+            "wave" => Command::Wave(arg(&mut words, "n")?),
+            "god" => Command::God,
+            "timescale" => {
+                let scale: f32 = arg(&mut words, "f")?;
+                if !scale.is_finite() || scale < 0. {
+                    return Err(format!("bad <f>: `{}`", scale));
+                }
+                Command::TimeScale(scale)
+            }
+            "seed" => Command::Seed(arg(&mut words, "n")?),
+            "score" => Command::Score(arg(&mut words, "n")?),
+            word => return Err(format!("unknown command `{}`", word)),
+        };
+        match words.next() {
+            Some(word) => Err(format!("unexpected `{}`", word)),
+            None => Ok(command),
+        }
+    }
+}
+
+impl Command {
+    /// Whether the command can only be run while playing
+    fn needs_game(&self) -> bool {
+        !matches!(
+            self,
+            Command::God | Command::TimeScale(_) | Command::Seed(_)
+        )
+    }
+}
+
+/// Run something which needs commands on the world right away
+fn with_commands(world: &mut World, f: impl FnOnce(Commands)) {
+    let mut queue = CommandQueue::default();
+    f(Commands::new(&mut queue, world));
+    queue.apply(world);
+}
+
+/// Run the command, telling how it went
+fn execute(world: &mut World, command: Command, in_game: bool) -> Result<String, String> {
+    if in_game {
+        world.resource_mut::<RunRecord>().assisted = true;
+    }
+    match command {
+        Command::SpawnBomb { pos, seconds } => {
+            let texture_atlas = (**world.resource::<BombTextureAtlas>()).clone();
+            let bounce_sound = world.resource::<GameSoundSources>().thwack10.clone();
+            with_commands(world, |mut commands| {
+                crate::bomb::spawn_bomb(
+                    &mut commands,
+                    texture_atlas,
+                    bounce_sound,
+                    pos.extend(DROP_HEIGHT),
+                    Vec3::ZERO,
+                    seconds,
+                );
+            });
+            Ok(format!("bomb at {}, {}", pos.x, pos.y))
+        }
+        Command::SpawnDynamite(None) => {
+            world
+                .resource_mut::<Events<DynamiteThrownEvent>>()
+                .send(DynamiteThrownEvent);
+            Ok("dynamite thrown".to_string())
+        }
+        Command::SpawnDynamite(Some(pos)) => {
+            let texture_atlas = world.resource::<DynamiteTextureAtlas>().0.clone();
+            let bounce_sound = world.resource::<GameSoundSources>().thwack3.clone();
+            with_commands(world, |mut commands| {
+                crate::dynamite::spawn_dynamite(
+                    &mut commands,
+                    texture_atlas,
+                    bounce_sound,
+                    pos.extend(DROP_HEIGHT),
+                    Vec3::ZERO,
+                );
+            });
+            Ok(format!("dynamite at {}, {}", pos.x, pos.y))
+        }
+        Command::Wave(wave) => {
+            let waves = world.resource::<WaveList>();
+            let wave_count = waves.count();
+            let Some(descriptor) = waves.get(wave).cloned() else {
+                return Err(format!("there are only waves 0 to {}", wave_count - 1));
+            };
+            // whatever the current wave had left is dropped
+            let mut query = world.query_filtered::<Entity, Or<(
+                With<Spawner>,
+                With<PendingThrow>,
+                With<WaveFinished>,
+                With<AwaitedWave>,
+            )>>();
+            let leftovers: Vec<Entity> = query.iter(world).collect();
+            for entity in leftovers {
+                world.despawn(entity);
+            }
+            // and so are its obstacles, as the new wave brings its own level
+            let mut query_obstacles =
+                world.query_filtered::<Entity, (With<Obstacle>, Without<LevelObstacle>)>();
+            let obstacles: Vec<Entity> = query_obstacles.iter(world).collect();
+            for entity in obstacles {
+                despawn_with_children_recursive(world, entity);
+            }
+            let layout = world.resource::<LevelLayouts>().get(wave_level(wave));
+            world.insert_resource(CurrentLevel(layout));
+
+            world.resource_mut::<Wave>().0 = wave;
+            let mut query_wave_ui = world.query_filtered::<&mut LocalizedText, With<WaveUi>>();
+            if let Ok(mut wave_ui_text) = query_wave_ui.get_single_mut(world) {
+                *wave_ui_text = wave_text(wave, wave_count);
+            }
+            with_commands(world, |commands| descriptor.spawn(commands));
+            Ok(format!("on to wave {}", wave))
+        }
+        Command::God => {
+            if world.remove_resource::<GodMode>().is_some() {
+                Ok("god mode off".to_string())
+            } else {
+                world.insert_resource(GodMode);
+                Ok("god mode on".to_string())
+            }
+        }
+        Command::TimeScale(scale) => {
+            world.resource_mut::<Time>().set_relative_speed(scale);
+            if scale == 1. {
+                world.remove_resource::<TimeScaled>();
+            } else {
+                world.insert_resource(TimeScaled);
+            }
+            Ok(format!("time at {}x", scale))
+        }
+        Command::Seed(seed) => {
+            if in_game {
+                world.insert_resource(Rng::from_seed(seed));
+                world.resource_mut::<RunSeed>().seed = seed;
+                Ok(format!("run reseeded with {}", seed))
+            } else {
+                world.insert_resource(NextSeed(seed));
+                Ok(format!("next run seeded with {}", seed))
+            }
+        }
+        Command::Score(score) => {
+            world.resource_mut::<GameScores>().score = score;
+            let mut query_score_ui = world.query_filtered::<&mut Text, With<GameScoreUi>>();
+            for mut text in query_score_ui.iter_mut(world) {
+                text.sections[0].value = score.to_string();
+            }
+            Ok(format!("score set to {}", score))
+        }
+    }
+}
+
+/// system: queue the commands of the script given at startup, if any
+pub fn load_script(mut console: ResMut<Console>) {
+    let mut args = std::env::args().skip_while(|arg| arg != "--console-script");
+    let Some(path) = args.nth(1) else {
+        return;
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(script) => {
+            let lines = script
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from);
+            console.pending.extend(lines);
+        }
+        Err(e) => warn!("Could not read console script `{}`: {}", path, e),
+    }
+}
+
+/// system: open or close the console, and type commands into it
+pub fn type_input(
+    keyboard: Res<Input<KeyCode>>,
+    mut console: ResMut<Console>,
+    mut char_reader: EventReader<ReceivedCharacter>,
+) {
+    if keyboard.just_pressed(KeyCode::Grave) {
+        console.open = !console.open;
+        char_reader.clear();
+        return;
+    }
+    if !console.open {
+        char_reader.clear();
+        return;
+    }
+
+    for ReceivedCharacter { char, .. } in char_reader.iter() {
+        if !char.is_control() && *char != '`' {
+            console.input.push(*char);
+        }
+    }
+    if keyboard.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if keyboard.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input);
+        let line = line.trim();
+        if !line.is_empty() {
+            console.pending.push_back(line.to_string());
+        }
+    }
+}
+
+/// system: run the pending commands, as far as they can be run
+pub fn run_commands(world: &mut World) {
+    let in_game = *world.resource::<State<AppState>>().current() == AppState::InGame;
+
+    loop {
+        let Some(line) = world.resource::<Console>().pending.front().cloned() else {
+            return;
+        };
+        let command = line.parse::<Command>();
+        // wait for a game to be started
+        if matches!(&command, Ok(command) if command.needs_game() && !in_game) {
+            return;
+        }
+        world.resource_mut::<Console>().pending.pop_front();
+        let outcome = command.and_then(|command| execute(world, command, in_game));
+
+        let mut console = world.resource_mut::<Console>();
+        match outcome {
+            Ok(message) => console.log(format!("> {}: {}", line, message)),
+            Err(message) => console.log(format!("> {}: error, {}", line, message)),
+        }
+    }
+}
+
+/// system: count the run as assisted while cheats set outside of it last
+pub fn mark_lasting_cheats(
+    god_mode: Option<Res<GodMode>>,
+    time_scaled: Option<Res<TimeScaled>>,
+    mut record: ResMut<RunRecord>,
+) {
+    if (god_mode.is_some() || time_scaled.is_some()) && !record.assisted {
+        record.assisted = true;
+    }
+}
+
+/// system: keep the panel up to date, or remove it when closed
+pub fn show_panel(
+    mut commands: Commands,
+    console: Res<Console>,
+    default_font: Option<Res<DefaultFont>>,
+    mut query_panel: Query<(Entity, &mut Text), With<ConsolePanel>>,
+) {
+    if !console.open {
+        for (entity, _) in &query_panel {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let mut lines = console.log.clone();
+    lines.extend(
+        console
+            .pending
+            .iter()
+            .map(|line| format!("(waiting) {}", line)),
+    );
+    lines.push(format!("> {}_", console.input));
+    let value = lines.join("\n");
+    if let Ok((_, mut text)) = query_panel.get_single_mut() {
+        text.sections[0].value = value;
+        return;
+    }
+    // the panel goes away with everything else when the screen changes
+    let Some(font) = default_font else {
+        return;
+    };
+    commands.spawn((
+        TextBundle::from_section(
+            value,
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 14.,
+                color: Color::rgb(1., 0.9, 0.5),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(12.),
+                left: Val::Px(12.),
+                ..default()
+            },
+            ..default()
+        }),
+        BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+        ConsolePanel,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(
+            "spawn bomb 120 200.5 4".parse(),
+            Ok(Command::SpawnBomb {
+                pos: Vec2::new(120., 200.5),
+                seconds: 4,
+            })
+        );
+        assert_eq!("spawn dynamite".parse(), Ok(Command::SpawnDynamite(None)));
+        assert_eq!(
+            "spawn dynamite 10 20".parse(),
+            Ok(Command::SpawnDynamite(Some(Vec2::new(10., 20.))))
+        );
+        assert_eq!("wave 3".parse(), Ok(Command::Wave(3)));
+        assert_eq!("god".parse(), Ok(Command::God));
+        assert_eq!("timescale 0.5".parse(), Ok(Command::TimeScale(0.5)));
+        assert_eq!("seed 42".parse(), Ok(Command::Seed(42)));
+        assert_eq!("score -12".parse(), Ok(Command::Score(-12)));
+    }
+
+    #[test]
+    fn parse_ignores_extra_whitespace() {
+        assert_eq!("  wave   2 ".parse(), Ok(Command::Wave(2)));
+    }
+
+    #[test]
+    fn parse_missing_args() {
+        assert_eq!(
+            "spawn bomb 1 2".parse::<Command>(),
+            Err("missing <secs>".to_string())
+        );
+        assert_eq!(
+            "spawn dynamite 10".parse::<Command>(),
+            Err("missing <y>".to_string())
+        );
+        assert_eq!("wave".parse::<Command>(), Err("missing <n>".to_string()));
+        assert_eq!(
+            "timescale".parse::<Command>(),
+            Err("missing <f>".to_string())
+        );
+        assert!("spawn".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn parse_extra_args() {
+        assert_eq!(
+            "god mode".parse::<Command>(),
+            Err("unexpected `mode`".to_string())
+        );
+        assert_eq!(
+            "wave 3 4".parse::<Command>(),
+            Err("unexpected `4`".to_string())
+        );
+        assert_eq!(
+            "spawn dynamite 1 2 3".parse::<Command>(),
+            Err("unexpected `3`".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_bad_args() {
+        assert_eq!(
+            "wave three".parse::<Command>(),
+            Err("bad <n>: `three`".to_string())
+        );
+        assert_eq!(
+            "wave -1".parse::<Command>(),
+            Err("bad <n>: `-1`".to_string())
+        );
+        assert!("timescale -2".parse::<Command>().is_err());
+        assert!("timescale NaN".parse::<Command>().is_err());
+        assert_eq!(
+            "dance".parse::<Command>(),
+            Err("unknown command `dance`".to_string())
+        );
+        assert_eq!("".parse::<Command>(), Err("unknown command ``".to_string()));
+    }
+
+    #[test]
+    fn commands_outside_of_a_game() {
+        assert!(!Command::God.needs_game());
+        assert!(!Command::Seed(1).needs_game());
+        assert!(Command::Wave(1).needs_game());
+        assert!(Command::Score(0).needs_game());
+    }
+}
@@ -0,0 +1,460 @@
+//! daily challenge: one run a day, the same for everyone.
+//!
+//! The run's seed and its gameplay modifiers are derived from the date alone
+//! (in UTC, so that the day turns at the same time everywhere),
+//! which takes no server to agree on.
+//! Only the first attempt of the day is scored,
+//! and the scores of past days are kept as a history.
+//! Any further attempts on the same day are practice.
+
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    achievements::RunRecord,
+    audio::GameSoundSources,
+    guy::{GuyPerformance, Player, GUY_BASE_PERFORMANCE, GUY_BASE_SPEED},
+    i18n::LocalizedText,
+    ingame::{GameMode, Wave},
+    menu::{fade_to, spawn_back_button, spawn_text, HOVER_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON},
+    movement::MaxSpeed,
+    scores::GameScores,
+    spawner::{NextSeed, Rng},
+    AppState, DefaultFont, DelayedStateChange,
+};
+
+/// The key under which the history of daily results is stored
+const DAILY_KEY: &str = "daily";
+
+/// How many modifiers a daily challenge has
+const MODIFIERS_PER_DAY: usize = 2;
+
+/// How many past days are shown on the daily challenge screen
+const HISTORY_SHOWN: usize = 7;
+
+/// A change to the rules of a run
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Modifier {
+    /// bombs start with fewer ticks left
+    ShortFuses,
+    /// no coffee is thrown
+    NoCoffee,
+    /// the guy walks slower
+    HeavyBoots,
+    /// the guy disarms slower
+    ShakyHands,
+}
+
+impl Modifier {
+    const ALL: [Modifier; 4] = [
+        Modifier::ShortFuses,
+        Modifier::NoCoffee,
+        Modifier::HeavyBoots,
+        Modifier::ShakyHands,
+    ];
+
+    /// The key of the modifier's description in the string tables
+    fn name_key(self) -> &'static str {
+        match self {
+            Modifier::ShortFuses => "modifier_short_fuses",
+            Modifier::NoCoffee => "modifier_no_coffee",
+            Modifier::HeavyBoots => "modifier_heavy_boots",
+            Modifier::ShakyHands => "modifier_shaky_hands",
+        }
+    }
+}
+
+/// The modifiers in play in the current run, if any
+#[derive(Debug, Default, Clone, PartialEq, Resource, Serialize, Deserialize)]
+pub struct Modifiers(pub Vec<Modifier>);
+
+impl Modifiers {
+    pub fn contains(&self, modifier: Modifier) -> bool {
+        self.0.contains(&modifier)
+    }
+}
+
+/// Days since 1970-01-01, in UTC
+#[cfg(not(target_arch = "wasm32"))]
+fn days_since_epoch() -> i64 {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    (secs / 86_400) as i64
+}
+
+/// Days since 1970-01-01, in UTC
+#[cfg(target_arch = "wasm32")]
+fn days_since_epoch() -> i64 {
+    (js_sys::Date::now() / 86_400_000.).floor() as i64
+}
+
+/// The date of a day since 1970-01-01, as year-month-day
+fn format_day(day: i64) -> String {
+    // days to civil date, after Howard Hinnant
+    let z = day + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + (m <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// The seed of a day's challenge (SplitMix64 of the day)
+fn seed_for_day(day: i64) -> u64 {
+    let mut z = (day as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// The challenge of one day
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyChallenge {
+    /// days since 1970-01-01
+    pub day: i64,
+    pub seed: u64,
+    pub modifiers: Modifiers,
+}
+
+impl DailyChallenge {
+    pub fn for_day(day: i64) -> Self {
+        let seed = seed_for_day(day);
+        let mut rng = Rng::from_seed(seed);
+        let mut modifiers: Vec<_> = Modifier::ALL
+            .choose_multiple(rng.stream("daily_modifiers"), MODIFIERS_PER_DAY)
+            .copied()
+            .collect();
+        modifiers.sort();
+        DailyChallenge {
+            day,
+            seed,
+            modifiers: Modifiers(modifiers),
+        }
+    }
+
+    pub fn today() -> Self {
+        Self::for_day(days_since_epoch())
+    }
+}
+
+/// How the scored attempt of one day went
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyResult {
+    /// days since 1970-01-01
+    pub day: i64,
+    pub score: i32,
+    /// the last wave reached
+    pub wave: u16,
+}
+
+/// The results of all daily challenges played, oldest first
+#[derive(Debug, Default, Clone, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyHistory {
+    pub results: Vec<DailyResult>,
+}
+
+impl DailyHistory {
+    /// Load the history of daily results from storage
+    pub fn load() -> Self {
+        crate::storage::load(DAILY_KEY).unwrap_or_default()
+    }
+
+    /// The result of the given day, if it was attempted
+    pub fn result(&self, day: i64) -> Option<&DailyResult> {
+        self.results.iter().find(|result| result.day == day)
+    }
+}
+
+/// A daily challenge being played
+#[derive(Debug, Copy, Clone, Resource, Serialize, Deserialize)]
+pub struct DailyRun {
+    pub day: i64,
+    /// whether this is the scored attempt of the day
+    pub scored: bool,
+}
+
+/// system: take up the scored attempt of the day as the run begins,
+/// so that leaving it early does not bring it back
+pub fn start_run(daily_run: Option<Res<DailyRun>>, mut history: ResMut<DailyHistory>) {
+    let Some(daily_run) = daily_run else {
+        return;
+    };
+    if !daily_run.scored || history.result(daily_run.day).is_some() {
+        return;
+    }
+    history.results.push(DailyResult {
+        day: daily_run.day,
+        ..default()
+    });
+    crate::storage::save(DAILY_KEY, &*history);
+}
+
+/// system: apply the modifiers of the run to the guys as they come in
+pub fn apply_modifiers(
+    modifiers: Res<Modifiers>,
+    mut query: Query<(&mut MaxSpeed, &mut GuyPerformance), Added<Player>>,
+) {
+    for (mut max_speed, mut performance) in &mut query {
+        if modifiers.contains(Modifier::HeavyBoots) {
+            max_speed.0 -= GUY_BASE_SPEED * 0.2;
+        }
+        if modifiers.contains(Modifier::ShakyHands) {
+            performance.0 -= GUY_BASE_PERFORMANCE * 0.2;
+        }
+    }
+}
+
+/// system: once a daily run is left, record its score if it was the scored one,
+/// and go back to the usual rules
+pub fn finish_run(
+    mut commands: Commands,
+    daily_run: Option<Res<DailyRun>>,
+    mut history: ResMut<DailyHistory>,
+    (scores, wave, record): (Res<GameScores>, Res<Wave>, Res<RunRecord>),
+) {
+    commands.insert_resource(Modifiers::default());
+    let Some(daily_run) = daily_run else {
+        return;
+    };
+    commands.remove_resource::<DailyRun>();
+    if !daily_run.scored {
+        return;
+    }
+
+    let Some(result) = history
+        .results
+        .iter_mut()
+        .find(|result| result.day == daily_run.day)
+    else {
+        return;
+    };
+    // points made by the autopilot do not count
+    result.score = if record.assisted { 0 } else { scores.score };
+    result.wave = wave.0;
+    crate::storage::save(DAILY_KEY, &*history);
+}
+
+/// The button starting today's challenge
+#[derive(Debug, Component)]
+pub struct PlayDailyButton;
+
+/// system: show today's challenge and the results of past days
+pub fn setup(mut commands: Commands, font: Res<DefaultFont>, history: Res<DailyHistory>) {
+    // UI camera
+    commands.spawn(crate::screen::camera_bundle());
+
+    let font = font.0.clone();
+    let challenge = DailyChallenge::today();
+    let today = history.result(challenge.day);
+    let dim = Color::rgb(0.85, 0.85, 0.85);
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            spawn_text(
+                parent,
+                &font,
+                36.,
+                Color::WHITE,
+                LocalizedText::new("daily"),
+            );
+            spawn_text(
+                parent,
+                &font,
+                20.,
+                dim,
+                LocalizedText::new("daily_date").with_arg("date", format_day(challenge.day)),
+            );
+            for modifier in &challenge.modifiers.0 {
+                spawn_text(
+                    parent,
+                    &font,
+                    18.,
+                    Color::rgb(1., 0.85, 0.3),
+                    LocalizedText::new(modifier.name_key()),
+                );
+            }
+
+            if let Some(today) = today {
+                spawn_text(
+                    parent,
+                    &font,
+                    18.,
+                    dim,
+                    LocalizedText::new("daily_done").with_arg("score", today.score),
+                );
+            }
+
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(220.0), Val::Px(46.0)),
+                            margin: UiRect {
+                                top: Val::Px(8.),
+                                bottom: Val::Px(8.),
+                                ..default()
+                            },
+                            // horizontally center child text
+                            justify_content: JustifyContent::Center,
+                            // vertically center child text
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: NORMAL_BUTTON.into(),
+                        ..default()
+                    },
+                    PlayDailyButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 28.0,
+                                color: Color::rgba(1., 1., 0.8, 1.0),
+                            },
+                        ),
+                        LocalizedText::new(if today.is_some() {
+                            "daily_practice"
+                        } else {
+                            "daily_play"
+                        }),
+                    ));
+                });
+
+            // the most recent days first
+            let past = history
+                .results
+                .iter()
+                .rev()
+                .filter(|result| result.day != challenge.day)
+                .take(HISTORY_SHOWN);
+            for result in past {
+                spawn_text(
+                    parent,
+                    &font,
+                    16.,
+                    dim,
+                    LocalizedText::new("daily_result")
+                        .with_arg("date", format_day(result.day))
+                        .with_arg("score", result.score)
+                        .with_arg("wave", result.wave),
+                );
+            }
+
+            spawn_back_button(parent, font.clone());
+        });
+}
+
+/// interactions with the button starting today's challenge
+type PlayDailyButtonChanged = (Changed<Interaction>, With<PlayDailyButton>);
+
+pub fn button_system(
+    mut commands: Commands,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor), PlayDailyButtonChanged>,
+    history: Res<DailyHistory>,
+    audio: Res<Audio>,
+    sound_sources: Res<GameSoundSources>,
+    transition_entity: Query<Entity, With<DelayedStateChange>>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+
+                audio.play(sound_sources.click.cast_weak());
+
+                // ensure that we don't spawn more than one
+                if transition_entity.get_single().is_err() {
+                    let challenge = DailyChallenge::today();
+                    commands.insert_resource(GameMode::Daily);
+                    commands.insert_resource(NextSeed(challenge.seed));
+                    commands.insert_resource(DailyRun {
+                        day: challenge.day,
+                        scored: history.result(challenge.day).is_none(),
+                    });
+                    commands.insert_resource(challenge.modifiers);
+                    fade_to(&mut commands, AppState::InGame);
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVER_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_day_around_the_epoch() {
+        assert_eq!(format_day(0), "1970-01-01");
+        assert_eq!(format_day(1), "1970-01-02");
+        assert_eq!(format_day(-1), "1969-12-31");
+    }
+
+    #[test]
+    fn format_day_on_leap_days() {
+        assert_eq!(format_day(11_016), "2000-02-29");
+        assert_eq!(format_day(11_017), "2000-03-01");
+        assert_eq!(format_day(19_782), "2024-02-29");
+        // centuries are not leap years, unless divisible by 400
+        assert_eq!(format_day(-25_509), "1900-02-28");
+        assert_eq!(format_day(-25_508), "1900-03-01");
+        assert_eq!(format_day(47_541), "2100-03-01");
+    }
+
+    #[test]
+    fn seed_for_day_is_splitmix64() {
+        assert_eq!(seed_for_day(0), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(seed_for_day(1), 0x910a_2dec_8902_5cc1);
+    }
+
+    #[test]
+    fn challenge_is_the_same_all_day() {
+        let challenge = DailyChallenge::for_day(19_782);
+        assert_eq!(challenge, DailyChallenge::for_day(19_782));
+        assert_eq!(challenge.day, 19_782);
+        assert_eq!(challenge.seed, seed_for_day(19_782));
+    }
+
+    #[test]
+    fn challenge_modifiers_are_distinct_and_sorted() {
+        for day in -3..30 {
+            let modifiers = DailyChallenge::for_day(day).modifiers.0;
+            assert_eq!(modifiers.len(), MODIFIERS_PER_DAY);
+            assert!(modifiers.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[test]
+    fn challenges_change_from_day_to_day() {
+        let first = DailyChallenge::for_day(0);
+        assert_ne!(first.seed, DailyChallenge::for_day(1).seed);
+        assert!((1..30).any(|day| DailyChallenge::for_day(day).modifiers != first.modifiers));
+    }
+}
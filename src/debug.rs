@@ -0,0 +1,325 @@
+//! a debug overlay for tuning, toggled with F3.
+//!
+//! On the play field, it draws:
+//! - the blast radius around each bomb and dynamite;
+//! - the reach of each guy for picking up dynamites and coffee;
+//! - how close bombs must be for an idle guy to go disarm them;
+//! - the path of each guy to their destination.
+//!
+//! A panel lists the frame rate, the number of entities,
+//! what each spawner has left to spawn and the events scheduled.
+//!
+//! Only compiled in with the `debug` cargo feature.
+
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+    render::render_resource::PrimitiveTopology,
+    sprite::MaterialMesh2dBundle,
+    utils::get_short_name,
+};
+
+use crate::{
+    bomb::BombState,
+    events::{
+        BombThrownEvent, CoffeeThrownEvent, DynamiteThrownEvent, ExplosiveKind, NextWaveEvent,
+    },
+    guy::{GuyDestination, GuyPath, GuyWaypoints, BOMB_APPROACH_DIST_SQR},
+    helper::{BaseTranslation, ScheduledEvent},
+    movement::SpatialPosition,
+    spawner::{RandomEventProducer, Spawner, SpawnerCooldown},
+    DefaultFont,
+};
+
+/// Drawn above everything on the play field
+const OVERLAY_Z: f32 = 100.;
+
+/// The number of segments in a circle
+const CIRCLE_SEGMENTS: usize = 48;
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .init_resource::<DebugOverlay>()
+            .add_startup_system(setup)
+            .add_system(toggle)
+            .add_system(begin_panel.after(toggle))
+            .add_system(list_spawners::<DynamiteThrownEvent>.after(begin_panel))
+            .add_system(list_spawners::<BombThrownEvent>.after(begin_panel))
+            .add_system(list_spawners::<CoffeeThrownEvent>.after(begin_panel))
+            .add_system(list_scheduled::<DynamiteThrownEvent>.after(begin_panel))
+            .add_system(list_scheduled::<BombThrownEvent>.after(begin_panel))
+            .add_system(list_scheduled::<CoffeeThrownEvent>.after(begin_panel))
+            .add_system(list_scheduled::<NextWaveEvent>.after(begin_panel))
+            .add_system(
+                show_panel
+                    .after(list_spawners::<DynamiteThrownEvent>)
+                    .after(list_spawners::<BombThrownEvent>)
+                    .after(list_spawners::<CoffeeThrownEvent>)
+                    .after(list_scheduled::<DynamiteThrownEvent>)
+                    .after(list_scheduled::<BombThrownEvent>)
+                    .after(list_scheduled::<CoffeeThrownEvent>)
+                    .after(list_scheduled::<NextWaveEvent>),
+            )
+            .add_system(draw_shapes.after(toggle));
+    }
+}
+
+/// Whether the overlay is shown, and the lines of its panel
+#[derive(Debug, Default, Resource)]
+pub struct DebugOverlay {
+    visible: bool,
+    lines: Vec<String>,
+}
+
+/// The meshes and colors of the shapes drawn
+#[derive(Debug, Resource)]
+pub struct DebugShapes {
+    /// a circle of radius 1
+    circle: Handle<Mesh>,
+    blast: Handle<ColorMaterial>,
+    dynamite_reach: Handle<ColorMaterial>,
+    coffee_reach: Handle<ColorMaterial>,
+    bomb_approach: Handle<ColorMaterial>,
+    path: Handle<ColorMaterial>,
+}
+
+/// Marker for the shapes of the overlay, redrawn every frame
+#[derive(Debug, Component)]
+pub struct DebugShape;
+
+/// Marker for the panel of the overlay
+#[derive(Debug, Component)]
+pub struct DebugPanel;
+
+/// A mesh of lines through the given points
+fn line_strip(points: &[Vec2]) -> Mesh {
+    let positions: Vec<[f32; 3]> = points.iter().map(|p| [p.x, p.y, 0.]).collect();
+    let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh
+}
+
+/// system: make the meshes and colors of the overlay
+pub fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let circle: Vec<Vec2> = (0..=CIRCLE_SEGMENTS)
+        .map(|i| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            Vec2::new(angle.cos(), angle.sin())
+        })
+        .collect();
+
+    let mut material = |color: Color| materials.add(ColorMaterial::from(color));
+    commands.insert_resource(DebugShapes {
+        circle: meshes.add(line_strip(&circle)),
+        blast: material(Color::rgba(1., 0.2, 0.1, 0.8)),
+        dynamite_reach: material(Color::rgba(1., 0.8, 0.1, 0.9)),
+        coffee_reach: material(Color::rgba(0.7, 0.45, 0.2, 0.9)),
+        bomb_approach: material(Color::rgba(0.2, 0.9, 1., 0.9)),
+        path: material(Color::rgba(1., 1., 1., 0.8)),
+    });
+}
+
+/// system: show or hide the overlay on F3
+pub fn toggle(keyboard: Res<Input<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if keyboard.just_pressed(KeyCode::F3) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+/// system: start the panel over, with the frame rate and entity count
+pub fn begin_panel(
+    mut overlay: ResMut<DebugOverlay>,
+    diagnostics: Res<Diagnostics>,
+    query_entities: Query<()>,
+) {
+    overlay.lines.clear();
+    if !overlay.visible {
+        return;
+    }
+
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+    overlay.lines.push(format!("FPS {:.0}", fps));
+    overlay
+        .lines
+        .push(format!("entities {}", query_entities.iter().count()));
+}
+
+/// system: list the spawners of this kind of event, with what they have left
+pub fn list_spawners<E: Reflect + Default>(
+    mut overlay: ResMut<DebugOverlay>,
+    query: Query<(&Spawner, Option<&SpawnerCooldown>), With<RandomEventProducer<E>>>,
+) {
+    if !overlay.visible {
+        return;
+    }
+    for (spawner, cooldown) in &query {
+        overlay.lines.push(format!(
+            "spawner {}: {} left{}{}",
+            get_short_name(std::any::type_name::<E>()),
+            spawner.remaining,
+            if spawner.essential { ", essential" } else { "" },
+            if cooldown.is_some() { ", waiting" } else { "" },
+        ));
+    }
+}
+
+/// system: list the pending events of this kind, with the time left
+pub fn list_scheduled<E: Reflect + Default>(
+    mut overlay: ResMut<DebugOverlay>,
+    query: Query<&ScheduledEvent<E>>,
+) {
+    if !overlay.visible {
+        return;
+    }
+    for scheduled_event in &query {
+        overlay.lines.push(format!(
+            "{} in {:.1}s",
+            get_short_name(std::any::type_name::<E>()),
+            scheduled_event.remaining().as_secs_f32(),
+        ));
+    }
+}
+
+/// system: keep the panel up to date, or remove it when hidden
+pub fn show_panel(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    default_font: Option<Res<DefaultFont>>,
+    mut query_panel: Query<(Entity, &mut Text), With<DebugPanel>>,
+) {
+    if !overlay.visible {
+        for (entity, _) in &query_panel {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let value = overlay.lines.join("\n");
+    if let Ok((_, mut text)) = query_panel.get_single_mut() {
+        text.sections[0].value = value;
+        return;
+    }
+    // the panel goes away with everything else when the screen changes
+    let Some(font) = default_font else {
+        return;
+    };
+    commands.spawn((
+        TextBundle::from_section(
+            value,
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 14.,
+                color: Color::rgb(0.6, 1., 0.6),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(48.),
+                right: Val::Px(12.),
+                ..default()
+            },
+            ..default()
+        }),
+        DebugPanel,
+    ));
+}
+
+/// system: redraw the shapes on the play field
+pub fn draw_shapes(
+    mut commands: Commands,
+    (overlay, shapes): (Res<DebugOverlay>, Option<Res<DebugShapes>>),
+    mut meshes: ResMut<Assets<Mesh>>,
+    query_shapes: Query<Entity, With<DebugShape>>,
+    query_guys: Query<(
+        &SpatialPosition,
+        &BaseTranslation,
+        &GuyDestination,
+        &GuyPath,
+        &GuyWaypoints,
+    )>,
+    query_bombs: Query<(&SpatialPosition, &BaseTranslation, &BombState)>,
+    query_dynamites: Query<&SpatialPosition, With<crate::dynamite::Dynamite>>,
+) {
+    for entity in &query_shapes {
+        commands.entity(entity).despawn();
+    }
+    let Some(shapes) = shapes else {
+        return;
+    };
+    if !overlay.visible {
+        return;
+    }
+
+    let mut circle = |center: Vec2, radius: f32, material: &Handle<ColorMaterial>| {
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: shapes.circle.clone().into(),
+                material: material.clone(),
+                transform: Transform::from_translation(center.extend(OVERLAY_Z))
+                    .with_scale(Vec3::new(radius, radius, 1.)),
+                ..default()
+            },
+            DebugShape,
+        ));
+    };
+
+    // bombs: how far they blast, and how close they lure idle guys
+    for (pos, base_translation, state) in &query_bombs {
+        if *state != BombState::Idle {
+            continue;
+        }
+        circle(
+            pos.0.truncate(),
+            ExplosiveKind::Bomb.blast_radius(),
+            &shapes.blast,
+        );
+        circle(
+            pos.0.truncate() - base_translation.0,
+            BOMB_APPROACH_DIST_SQR.sqrt(),
+            &shapes.bomb_approach,
+        );
+    }
+    for pos in &query_dynamites {
+        circle(
+            pos.0.truncate(),
+            ExplosiveKind::Dynamite.blast_radius(),
+            &shapes.blast,
+        );
+    }
+
+    // guys: how far they reach, and where they are going
+    let mut paths = vec![];
+    for (pos, base_translation, destination, path, waypoints) in &query_guys {
+        let feet = pos.0.truncate() + base_translation.0;
+        circle(feet, crate::dynamite::DIST_REACH, &shapes.dynamite_reach);
+        circle(feet, crate::coffee::DIST_REACH, &shapes.coffee_reach);
+
+        let mut points = vec![pos.0.truncate()];
+        points.extend_from_slice(path.waypoints());
+        points.push(destination.0);
+        points.extend_from_slice(&waypoints.0);
+        paths.push(points);
+    }
+    for points in paths {
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes.add(line_strip(&points)).into(),
+                material: shapes.path.clone(),
+                transform: Transform::from_xyz(0., 0., OVERLAY_Z),
+                ..default()
+            },
+            DebugShape,
+        ));
+    }
+}
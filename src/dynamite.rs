@@ -4,18 +4,24 @@ use crate::animation::{OneShotAnimationTimer, ToggleVisibility};
 use crate::audio::{BounceAudio, GameSoundSources};
 use crate::bomb::BombExplosionBundle;
 use crate::events::{DynamiteDefusedEvent, ExplodedEvent, ExplosiveKind};
-use crate::guy::GuyState;
+use crate::guy::{GuyState, Player};
 use crate::helper::{DelayedComponent, TimeToLive};
+use crate::i18n::LocalizedText;
 use crate::movement::{Gravity, SpatialPosition, SpatialVelocity};
+use crate::particles::{ParticleEffect, ParticleEmitter};
+use crate::targeting::Targetable;
 use crate::{animation::LoopedAnimationTimer, helper::BaseTranslation};
 use crate::{poptext, DefaultFont};
 use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
 use bevy::utils::Duration;
 
-#[derive(Default, Component)]
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct Dynamite;
 
-#[derive(Component, Deref, DerefMut)]
+#[derive(Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct TimeToExplode(pub Timer);
 
 impl TimeToExplode {
@@ -39,6 +45,7 @@ pub struct DynamiteBundle {
     pub base_translation: BaseTranslation,
     pub sprite_sheet: SpriteSheetBundle,
     pub animation_timer: LoopedAnimationTimer,
+    pub targetable: Targetable,
 }
 
 #[derive(Resource, Deref)]
@@ -47,6 +54,13 @@ pub struct DynamiteTextureAtlas(pub Handle<TextureAtlas>);
 #[derive(Resource, Deref)]
 pub struct DynamiteExplosionTextureAtlas(pub Handle<TextureAtlas>);
 
+/// The sprite sheets are kept under fixed handles,
+/// so that snapshots of the play field can refer to them
+const DYNAMITE_TEXTURE_ATLAS: HandleUntyped =
+    HandleUntyped::weak_from_u64(TextureAtlas::TYPE_UUID, 0x47c2_e80d_3a95_1b6f);
+const DYNAMITE_EXPLOSION_TEXTURE_ATLAS: HandleUntyped =
+    HandleUntyped::weak_from_u64(TextureAtlas::TYPE_UUID, 0xb05e_9d14_72c8_a3e6);
+
 /// system: set up dynamite
 pub fn setup(
     mut commands: Commands,
@@ -56,14 +70,15 @@ pub fn setup(
     let tex: Handle<Image> = asset_server.load("img/dynamite.png");
 
     let texture_atlas = TextureAtlas::from_grid(tex, Vec2::new(30.0, 18.0), 2, 1, None, None);
-    let texture_atlas = texture_atlases.add(texture_atlas);
+    let texture_atlas = texture_atlases.set(DYNAMITE_TEXTURE_ATLAS, texture_atlas);
 
     commands.insert_resource(DynamiteTextureAtlas(texture_atlas.clone()));
 
     // load dynamite explosion spritesheet
     let tex_explosion: Handle<Image> = asset_server.load("img/dynamite-explosion.png");
     let atlas = TextureAtlas::from_grid(tex_explosion, Vec2::new(48.0, 48.0), 3, 1, None, None);
-    let explosion_texture_atlas_handle = texture_atlases.add(atlas);
+    let explosion_texture_atlas_handle =
+        texture_atlases.set(DYNAMITE_EXPLOSION_TEXTURE_ATLAS, atlas);
 
     // save as resource for later
     commands.insert_resource(DynamiteExplosionTextureAtlas(
@@ -107,54 +122,63 @@ pub fn spawn_dynamite(
             BounceAudio(bounce_sound),
             // blink shortly before it explodes
             DelayedComponent::new(ToggleVisibility::default(), Duration::from_millis(4_100)),
+            // sparks from the burning fuse
+            ParticleEmitter::new(ParticleEffect::Sparks, Vec3::new(22., 0., 10.)),
         ))
         .id()
 }
 
-/// system: if guy is close to dynamite, defuse it
+/// How close the guy's feet must get to a dynamite to grab it
+pub const DIST_REACH: f32 = 30.;
+const DIST_SQR_REACH: f32 = DIST_REACH * DIST_REACH;
+
+/// system: if a guy is close to dynamite, defuse it
 pub fn detect_guy_touch_dynamite(
     mut commands: Commands,
-    query_guy: Query<(&GuyState, &SpatialPosition, &BaseTranslation)>,
+    query_guy: Query<(&GuyState, &SpatialPosition, &BaseTranslation, &Player)>,
     query_dynamite: Query<(Entity, &SpatialPosition), With<Dynamite>>,
     font: Res<DefaultFont>,
     audio: Res<Audio>,
     sound_sources: Res<GameSoundSources>,
     mut event_writer: EventWriter<DynamiteDefusedEvent>,
 ) {
-    let Ok((guy_state, guy_pos, base_translation)) = query_guy.get_single() else {
-        return;
-    };
-
-    // do not pick up dynamites in these states
-    if matches!(
-        guy_state,
-        GuyState::Disarming { .. } | GuyState::Ouch | GuyState::Victorious
-    ) {
-        return;
-    }
-
-    const DIST_REACH: f32 = 30.;
-    const DIST_SQR_REACH: f32 = DIST_REACH * DIST_REACH;
-
-    for (entity, pos) in &query_dynamite {
-        let dist_sqr = (guy_pos.0 + base_translation.0.extend(0.)).distance_squared(pos.0);
-        if dist_sqr <= DIST_SQR_REACH {
-            // grab it!
-            event_writer.send(DynamiteDefusedEvent(entity));
-
-            // play audio
-            audio.play(sound_sources.woosh.cast_weak());
-
-            poptext::spawn_popup_text(
-                &mut commands,
-                font.0.clone(),
-                pos.0.truncate(),
-                "defused",
-                16.,
-                Color::WHITE,
-            );
+    // dynamites already grabbed by another guy in this frame
+    let mut taken: Vec<Entity> = vec![];
+
+    for (guy_state, guy_pos, base_translation, player) in &query_guy {
+        // do not pick up dynamites in these states
+        if matches!(
+            guy_state,
+            GuyState::Disarming { .. } | GuyState::Ouch | GuyState::Victorious
+        ) {
+            continue;
+        }
 
-            commands.entity(entity).despawn();
+        for (entity, pos) in &query_dynamite {
+            if taken.contains(&entity) {
+                continue;
+            }
+            let dist_sqr = (guy_pos.0 + base_translation.0.extend(0.)).distance_squared(pos.0);
+            if dist_sqr <= DIST_SQR_REACH {
+                // grab it!
+                taken.push(entity);
+                event_writer.send(DynamiteDefusedEvent(entity, *player));
+
+                // play audio
+                audio.play(sound_sources.woosh.cast_weak());
+
+                let text = poptext::spawn_popup_text(
+                    &mut commands,
+                    font.0.clone(),
+                    pos.0.truncate(),
+                    "",
+                    16.,
+                    Color::WHITE,
+                );
+                commands.entity(text).insert(LocalizedText::new("defused"));
+
+                commands.entity(entity).despawn();
+            }
         }
     }
 }
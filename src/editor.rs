@@ -0,0 +1,590 @@
+//! the wave editor, for laying out waves right on the play field.
+//!
+//! Open it with F4 from the main menu. Then:
+//! - 1 to 3 pick an item to throw (dynamite, bomb, coffee),
+//!   and 4 to 6 an obstacle (crate, cement mixer, scaffolding);
+//! - clicking places a throw or an obstacle there, right clicking removes the closest one;
+//! - up and down change when the throws placed next happen;
+//! - A adds a spawner of the item picked, Tab goes through the spawners,
+//!   left and right change the rate of the spawner selected, page up and down its count,
+//!   E whether it is essential and Delete removes it;
+//! - L goes through the level layouts;
+//! - P plays the wave as laid out, coming back to the editor once it is over (or on Escape);
+//! - N names the wave: type the name and press Enter
+//!   (or Escape to keep the name it had), and if there is a wave by that name
+//!   it is opened, otherwise the wave being edited is kept under that name;
+//! - S saves the wave;
+//! - Escape goes back to the main menu.
+//!
+//! The wave is saved as a [`WaveDefinition`] in RON,
+//! as `waves/<name>.wave.ron` in the game's storage.
+//! Runs play the waves saved this way after the shipped ones,
+//! in the order of their names
+//! (except on the web, where they can only be played from the editor).
+//!
+//! The editor is only there with the `editor` feature.
+
+use bevy::prelude::*;
+
+use crate::{
+    achievements::RunRecord,
+    events::WaveFinishedEvent,
+    ingame::GameMode,
+    level::{CurrentLevel, LevelLayouts, LEVELS},
+    menu::fade_to,
+    obstacle::{spawn_obstacle, ObstacleKind},
+    screen::window_to_world,
+    spawner::Throwable,
+    waves::{PreviewWave, SpawnerDefinition, ThrowDefinition, WaveDefinition, WaveList},
+    AppState, DefaultFont, DelayedStateChange,
+};
+
+/// The name of the wave edited when the editor is first opened
+const DEFAULT_NAME: &str = "custom";
+
+/// The longest name a wave can be given
+const MAX_NAME_LEN: usize = 24;
+
+/// How much the time of the next throws changes per key press, in seconds
+const THROW_TIME_STEP: f32 = 0.5;
+
+/// How much the rate of a spawner changes per key press
+const LAMBDA_STEP: f32 = 0.01;
+
+/// How close to a throw or obstacle a right click must be to remove it
+const PICK_DIST: f32 = 24.;
+
+/// Drawn above the play field
+const MARKER_Z: f32 = 50.;
+
+/// What clicking on the play field places
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Tool {
+    Throw(Throwable),
+    Obstacle(ObstacleKind),
+}
+
+/// The keys which pick each tool
+const TOOLS: [(KeyCode, Tool); 6] = [
+    (KeyCode::Key1, Tool::Throw(Throwable::Dynamite)),
+    (KeyCode::Key2, Tool::Throw(Throwable::Bomb)),
+    (KeyCode::Key3, Tool::Throw(Throwable::Coffee)),
+    (KeyCode::Key4, Tool::Obstacle(ObstacleKind::Crate)),
+    (KeyCode::Key5, Tool::Obstacle(ObstacleKind::CementMixer)),
+    (KeyCode::Key6, Tool::Obstacle(ObstacleKind::Scaffolding)),
+];
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaveEditor>()
+            .add_startup_system(add_saved_waves)
+            .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(enter_from_menu))
+            .add_system_set(SystemSet::on_enter(AppState::Editor).with_system(setup))
+            .add_system_set(
+                SystemSet::on_update(AppState::Editor)
+                    .with_system(edit_with_keys)
+                    .with_system(edit_with_mouse)
+                    .with_system(draw_markers.after(edit_with_keys).after(edit_with_mouse))
+                    .with_system(update_panel.after(edit_with_keys).after(edit_with_mouse))
+                    .with_system(save_or_leave)
+                    // typing the name takes the keys over from the other systems
+                    .with_system(name_wave.after(edit_with_keys).after(save_or_leave)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Editor).with_system(crate::menu::destroy))
+            .add_system_set(SystemSet::on_update(AppState::InGame).with_system(run_preview))
+            .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(end_preview));
+    }
+}
+
+/// The wave being edited, kept across previews
+#[derive(Debug, Resource)]
+pub struct WaveEditor {
+    wave: WaveDefinition,
+    /// the name the wave is saved under
+    name: String,
+    /// the name being typed, if any
+    naming: Option<String>,
+    tool: Tool,
+    /// when the throws placed next happen, in seconds from the start of the wave
+    after: f32,
+    /// the index of the spawner selected
+    selected: usize,
+    /// whether there are changes which were not saved
+    unsaved: bool,
+}
+
+impl Default for WaveEditor {
+    fn default() -> Self {
+        WaveEditor {
+            wave: load_wave(DEFAULT_NAME).unwrap_or_default(),
+            name: DEFAULT_NAME.to_string(),
+            naming: None,
+            tool: Tool::Throw(Throwable::Dynamite),
+            after: 1.,
+            selected: 0,
+            unsaved: false,
+        }
+    }
+}
+
+/// Marker for what the editor draws on the play field
+#[derive(Debug, Component)]
+pub struct EditorMarker;
+
+/// Marker for the panel of the editor
+#[derive(Debug, Component)]
+pub struct EditorPanel;
+
+/// The key under which the wave of the given name is saved
+fn wave_key(name: &str) -> String {
+    format!("waves/{}.wave", name)
+}
+
+/// Load the wave of the given name, if there is one
+fn load_wave(name: &str) -> Option<WaveDefinition> {
+    crate::storage::load(&wave_key(name))
+}
+
+/// Have runs play the wave saved under the given key,
+/// or pick up the changes to it if they already do
+fn add_saved_wave(asset_server: &AssetServer, waves: &mut WaveList, key: &str) {
+    let Some(path) = crate::storage::path(key) else {
+        return;
+    };
+    let handle = asset_server.load(path.clone());
+    if !waves.add_data(handle) {
+        asset_server.reload_asset(path);
+    }
+}
+
+/// system: have runs play the waves saved from the editor
+fn add_saved_waves(asset_server: Res<AssetServer>, mut waves: ResMut<WaveList>) {
+    for key in crate::storage::keys_in("waves") {
+        if key.ends_with(".wave") {
+            add_saved_wave(&asset_server, &mut waves, &key);
+        }
+    }
+}
+
+fn item_color(item: Throwable) -> Color {
+    match item {
+        Throwable::Dynamite => Color::rgb(0.9, 0.2, 0.15),
+        Throwable::Bomb => Color::rgb(0.15, 0.15, 0.2),
+        Throwable::Coffee => Color::rgb(0.55, 0.35, 0.2),
+    }
+}
+
+/// system: open the editor on F4 from the main menu
+pub fn enter_from_menu(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    transition_entity: Query<Entity, With<DelayedStateChange>>,
+) {
+    if keyboard.just_pressed(KeyCode::F4) && transition_entity.is_empty() {
+        fade_to(&mut commands, AppState::Editor);
+    }
+}
+
+/// system: lay out the play field and the panel of the editor
+pub fn setup(
+    mut commands: Commands,
+    layouts: Res<LevelLayouts>,
+    font: Res<DefaultFont>,
+    mut editor: ResMut<WaveEditor>,
+) {
+    commands.insert_resource(CurrentLevel(layouts.get(&editor.wave.level)));
+    commands.spawn(crate::screen::camera_bundle());
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 14.,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(8.),
+                left: Val::Px(8.),
+                ..default()
+            },
+            ..default()
+        }),
+        BackgroundColor(Color::rgba(0., 0., 0., 0.6)),
+        EditorPanel,
+    ));
+
+    // draw what was laid out before
+    editor.set_changed();
+}
+
+/// system: pick tools, change levels and tune spawners with the keyboard
+pub fn edit_with_keys(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    layouts: Res<LevelLayouts>,
+    mut editor: ResMut<WaveEditor>,
+) {
+    // only touch the editor on key presses, so that it is not redrawn for nothing
+    if keyboard.get_just_pressed().next().is_none() || editor.naming.is_some() {
+        return;
+    }
+    let editor = &mut *editor;
+
+    for (key, tool) in TOOLS {
+        if keyboard.just_pressed(key) {
+            editor.tool = tool;
+        }
+    }
+    if keyboard.just_pressed(KeyCode::Up) {
+        editor.after += THROW_TIME_STEP;
+    }
+    if keyboard.just_pressed(KeyCode::Down) {
+        editor.after = (editor.after - THROW_TIME_STEP).max(0.);
+    }
+    if keyboard.just_pressed(KeyCode::L) {
+        let next = LEVELS
+            .iter()
+            .position(|level| *level == editor.wave.level)
+            .map_or(0, |i| (i + 1) % LEVELS.len());
+        editor.wave.level = LEVELS[next].to_string();
+        commands.insert_resource(CurrentLevel(layouts.get(LEVELS[next])));
+        editor.unsaved = true;
+    }
+
+    // spawners
+    if keyboard.just_pressed(KeyCode::A) {
+        if let Tool::Throw(item) = editor.tool {
+            editor.wave.spawners.push(SpawnerDefinition {
+                item,
+                lambda: 0.3,
+                count: 10,
+                essential: item != Throwable::Coffee,
+            });
+            editor.selected = editor.wave.spawners.len() - 1;
+            editor.unsaved = true;
+        }
+    }
+    if keyboard.just_pressed(KeyCode::Tab) && !editor.wave.spawners.is_empty() {
+        editor.selected = (editor.selected + 1) % editor.wave.spawners.len();
+    }
+    if keyboard.just_pressed(KeyCode::Delete) && editor.selected < editor.wave.spawners.len() {
+        editor.wave.spawners.remove(editor.selected);
+        editor.selected = editor
+            .selected
+            .min(editor.wave.spawners.len().saturating_sub(1));
+        editor.unsaved = true;
+        return;
+    }
+    let Some(spawner) = editor.wave.spawners.get_mut(editor.selected) else {
+        return;
+    };
+    let mut changed = true;
+    if keyboard.just_pressed(KeyCode::Left) {
+        spawner.lambda = ((spawner.lambda - LAMBDA_STEP) / LAMBDA_STEP).round() * LAMBDA_STEP;
+        spawner.lambda = spawner.lambda.max(LAMBDA_STEP);
+    } else if keyboard.just_pressed(KeyCode::Right) {
+        spawner.lambda = ((spawner.lambda + LAMBDA_STEP) / LAMBDA_STEP).round() * LAMBDA_STEP;
+    } else if keyboard.just_pressed(KeyCode::PageDown) {
+        spawner.count = spawner.count.saturating_sub(1);
+    } else if keyboard.just_pressed(KeyCode::PageUp) {
+        spawner.count += 1;
+    } else if keyboard.just_pressed(KeyCode::E) {
+        spawner.essential = !spawner.essential;
+    } else {
+        changed = false;
+    }
+    editor.unsaved |= changed;
+}
+
+/// system: place throws and obstacles with the mouse
+pub fn edit_with_mouse(
+    windows: Res<Windows>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    mut editor: ResMut<WaveEditor>,
+    query_camera: Query<(&Camera, &GlobalTransform)>,
+) {
+    let place = mouse_button_input.just_pressed(MouseButton::Left);
+    let remove = mouse_button_input.just_pressed(MouseButton::Right);
+    if !place && !remove {
+        return;
+    }
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = query_camera.get_single() else {
+        return;
+    };
+    let Some(pos) = window
+        .cursor_position()
+        .and_then(|pos| window_to_world(camera, camera_transform, pos))
+    else {
+        return;
+    };
+
+    if place {
+        let position = (pos.x, pos.y);
+        match editor.tool {
+            Tool::Throw(item) => {
+                let after = editor.after;
+                editor.wave.throws.push(ThrowDefinition {
+                    item,
+                    after,
+                    position,
+                });
+            }
+            Tool::Obstacle(kind) => editor.wave.obstacles.push((kind, position)),
+        }
+        editor.unsaved = true;
+        return;
+    }
+
+    // remove whichever throw or obstacle is closest
+    let dist = |(x, y): (f32, f32)| Vec2::new(x, y).distance(pos);
+    let closest_throw = editor
+        .wave
+        .throws
+        .iter()
+        .enumerate()
+        .map(|(i, throw)| (i, dist(throw.position)))
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    let closest_obstacle = editor
+        .wave
+        .obstacles
+        .iter()
+        .enumerate()
+        .map(|(i, (_, position))| (i, dist(*position)))
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    let throw_first = match (closest_throw, closest_obstacle) {
+        (Some((_, d)), Some((_, o))) => d <= o,
+        (throw, _) => throw.is_some(),
+    };
+    match (closest_throw, closest_obstacle) {
+        (Some((i, d)), _) if throw_first && d < PICK_DIST => {
+            editor.wave.throws.remove(i);
+            editor.unsaved = true;
+        }
+        (_, Some((i, d))) if !throw_first && d < PICK_DIST => {
+            editor.wave.obstacles.remove(i);
+            editor.unsaved = true;
+        }
+        _ => {}
+    }
+}
+
+/// system: redraw the throws and obstacles of the wave whenever it changes
+pub fn draw_markers(
+    mut commands: Commands,
+    editor: Res<WaveEditor>,
+    font: Res<DefaultFont>,
+    query_markers: Query<Entity, With<EditorMarker>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+    for entity in &query_markers {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (kind, (x, y)) in &editor.wave.obstacles {
+        let obstacle = spawn_obstacle(&mut commands, *kind, Vec2::new(*x, *y));
+        commands.entity(obstacle).insert(EditorMarker);
+    }
+    for throw in &editor.wave.throws {
+        let (x, y) = throw.position;
+        commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: item_color(throw.item),
+                        custom_size: Some(Vec2::splat(10.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(x, y, MARKER_Z),
+                    ..default()
+                },
+                EditorMarker,
+            ))
+            .with_children(|parent| {
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        format!("{:.1}s", throw.after),
+                        TextStyle {
+                            font: font.0.clone(),
+                            font_size: 14.,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_alignment(TextAlignment::CENTER),
+                    transform: Transform::from_xyz(0., 14., 0.),
+                    ..default()
+                });
+            });
+    }
+}
+
+/// system: keep the panel up to date with the wave and the tool picked
+pub fn update_panel(
+    editor: Res<WaveEditor>,
+    query_added: Query<(), Added<EditorPanel>>,
+    mut query_panel: Query<&mut Text, With<EditorPanel>>,
+) {
+    if !editor.is_changed() && query_added.is_empty() {
+        return;
+    }
+    let Ok(mut text) = query_panel.get_single_mut() else {
+        return;
+    };
+
+    let tool = match editor.tool {
+        Tool::Throw(item) => format!("throw {:?}", item),
+        Tool::Obstacle(kind) => format!("place {:?}", kind),
+    };
+    let mut lines = vec![
+        format!(
+            "WAVE EDITOR{}",
+            if editor.unsaved { " (not saved)" } else { "" }
+        ),
+        match &editor.naming {
+            Some(name) => format!("name: {}_ (Enter open or rename, Esc cancel)", name),
+            None => format!("name: {} (N)", editor.name),
+        },
+        format!("level: {} (L)", editor.wave.level),
+        format!("tool: {} (1-6)", tool),
+        format!("next throws at {:.1}s (up/down)", editor.after),
+        "spawners (A add, Tab select):".to_string(),
+    ];
+    for (i, spawner) in editor.wave.spawners.iter().enumerate() {
+        lines.push(format!(
+            "{} {:?} x{} at {:.2}/s{}",
+            if i == editor.selected { ">" } else { " " },
+            spawner.item,
+            spawner.count,
+            spawner.lambda,
+            if spawner.essential { ", essential" } else { "" },
+        ));
+    }
+    lines.push("  (left/right rate, page up/down count, E essential, Del remove)".to_string());
+    lines.push("click place, right click remove".to_string());
+    lines.push("P preview, S save, Esc leave".to_string());
+    text.sections[0].value = lines.join("\n");
+}
+
+/// system: save the wave, preview it or leave the editor
+pub fn save_or_leave(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    (mut editor, mut waves): (ResMut<WaveEditor>, ResMut<WaveList>),
+    transition_entity: Query<Entity, With<DelayedStateChange>>,
+) {
+    if !transition_entity.is_empty() || editor.naming.is_some() {
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::S) && !editor.wave.has_valid_rates() {
+        warn!(
+            "Not saving wave `{}`: a spawner has a rate which is not positive",
+            editor.name
+        );
+    } else if keyboard.just_pressed(KeyCode::S) {
+        let key = wave_key(&editor.name);
+        crate::storage::save(&key, &editor.wave);
+        add_saved_wave(&asset_server, &mut waves, &key);
+        editor.unsaved = false;
+        info!("Wave saved as `{}`", editor.name);
+    }
+    if keyboard.just_pressed(KeyCode::P) {
+        commands.insert_resource(PreviewWave(editor.wave.clone()));
+        commands.insert_resource(GameMode::Classic);
+        fade_to(&mut commands, AppState::InGame);
+    } else if keyboard.just_pressed(KeyCode::Escape) {
+        fade_to(&mut commands, AppState::MainMenu);
+    }
+}
+
+/// system: type in the name of the wave,
+/// opening the wave by that name if there is one
+pub fn name_wave(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    layouts: Res<LevelLayouts>,
+    mut char_reader: EventReader<ReceivedCharacter>,
+    mut editor: ResMut<WaveEditor>,
+) {
+    let Some(typed) = &editor.naming else {
+        char_reader.clear();
+        if keyboard.just_pressed(KeyCode::N) {
+            editor.naming = Some(String::new());
+        }
+        return;
+    };
+
+    let mut name = typed.clone();
+    for ReceivedCharacter { char, .. } in char_reader.iter() {
+        // the name is that of a file
+        if (char.is_ascii_alphanumeric() || *char == '_' || *char == '-')
+            && name.len() < MAX_NAME_LEN
+        {
+            name.push(*char);
+        }
+    }
+    if keyboard.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        editor.naming = None;
+    } else if keyboard.just_pressed(KeyCode::Return) {
+        editor.naming = None;
+        if name.is_empty() || name == editor.name {
+            return;
+        }
+        match load_wave(&name) {
+            Some(wave) => {
+                info!("Wave `{}` opened", name);
+                commands.insert_resource(CurrentLevel(layouts.get(&wave.level)));
+                editor.wave = wave;
+                editor.selected = 0;
+                editor.unsaved = false;
+            }
+            // the wave being edited is to be saved under the new name
+            None => editor.unsaved = true,
+        }
+        editor.name = name;
+    } else if name != *typed {
+        // only touch the editor when the name changes, so that it is not redrawn for nothing
+        editor.naming = Some(name);
+    }
+}
+
+/// system: keep a preview out of the records,
+/// and go back to the editor once the wave is over
+pub fn run_preview(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    preview: Option<Res<PreviewWave>>,
+    mut record: ResMut<RunRecord>,
+    mut event_reader: EventReader<WaveFinishedEvent>,
+    transition_entity: Query<Entity, With<DelayedStateChange>>,
+) {
+    let finished = event_reader.iter().count() > 0;
+    if preview.is_none() {
+        return;
+    }
+    if !record.assisted {
+        record.assisted = true;
+    }
+    if (finished || keyboard.just_pressed(KeyCode::Escape)) && transition_entity.is_empty() {
+        fade_to(&mut commands, AppState::Editor);
+    }
+}
+
+/// system: the preview is over
+pub fn end_preview(mut commands: Commands) {
+    commands.remove_resource::<PreviewWave>();
+}
@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 
-pub struct DynamiteDefusedEvent(pub Entity);
+use crate::guy::Player;
+
+/// A dynamite was defused, and by which player
+pub struct DynamiteDefusedEvent(pub Entity, pub Player);
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ExplosiveKind {
@@ -8,9 +11,18 @@ pub enum ExplosiveKind {
     Bomb,
 }
 
-pub struct BombDisarmedEvent(pub Entity);
+impl ExplosiveKind {
+    /// How far from the explosion the guy gets hurt
+    pub fn blast_radius(self) -> f32 {
+        match self {
+            ExplosiveKind::Dynamite => 112.,
+            ExplosiveKind::Bomb => 164.,
+        }
+    }
+}
 
-pub struct DisarmProgressEvent(pub f32);
+/// A bomb was disarmed, and by which player
+pub struct BombDisarmedEvent(pub Entity, pub Player);
 
 pub struct DisarmCancelledEvent(pub Entity);
 
@@ -20,24 +32,34 @@ pub struct ExplodedEvent {
     pub position: Vec3,
 }
 
+/// Something bounced off the floor
+#[derive(Debug)]
+pub struct BouncedEvent {
+    pub position: Vec3,
+    /// how fast it was falling
+    pub speed: f32,
+}
+
 #[derive(Debug)]
 pub struct GuyHurtEvent {
     pub from: ExplosiveKind,
+    /// the player whose guy got hurt
+    pub player: Player,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, Reflect)]
 pub struct DynamiteThrownEvent;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, Reflect)]
 pub struct BombThrownEvent;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, Reflect)]
 pub struct CoffeeThrownEvent;
 
 #[derive(Debug, Copy, Clone)]
 pub struct WaveFinishedEvent;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, Reflect)]
 pub struct NextWaveEvent;
 
 #[derive(Debug, Copy, Clone)]
@@ -0,0 +1,220 @@
+//! game feel: camera shake, hit-stop and screen flashes,
+//! so that explosions actually feel like explosions
+
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::{
+    events::{ExplodedEvent, ExplosiveKind, GuyHurtEvent},
+    guy::GuyState,
+    movement::SpatialPosition,
+    settings::Settings,
+};
+
+/// Marker for the camera which shakes,
+/// keeping track of where it should be at rest
+#[derive(Debug, Component)]
+pub struct ShakyCamera {
+    pub base: Vec3,
+}
+
+/// The amount of camera trauma, from 0 to 1.
+/// The camera shakes proportionally to the square of trauma,
+/// which decays over time.
+#[derive(Debug, Default, Resource)]
+pub struct Trauma(pub f32);
+
+impl Trauma {
+    pub fn add(&mut self, amount: f32) {
+        self.0 = (self.0 + amount).min(1.);
+    }
+}
+
+/// A brief freeze of game time after a big hit.
+#[derive(Debug, Default, Resource)]
+pub struct HitStop {
+    /// real time left in the freeze
+    remaining: Duration,
+    /// the relative speed of time to restore afterwards
+    restore_speed: Option<f32>,
+}
+
+/// how quickly trauma goes away, per second
+const TRAUMA_DECAY: f32 = 1.6;
+/// the maximum camera offset at full trauma, in pixels
+const MAX_SHAKE_OFFSET: f32 = 14.;
+/// the maximum camera roll at full trauma, in radians
+const MAX_SHAKE_ROLL: f32 = 0.05;
+/// explosions further than this away from every guy barely shake the camera
+const SHAKE_FALLOFF_DISTANCE: f32 = 420.;
+
+/// system: add trauma on explosions and when the guy gets hurt
+pub fn add_trauma(
+    settings: Res<Settings>,
+    mut trauma: ResMut<Trauma>,
+    mut exploded_event_reader: EventReader<ExplodedEvent>,
+    mut hurt_event_reader: EventReader<GuyHurtEvent>,
+    query_guy: Query<&SpatialPosition, With<GuyState>>,
+) {
+    let intensity = settings.effective_feel_intensity();
+
+    for ExplodedEvent { kind, position } in exploded_event_reader.iter() {
+        let base = match kind {
+            ExplosiveKind::Dynamite => 0.3,
+            ExplosiveKind::Bomb => 0.75,
+        };
+        // explosions closer to a guy shake more
+        let falloff = query_guy
+            .iter()
+            .map(|guy_pos| guy_pos.0.truncate().distance(position.truncate()))
+            .min_by(f32::total_cmp)
+            .map(|distance| (1. - distance / SHAKE_FALLOFF_DISTANCE).clamp(0.25, 1.))
+            .unwrap_or(1.);
+        trauma.add(base * falloff * intensity);
+    }
+
+    for GuyHurtEvent { from, .. } in hurt_event_reader.iter() {
+        let amount = match from {
+            ExplosiveKind::Dynamite => 0.2,
+            ExplosiveKind::Bomb => 0.4,
+        };
+        trauma.add(amount * intensity);
+    }
+}
+
+/// system: shake the camera according to trauma
+pub fn shake_camera(
+    time: Res<Time>,
+    mut trauma: ResMut<Trauma>,
+    mut query: Query<(&mut Transform, &ShakyCamera)>,
+) {
+    // use real time, so that the shake goes on during hit-stop
+    let t = time.raw_elapsed_seconds();
+    trauma.0 = (trauma.0 - TRAUMA_DECAY * time.raw_delta_seconds()).max(0.);
+    let shake = trauma.0 * trauma.0;
+
+    for (mut transform, camera) in &mut query {
+        // cheap smooth noise out of a few sine waves
+        let noise = |seed: f32| ((t * 37. + seed).sin() + (t * 61. + seed * 2.1).sin() * 0.5) / 1.5;
+        let offset = Vec3::new(noise(0.), noise(10.), 0.) * MAX_SHAKE_OFFSET * shake;
+        transform.translation = camera.base + offset;
+        transform.rotation = Quat::from_rotation_z(noise(20.) * MAX_SHAKE_ROLL * shake);
+    }
+}
+
+/// system: briefly freeze the game when a bomb explodes
+pub fn start_hit_stop(
+    settings: Res<Settings>,
+    mut time: ResMut<Time>,
+    mut hit_stop: ResMut<HitStop>,
+    mut event_reader: EventReader<ExplodedEvent>,
+) {
+    let intensity = settings.effective_feel_intensity();
+    if intensity <= 0. {
+        event_reader.clear();
+        return;
+    }
+
+    for ev in event_reader.iter() {
+        if ev.kind != ExplosiveKind::Bomb {
+            continue;
+        }
+
+        if hit_stop.restore_speed.is_none() {
+            hit_stop.restore_speed = Some(time.relative_speed());
+        }
+        hit_stop.remaining = Duration::from_secs_f32(0.09 * intensity);
+        time.set_relative_speed(0.05);
+    }
+}
+
+/// system: resume the game after a hit-stop
+pub fn update_hit_stop(mut time: ResMut<Time>, mut hit_stop: ResMut<HitStop>) {
+    let Some(restore_speed) = hit_stop.restore_speed else {
+        return;
+    };
+
+    let raw_delta = time.raw_delta();
+    hit_stop.remaining = hit_stop.remaining.saturating_sub(raw_delta);
+    if hit_stop.remaining.is_zero() {
+        time.set_relative_speed(restore_speed);
+        hit_stop.restore_speed = None;
+    }
+}
+
+/// A full screen flash which quickly fades away
+#[derive(Debug, Component)]
+pub struct ScreenFlash {
+    timer: Timer,
+    opacity: f32,
+}
+
+/// system: flash the screen white on bomb explosions and when the guy gets hurt
+pub fn start_screen_flash(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut exploded_event_reader: EventReader<ExplodedEvent>,
+    mut hurt_event_reader: EventReader<GuyHurtEvent>,
+) {
+    let intensity = settings.effective_feel_intensity();
+
+    let bomb_exploded = exploded_event_reader
+        .iter()
+        .any(|ev| ev.kind == ExplosiveKind::Bomb);
+    let guy_hurt = hurt_event_reader.iter().next().is_some();
+
+    let opacity = if bomb_exploded {
+        0.7
+    } else if guy_hurt {
+        0.35
+    } else {
+        return;
+    } * intensity;
+
+    if opacity <= 0. {
+        return;
+    }
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                ..default()
+            },
+            background_color: Color::rgba(1., 1., 1., opacity).into(),
+            focus_policy: bevy::ui::FocusPolicy::Pass,
+            z_index: ZIndex::Global(900),
+            ..default()
+        },
+        ScreenFlash {
+            timer: Timer::new(Duration::from_millis(180), TimerMode::Once),
+            opacity,
+        },
+    ));
+}
+
+/// system: fade out screen flashes
+pub fn fade_screen_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut ScreenFlash, &mut BackgroundColor)>,
+) {
+    for (entity, mut flash, mut color) in &mut query {
+        // real time, so that it fades during hit-stop too
+        flash.timer.tick(time.raw_delta());
+        color.0.set_a(flash.opacity * flash.timer.percent_left());
+        if flash.timer.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// system: reset everything when leaving the game
+pub fn reset(mut time: ResMut<Time>, mut trauma: ResMut<Trauma>, mut hit_stop: ResMut<HitStop>) {
+    if let Some(restore_speed) = hit_stop.restore_speed.take() {
+        time.set_relative_speed(restore_speed);
+    }
+    hit_stop.remaining = Duration::ZERO;
+    trauma.0 = 0.;
+}
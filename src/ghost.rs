@@ -0,0 +1,377 @@
+//! ghost runs: the best run, recorded, and raced against later.
+//!
+//! Classic runs are recorded as they are played:
+//! where the guy was and what he was doing, a few times a second,
+//! and the score at the end of each wave.
+//! A finished run which beats the best score so far is stored as the ghost.
+//!
+//! Racing the ghost plays a run on the ghost's seed,
+//! with a translucent guy walking the recorded path next to the live one,
+//! animated by [`crate::guy::animate_guy`] like any other guy.
+//! At the end of each wave, the HUD tells how far ahead or behind the ghost the player is.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility::HudText,
+    achievements::RunRecord,
+    bomb::BombTick,
+    events::WaveFinishedEvent,
+    guy::{DisarmHurry, GuyAnimationTimer, GuyState, GuyTextureAtlas, PointerControlled},
+    helper::BaseTranslation,
+    i18n::LocalizedText,
+    ingame::GameMode,
+    movement::{SpatialPosition, SpatialVelocity},
+    resume::ResumeRun,
+    scores::{GameScores, Stats},
+    screen::InField,
+    spawner::RunSeed,
+    tutorial::TutorialProgress,
+    DefaultFont,
+};
+
+/// The key under which the ghost of the best run is stored
+const GHOST_KEY: &str = "ghost";
+
+/// How often the guy is recorded, in seconds
+const SAMPLE_INTERVAL: f32 = 0.1;
+
+/// How opaque the ghost guy is
+const GHOST_ALPHA: f32 = 0.45;
+
+/// What the guy was doing, as far as the ghost needs to know
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum GhostPose {
+    Idle,
+    Running,
+    Disarming,
+    /// disarming a bomb about to go off
+    DisarmingHurry,
+    Ouch,
+    Victorious,
+    Loser,
+}
+
+/// The guy at one point in time
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+struct GhostSample {
+    /// seconds since the run started
+    t: f32,
+    pos: [f32; 3],
+    vel: [f32; 2],
+    pose: GhostPose,
+}
+
+/// A recorded run
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GhostRun {
+    /// the seed which the run was played on
+    pub seed: u64,
+    /// the final score
+    pub score: i32,
+    /// the score at the end of each wave
+    pub wave_scores: Vec<i32>,
+    samples: Vec<GhostSample>,
+}
+
+impl GhostRun {
+    /// Load the ghost of the best run from storage, if there is one
+    pub fn load() -> Option<Self> {
+        crate::storage::load(GHOST_KEY)
+    }
+}
+
+/// The run being played, as it is recorded
+#[derive(Debug, Default, Resource)]
+pub struct GhostRecorder {
+    run: GhostRun,
+    elapsed: f32,
+    /// whether a race on the run's seed would play the same,
+    /// which is not the case if the tutorial is shown
+    eligible: bool,
+    finished: bool,
+}
+
+/// A race against the ghost of the best run
+#[derive(Debug, Resource)]
+pub struct GhostRace {
+    run: GhostRun,
+    elapsed: f32,
+    /// how many waves were finished so far
+    waves_finished: usize,
+    spawned: bool,
+}
+
+impl GhostRace {
+    pub fn new(run: GhostRun) -> Self {
+        GhostRace {
+            run,
+            elapsed: 0.,
+            waves_finished: 0,
+            spawned: false,
+        }
+    }
+}
+
+/// Marker for the ghost guy
+#[derive(Debug, Component)]
+pub struct Ghost;
+
+/// Marker for the points ahead of or behind the ghost, in the HUD
+#[derive(Debug, Component)]
+pub struct GhostDeltaUi;
+
+/// system: start recording a new run
+pub fn start_recording(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    progress: Res<TutorialProgress>,
+    race: Option<Res<GhostRace>>,
+    resume: Option<Res<ResumeRun>>,
+) {
+    // the tutorial is skipped in races, as in any run on a given seed,
+    // and a resumed run was not recorded from the start
+    let eligible =
+        *mode == GameMode::Classic && (progress.completed || race.is_some()) && resume.is_none();
+    commands.insert_resource(GhostRecorder {
+        eligible,
+        ..default()
+    });
+}
+
+/// system: record the guy, the score of each wave, and the end of the run
+pub fn record(
+    time: Res<Time>,
+    scores: Res<GameScores>,
+    mut recorder: ResMut<GhostRecorder>,
+    query_guy: Query<(&SpatialPosition, &SpatialVelocity, &GuyState), With<PointerControlled>>,
+    query_bombs: Query<&BombTick>,
+    query_game_over: Query<(), Added<Stats>>,
+    mut event_reader: EventReader<WaveFinishedEvent>,
+) {
+    let waves_finished = event_reader.iter().count();
+    if !recorder.eligible || recorder.finished {
+        return;
+    }
+
+    for _ in 0..waves_finished {
+        recorder.run.wave_scores.push(scores.score);
+    }
+    if !query_game_over.is_empty() {
+        recorder.finished = true;
+        recorder.run.score = scores.score;
+    }
+
+    recorder.elapsed += time.delta_seconds();
+    let t = recorder.elapsed;
+    let due = recorder
+        .run
+        .samples
+        .last()
+        .map_or(true, |last| t - last.t >= SAMPLE_INTERVAL);
+    if !due {
+        return;
+    }
+    let Ok((position, velocity, state)) = query_guy.get_single() else {
+        return;
+    };
+    let pose = match state {
+        GuyState::Idle => GhostPose::Idle,
+        GuyState::Running => GhostPose::Running,
+        GuyState::Disarming { bomb_entity, .. } => {
+            let hurry = query_bombs
+                .get(*bomb_entity)
+                .is_ok_and(|bomb_tick| bomb_tick.ticks_left <= 1);
+            if hurry {
+                GhostPose::DisarmingHurry
+            } else {
+                GhostPose::Disarming
+            }
+        }
+        GuyState::Ouch => GhostPose::Ouch,
+        GuyState::Victorious => GhostPose::Victorious,
+        GuyState::Loser => GhostPose::Loser,
+    };
+    recorder.run.samples.push(GhostSample {
+        t,
+        pos: position.0.to_array(),
+        vel: velocity.0.truncate().to_array(),
+        pose,
+    });
+}
+
+/// system: once a run is left, keep it as the ghost if it is the best yet
+pub fn save_if_best(recorder: Res<GhostRecorder>, record: Res<RunRecord>, run_seed: Res<RunSeed>) {
+    if !recorder.eligible || !recorder.finished || record.assisted {
+        return;
+    }
+    let best_score = GhostRun::load().map(|best| best.score);
+    if best_score.is_some_and(|best_score| best_score >= recorder.run.score) {
+        return;
+    }
+
+    info!("New best run: {}", recorder.run.score);
+    let run = GhostRun {
+        seed: run_seed.seed,
+        ..recorder.run.clone()
+    };
+    crate::storage::save(GHOST_KEY, &run);
+}
+
+/// system: the race is over once the run is left
+pub fn end_race(mut commands: Commands) {
+    commands.remove_resource::<GhostRace>();
+}
+
+/// system: bring in the ghost guy, and the HUD showing how the race goes
+pub fn spawn_ghost(
+    mut commands: Commands,
+    race: Option<ResMut<GhostRace>>,
+    texture_atlas: Option<Res<GuyTextureAtlas>>,
+    font: Res<DefaultFont>,
+) {
+    let (Some(mut race), Some(texture_atlas)) = (race, texture_atlas) else {
+        return;
+    };
+    if race.spawned {
+        return;
+    }
+    race.spawned = true;
+
+    commands.spawn((
+        SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                color: Color::rgba(1., 1., 1., GHOST_ALPHA),
+                ..default()
+            },
+            texture_atlas: texture_atlas.0.clone(),
+            transform: Transform::from_scale(Vec3::splat(2.)),
+            ..default()
+        },
+        GuyState::Idle,
+        GuyAnimationTimer::default(),
+        SpatialVelocity::default(),
+        DisarmHurry::default(),
+        BaseTranslation(Vec2::new(0., -22.)),
+        Ghost,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 18.,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(48.),
+                left: Val::Px(12.),
+                ..default()
+            },
+            ..default()
+        }),
+        GhostDeltaUi,
+        HudText { base_size: 18. },
+        InField,
+    ));
+}
+
+/// system: walk the ghost guy along the recorded run
+pub fn play_ghost(
+    time: Res<Time>,
+    race: Option<ResMut<GhostRace>>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut GuyState,
+            &mut SpatialVelocity,
+            &mut DisarmHurry,
+        ),
+        With<Ghost>,
+    >,
+) {
+    let Some(mut race) = race else {
+        return;
+    };
+    race.elapsed += time.delta_seconds();
+
+    let samples = &race.run.samples;
+    let i = samples.partition_point(|sample| sample.t <= race.elapsed);
+    let (Some(from), to) = (samples.get(i.saturating_sub(1)), samples.get(i)) else {
+        return;
+    };
+    // in between samples, move smoothly
+    let pos = match to {
+        Some(to) if to.t > from.t => {
+            let s = ((race.elapsed - from.t) / (to.t - from.t)).clamp(0., 1.);
+            Vec3::from(from.pos).lerp(Vec3::from(to.pos), s)
+        }
+        _ => Vec3::from(from.pos),
+    };
+
+    for (mut transform, mut state, mut velocity, mut hurry) in &mut query {
+        // as in `spatial_position_to_transform`
+        transform.translation.x = pos.x;
+        transform.translation.y = pos.y + pos.z * 0.5;
+        velocity.0 = Vec2::from(from.vel).extend(0.);
+
+        let new_state = match from.pose {
+            GhostPose::Idle => GuyState::Idle,
+            GhostPose::Running => GuyState::Running,
+            GhostPose::Disarming | GhostPose::DisarmingHurry => GuyState::Disarming {
+                // the recorded bomb is long gone,
+                // how hurried the ghost looks comes from `DisarmHurry`
+                bomb_entity: Entity::from_raw(u32::MAX),
+                progress: 0.,
+            },
+            GhostPose::Ouch => GuyState::Ouch,
+            GhostPose::Victorious => GuyState::Victorious,
+            GhostPose::Loser => GuyState::Loser,
+        };
+        if *state != new_state {
+            *state = new_state;
+        }
+        hurry.0 = from.pose == GhostPose::DisarmingHurry;
+    }
+}
+
+/// system: at the end of each wave, show how far ahead of the ghost the player is
+pub fn show_delta(
+    scores: Res<GameScores>,
+    race: Option<ResMut<GhostRace>>,
+    mut event_reader: EventReader<WaveFinishedEvent>,
+    mut query_ui: Query<(Entity, &mut Text), With<GhostDeltaUi>>,
+    mut commands: Commands,
+) {
+    let waves_finished = event_reader.iter().count();
+    let Some(mut race) = race else {
+        return;
+    };
+
+    for _ in 0..waves_finished {
+        let wave = race.waves_finished;
+        race.waves_finished += 1;
+        let Some(ghost_score) = race.run.wave_scores.get(wave) else {
+            continue;
+        };
+
+        let delta = scores.score - ghost_score;
+        let (key, color) = match delta {
+            d if d > 0 => ("ghost_ahead", Color::rgb(0.4, 1., 0.4)),
+            d if d < 0 => ("ghost_behind", Color::TOMATO),
+            _ => ("ghost_even", Color::WHITE),
+        };
+        for (entity, mut text) in &mut query_ui {
+            text.sections[0].style.color = color;
+            commands
+                .entity(entity)
+                .insert(LocalizedText::new(key).with_arg("points", delta.abs()));
+        }
+    }
+}
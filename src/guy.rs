@@ -2,18 +2,23 @@
 
 use std::time::Duration;
 
-use bevy::prelude::*;
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+    reflect::TypeUuid,
+};
 
 use crate::{
     animation::ToggleVisibility,
     bomb::{BombState, BombTick},
-    events::{
-        BombDisarmedEvent, DisarmCancelledEvent, DisarmProgressEvent, ExplodedEvent, ExplosiveKind,
-        GuyHurtEvent,
-    },
+    events::{BombDisarmedEvent, DisarmCancelledEvent, ExplodedEvent, ExplosiveKind, GuyHurtEvent},
     helper::{BaseTranslation, DelayedRemoval},
+    ingame::GameMode,
     movement::{move_towards, Gravity, MaxSpeed, SpatialPosition, SpatialVelocity},
-    progress_bar::spawn_progress_bar,
+    pathfinding::NavGrid,
 };
 
 /// Bundle for all components of the fat guy.
@@ -25,12 +30,54 @@ pub struct GuyBundle {
     pub max_speed: MaxSpeed,
     pub performance: GuyPerformance,
     pub destination: GuyDestination,
+    pub waypoints: GuyWaypoints,
+    pub target: GuyTarget,
+    pub path: GuyPath,
     pub base_translation: BaseTranslation,
     pub animation_timer: GuyAnimationTimer,
     pub sprite_sheet: SpriteSheetBundle,
 }
 
-#[derive(Debug, Default, PartialEq, Component)]
+/// Which player a guy belongs to, counting from 0
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Component, Reflect)]
+#[reflect(Component)]
+pub struct Player(pub u8);
+
+impl Player {
+    /// The tint of the player's guy, to tell the guys apart
+    pub fn tint(self) -> Color {
+        match self.0 {
+            0 => Color::WHITE,
+            1 => Color::rgb(0.55, 0.8, 1.),
+            2 => Color::rgb(1., 0.7, 0.55),
+            _ => Color::rgb(0.7, 1., 0.6),
+        }
+    }
+}
+
+/// Whether a guy disarming is in a hurry,
+/// for guys which are not disarming a bomb on the field (such as ghosts)
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct DisarmHurry(pub bool);
+
+/// The sprite sheet of the guy, for others to use
+#[derive(Debug, Clone, Resource, Deref)]
+pub struct GuyTextureAtlas(pub Handle<TextureAtlas>);
+
+/// The guy's sprite sheet is kept under a fixed handle,
+/// so that snapshots of the play field can refer to it
+const GUY_TEXTURE_ATLAS: HandleUntyped =
+    HandleUntyped::weak_from_u64(TextureAtlas::TYPE_UUID, 0x6a1f_3c2e_94d0_b7e5);
+
+/// Marker for the guy moved around with the mouse or touch,
+/// which is the first player's
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct PointerControlled;
+
+#[derive(Debug, Default, PartialEq, Component, Reflect)]
+#[reflect(Component, MapEntities)]
 pub enum GuyState {
     /// Stopped, not doing anything
     #[default]
@@ -52,11 +99,31 @@ pub enum GuyState {
     Loser,
 }
 
+impl MapEntities for GuyState {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        if let GuyState::Disarming { bomb_entity, .. } = self {
+            match entity_map.get(*bomb_entity) {
+                Ok(entity) => *bomb_entity = entity,
+                // the bomb was left behind
+                Err(_) => *self = GuyState::Idle,
+            }
+        }
+        Ok(())
+    }
+}
+
 pub const GUY_BASE_SPEED: f32 = 150.;
 pub const GUY_BASE_PERFORMANCE: f32 = 0.25;
+/// the most guys on the field at once, in co-op
+pub const MAX_PLAYERS: u8 = 4;
+/// how close (squared) a bomb must be for an idle guy to walk up to it
+pub const BOMB_APPROACH_DIST_SQR: f32 = 750.;
+/// how close (squared) the guy must be to a bomb to start disarming it
+pub const BOMB_DISARM_DIST_SQR: f32 = 5.;
 
 /// The speed at which guy defuses bombs
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct GuyPerformance(pub f32);
 
 impl Default for GuyPerformance {
@@ -67,35 +134,91 @@ impl Default for GuyPerformance {
 
 /// The position that the character should move towards,
 /// as defined by the user
-#[derive(Debug, Component)]
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct GuyDestination(pub Vec2);
 
+/// The destinations queued up by the user,
+/// to be walked to in order after the current one
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct GuyWaypoints(pub Vec<Vec2>);
+
+/// The bomb or item which the user sent the guy to, if any
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct GuyTarget(pub Option<Entity>);
+
+impl MapEntities for GuyTarget {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        // a target left behind is no target
+        self.0 = self.0.and_then(|target| entity_map.get(target).ok());
+        Ok(())
+    }
+}
+
+/// The waypoints to walk through in order to reach the destination
+/// without bumping into obstacles
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct GuyPath {
+    /// the destination which this path was planned for
+    target: Option<Vec2>,
+    /// the remaining waypoints, ending at the destination
+    waypoints: Vec<Vec2>,
+}
+
+impl GuyPath {
+    /// Whether the guy has walked this path to its end
+    /// on the way to the given destination.
+    /// The end can fall short of the destination,
+    /// when the destination is inside an obstacle.
+    fn walked_to(&self, position: &SpatialPosition, destination: &GuyDestination) -> bool {
+        if self.target != Some(destination.0) {
+            return false;
+        }
+        match self.waypoints.last() {
+            Some(end) => has_reached(position, &GuyDestination(*end)),
+            None => true,
+        }
+    }
+
+    /// The remaining waypoints, ending at the destination
+    #[cfg(feature = "debug")]
+    pub fn waypoints(&self) -> &[Vec2] {
+        &self.waypoints
+    }
+}
+
 pub fn setup(
-    commands: Commands,
+    mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    (mode, gamepads): (Res<GameMode>, Res<Gamepads>),
 ) {
     let tex_guy: Handle<Image> = asset_server.load("img/fatguy.png");
 
     let texture_atlas = TextureAtlas::from_grid(tex_guy, Vec2::new(24.0, 32.0), 3, 7, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-    spawn_guy(
-        commands,
-        texture_atlas_handle,
-        &mut meshes,
-        &mut materials,
-        Vec2::new(180., 300.),
-    );
+    let texture_atlas_handle = texture_atlases.set(GUY_TEXTURE_ATLAS, texture_atlas);
+    commands.insert_resource(GuyTextureAtlas(texture_atlas_handle.clone()));
+
+    let n_players = mode.player_count(&gamepads);
+    for i in 0..n_players {
+        // side by side around the middle
+        let x = 180. + (i as f32 - (n_players - 1) as f32 / 2.) * 80.;
+        spawn_guy(
+            &mut commands,
+            texture_atlas_handle.clone(),
+            Player(i),
+            Vec2::new(x, 300.),
+        );
+    }
 }
 
 fn spawn_guy(
-    mut commands: Commands,
+    commands: &mut Commands,
     texture_atlas_handle: Handle<TextureAtlas>,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<ColorMaterial>>,
+    player: Player,
     pos: Vec2,
 ) -> Entity {
     let scale = 2.0;
@@ -108,8 +231,15 @@ fn spawn_guy(
             animation_timer: GuyAnimationTimer::default(),
             performance: GuyPerformance::default(),
             destination: GuyDestination(pos),
+            waypoints: GuyWaypoints::default(),
+            target: GuyTarget::default(),
+            path: GuyPath::default(),
             base_translation: BaseTranslation(Vec2::new(0., -22.)),
             sprite_sheet: SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    color: player.tint(),
+                    ..default()
+                },
                 texture_atlas: texture_atlas_handle,
                 transform: Transform {
                     scale: Vec3::splat(scale),
@@ -121,19 +251,21 @@ fn spawn_guy(
         })
         // additional components for temporary effects
         .insert((
+            player,
             ToggleVisibility::default(),
             DelayedRemoval::<ToggleVisibility>::new(Duration::from_millis(750)),
         ))
         .id();
 
-    let progress_bar = spawn_progress_bar(&mut commands, meshes, materials);
-
-    commands.entity(guy_id).add_child(progress_bar);
+    if player.0 == 0 {
+        commands.entity(guy_id).insert(PointerControlled);
+    }
 
     guy_id
 }
 
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct GuyAnimationTimer {
     main_timer: Timer,
     ouch_timer: Timer,
@@ -166,10 +298,11 @@ pub fn animate_guy(
         &mut TextureAtlasSprite,
         &GuyState,
         &SpatialVelocity,
+        Option<&DisarmHurry>,
     )>,
     bomb_query: Query<&BombTick>,
 ) {
-    for (mut timer, mut sprite, guy_state, velocity) in &mut query {
+    for (mut timer, mut sprite, guy_state, velocity, hurry) in &mut query {
         match *guy_state {
             GuyState::Idle => {
                 sprite.index = GUY_SPRITESHEET_DOWN_INDEX_START + 1;
@@ -227,18 +360,21 @@ pub fn animate_guy(
                 timer.main_timer.tick(time.delta());
 
                 // choose animation based on bomb ticks left
-                let base_index = {
+                let hurry = match hurry {
+                    Some(DisarmHurry(hurry)) => *hurry,
                     // get ticks left
-                    if let Ok(bomb_tick) = bomb_query.get(bomb_entity) {
-                        if bomb_tick.ticks_left <= 1 {
-                            GUY_SPRITESHEET_DEFUSE_HURRY_INDEX_START
-                        } else {
-                            GUY_SPRITESHEET_DEFUSE_INDEX_START
+                    None => match bomb_query.get(bomb_entity) {
+                        Ok(bomb_tick) => bomb_tick.ticks_left <= 1,
+                        Err(_) => {
+                            warn!("Bomb being disarmed ({:?}) is missing!", bomb_entity);
+                            false
                         }
-                    } else {
-                        warn!("Bomb being disarmed ({:?}) is missing!", bomb_entity);
-                        GUY_SPRITESHEET_DEFUSE_INDEX_START
-                    }
+                    },
+                };
+                let base_index = if hurry {
+                    GUY_SPRITESHEET_DEFUSE_HURRY_INDEX_START
+                } else {
+                    GUY_SPRITESHEET_DEFUSE_INDEX_START
                 };
 
                 if sprite.index < base_index {
@@ -272,25 +408,103 @@ pub fn animate_guy(
     }
 }
 
+/// Whether the guy is standing at the given destination
+pub fn has_reached(position: &SpatialPosition, destination: &GuyDestination) -> bool {
+    // same threshold as in `move_towards`
+    position.0.truncate().distance_squared(destination.0) <= 1.
+}
+
+/// system: move on to the next queued destination
+/// once the current one is reached,
+/// or as close to it as obstacles allow
+pub fn follow_waypoints(
+    mut query: Query<(
+        &GuyState,
+        &SpatialPosition,
+        &mut GuyDestination,
+        &mut GuyWaypoints,
+        &GuyPath,
+    )>,
+) {
+    for (guy_state, position, mut destination, mut waypoints, path) in &mut query {
+        // only when not busy with something else
+        if *guy_state != GuyState::Idle {
+            continue;
+        }
+        if !has_reached(position, &destination) && !path.walked_to(position, &destination) {
+            continue;
+        }
+        if let Some(next) = (!waypoints.0.is_empty()).then(|| waypoints.0.remove(0)) {
+            destination.0 = next;
+        }
+    }
+}
+
+/// system: plan a path around obstacles whenever the destination changes
+pub fn plan_path(
+    nav_grid: Res<NavGrid>,
+    mut query: Query<(
+        &mut GuyPath,
+        &SpatialPosition,
+        &BaseTranslation,
+        &GuyDestination,
+    )>,
+) {
+    for (mut path, position, base_translation, destination) in &mut query {
+        if path.target == Some(destination.0) && !nav_grid.is_changed() {
+            continue;
+        }
+
+        // plan in floor coordinates (where the guy's feet are)
+        let offset = base_translation.0;
+        let from = position.0.truncate() + offset;
+        let to = destination.0 + offset;
+        path.target = Some(destination.0);
+        path.waypoints = nav_grid
+            .find_path(from, to)
+            .unwrap_or_else(|| vec![to])
+            .into_iter()
+            .map(|p| p - offset)
+            .collect();
+    }
+}
+
 /// system to move guy to the given destination
 pub fn walk_to_destination(
-    event_writer: EventWriter<DisarmCancelledEvent>,
+    mut event_writer: EventWriter<DisarmCancelledEvent>,
     mut query: Query<(
         &mut SpatialVelocity,
         &mut GuyState,
         &MaxSpeed,
         &SpatialPosition,
         &GuyDestination,
+        &mut GuyPath,
     )>,
 ) {
-    if let Ok((mut vel, mut guy_state, guy_speed, position, destination)) = query.get_single_mut() {
+    for (mut vel, mut guy_state, guy_speed, position, destination, mut path) in &mut query {
+        // skip the waypoints already reached
+        // (the final one is handled by `walk_towards` as usual)
+        const WAYPOINT_REACH_SQR: f32 = 16.;
+        while path.waypoints.len() > 1
+            && position.0.truncate().distance_squared(path.waypoints[0]) <= WAYPOINT_REACH_SQR
+        {
+            path.waypoints.remove(0);
+        }
+
+        let target = path
+            .waypoints
+            .first()
+            .copied()
+            .map(GuyDestination)
+            .unwrap_or(GuyDestination(destination.0));
+
         walk_towards(
             &mut vel,
             &mut guy_state,
-            &guy_speed,
-            &position,
-            &destination,
-            event_writer,
+            guy_speed,
+            position,
+            &target,
+            &mut event_writer,
         );
     }
 }
@@ -301,7 +515,7 @@ pub(crate) fn walk_towards(
     guy_speed: &MaxSpeed,
     guy_pos: &Vec3,
     target: &GuyDestination,
-    mut cancel_event_writer: EventWriter<DisarmCancelledEvent>,
+    cancel_event_writer: &mut EventWriter<DisarmCancelledEvent>,
 ) {
     // do not walk on ouch or victorious
     if *guy_state == GuyState::Ouch || *guy_state == GuyState::Victorious {
@@ -321,16 +535,22 @@ pub(crate) fn walk_towards(
     }
 }
 
-/// system that handles disarming a bomb if guy is close enough to it
+/// what a guy needs to go for bombs and disarm them
+type DisarmingGuy<'a> = (
+    &'a mut GuyState,
+    &'a mut GuyDestination,
+    &'a mut GuyWaypoints,
+    &'a GuyTarget,
+    &'a SpatialPosition,
+    &'a BaseTranslation,
+    &'a GuyPerformance,
+    &'a Player,
+);
+
+/// system that handles disarming a bomb if a guy is close enough to it
 pub fn disarming_bomb(
     time: Res<Time>,
-    mut query_guy: Query<(
-        &mut GuyState,
-        &mut GuyDestination,
-        &SpatialPosition,
-        &BaseTranslation,
-        &GuyPerformance,
-    )>,
+    mut query_guy: Query<DisarmingGuy<'static>>,
     query_bombs: Query<(
         Entity,
         &SpatialPosition,
@@ -339,72 +559,121 @@ pub fn disarming_bomb(
         &BombTick,
     )>,
     mut bomb_disarmed_ev_writer: EventWriter<BombDisarmedEvent>,
-    mut disarm_progress_ev_writer: EventWriter<DisarmProgressEvent>,
 ) {
-    let Ok((mut guy_state, mut guy_destination, guy_position, guy_base_translation, perf)) = query_guy.get_single_mut() else {
-        return
-    };
-
-    match *guy_state {
-        GuyState::Running | GuyState::Ouch | GuyState::Victorious | GuyState::Loser => {
-            /* no-op */
-        }
-        GuyState::Idle => {
-            let guy_pos = guy_position.0 + guy_base_translation.0.extend(0.);
-            // look for bombs
-            let nearest_bomb = query_bombs
-                .iter()
-                .filter(|(_, _, _, state, _)| **state == BombState::Idle)
-                .map(|(entity, bomb_pos, bomb_base_translation, _, _)| {
+    // bombs disarmed by a guy in this frame,
+    // so that two guys do not disarm the same bomb twice
+    let mut disarmed: Vec<Entity> = vec![];
+
+    for (
+        mut guy_state,
+        mut guy_destination,
+        mut guy_waypoints,
+        guy_target,
+        guy_position,
+        guy_base_translation,
+        perf,
+        player,
+    ) in &mut query_guy
+    {
+        match *guy_state {
+            GuyState::Running | GuyState::Ouch | GuyState::Victorious | GuyState::Loser => {
+                /* no-op */
+            }
+            GuyState::Idle => {
+                let guy_pos = guy_position.0 + guy_base_translation.0.extend(0.);
+                let bomb_distance = |(entity, bomb_pos, bomb_base_translation, _, _): (
+                    Entity,
+                    &SpatialPosition,
+                    &BaseTranslation,
+                    &BombState,
+                    &BombTick,
+                )| {
                     let bomb_pos = bomb_pos.0 - bomb_base_translation.0.extend(0.);
                     let distance_sqr = guy_pos.distance_squared(bomb_pos);
                     (entity, bomb_pos, distance_sqr)
-                })
-                .filter(|(_, _, dist_sqr)| *dist_sqr < 750.)
-                .min_by(|(_, _, dist1), (_, _, dist2)| dist1.total_cmp(dist2));
-
-            if let Some((bomb_entity, bomb_pos, dist_sqr)) = nearest_bomb {
-                if dist_sqr < 5. {
-                    // start disarming!
-                    *guy_state = GuyState::Disarming {
-                        bomb_entity,
-                        progress: 0.,
-                    };
-                    // TODO(audio) play sound effect
+                };
+
+                // the bomb chosen by the user comes first, however far
+                let targeted_bomb = guy_target
+                    .0
+                    .and_then(|entity| query_bombs.get(entity).ok())
+                    .filter(|(_, _, _, state, _)| **state == BombState::Idle)
+                    .map(bomb_distance);
+
+                // otherwise look for bombs nearby,
+                // unless the user sent the guy to something else
+                let targets_other =
+                    matches!(guy_target.0, Some(entity) if !query_bombs.contains(entity));
+                let nearest_bomb = targeted_bomb.or_else(|| {
+                    if targets_other {
+                        return None;
+                    }
+                    query_bombs
+                        .iter()
+                        .filter(|(_, _, _, state, _)| **state == BombState::Idle)
+                        .map(bomb_distance)
+                        .filter(|(_, _, dist_sqr)| *dist_sqr < BOMB_APPROACH_DIST_SQR)
+                        .min_by(|(_, _, dist1), (_, _, dist2)| dist1.total_cmp(dist2))
+                });
+
+                if let Some((bomb_entity, bomb_pos, dist_sqr)) = nearest_bomb {
+                    if dist_sqr < BOMB_DISARM_DIST_SQR {
+                        // start disarming!
+                        *guy_state = GuyState::Disarming {
+                            bomb_entity,
+                            progress: 0.,
+                        };
+                        // TODO(audio) play sound effect
+                    }
+                    // not enough, but close. set destination
+                    let bomb_destination = bomb_pos.truncate() - guy_base_translation.0;
+                    if guy_destination.0 != bomb_destination
+                        && !has_reached(guy_position, &guy_destination)
+                    {
+                        // go back to where we were going afterwards
+                        guy_waypoints.0.insert(0, guy_destination.0);
+                    }
+                    guy_destination.0 = bomb_destination;
                 }
-                // not enough, but close. set destination
-                guy_destination.0 = bomb_pos.truncate() - guy_base_translation.0;
             }
-        }
-        GuyState::Disarming {
-            bomb_entity,
-            progress,
-        } => {
-            // continue disarming
-
-            // check ticks left to enter hurry mode
-            let perf = if let Ok((_, _, _, _, bomb_tick)) = query_bombs.get(bomb_entity) {
-                if bomb_tick.ticks_left <= 1 {
-                    perf.0 * 3.
+            GuyState::Disarming {
+                bomb_entity,
+                progress,
+            } => {
+                // another guy got there first
+                let still_idle = query_bombs
+                    .get(bomb_entity)
+                    .map_or(true, |(_, _, _, state, _)| *state == BombState::Idle);
+                if !still_idle || disarmed.contains(&bomb_entity) {
+                    *guy_state = GuyState::Idle;
+                    continue;
+                }
+
+                // continue disarming
+
+                // check ticks left to enter hurry mode
+                let perf = if let Ok((_, _, _, _, bomb_tick)) = query_bombs.get(bomb_entity) {
+                    if bomb_tick.ticks_left <= 1 {
+                        perf.0 * 3.
+                    } else {
+                        perf.0
+                    }
                 } else {
+                    warn!("Bomb being disarmed ({:?}) is missing!", bomb_entity);
                     perf.0
-                }
-            } else {
-                warn!("Bomb being disarmed ({:?}) is missing!", bomb_entity);
-                perf.0
-            };
-            let new_progress = progress + (perf * time.delta_seconds());
-            *guy_state = GuyState::Disarming {
-                bomb_entity,
-                progress: new_progress,
-            };
+                };
+                let new_progress = progress + (perf * time.delta_seconds());
+                *guy_state = GuyState::Disarming {
+                    bomb_entity,
+                    progress: new_progress,
+                };
 
-            if new_progress >= 1. {
-                // finished disarming!
-                bomb_disarmed_ev_writer.send(BombDisarmedEvent(bomb_entity));
-                *guy_state = GuyState::Idle;
-            } else {
-                disarm_progress_ev_writer.send(DisarmProgressEvent(new_progress));
+                if new_progress >= 1. {
+                    // finished disarming!
+                    bomb_disarmed_ev_writer.send(BombDisarmedEvent(bomb_entity, *player));
+                    disarmed.push(bomb_entity);
+                    *guy_state = GuyState::Idle;
+                }
             }
         }
     }
@@ -418,45 +687,57 @@ pub fn take_hit(
         &mut GuyState,
         &mut SpatialVelocity,
         &SpatialPosition,
+        &Player,
     )>,
     mut event_reader: EventReader<ExplodedEvent>,
     mut event_writer: EventWriter<GuyHurtEvent>,
+    #[cfg(feature = "console")] god_mode: Option<Res<crate::console::GodMode>>,
 ) {
-    let Ok((guy, mut guy_state, mut guy_vel, guy_pos)) = guy_query.get_single_mut() else {
-        return
-    };
-
-    const BOMB_EXPLOSION_RADIUS: f32 = 164.;
-    const DYNAMITE_EXPLOSION_RADIUS: f32 = 112.;
-
-    for event in event_reader.iter() {
-        let ExplodedEvent { kind, position } = event;
-
-        let guy_pos = guy_pos.0;
-        let diff_pos: Vec3 = guy_pos - *position;
-        let (r, intensity) = match kind {
-            ExplosiveKind::Dynamite => (DYNAMITE_EXPLOSION_RADIUS, 1.8),
-            ExplosiveKind::Bomb => (BOMB_EXPLOSION_RADIUS, 3.6),
-        };
-        if diff_pos.length_squared() < r * r {
-            *guy_state = GuyState::Ouch;
-
-            // add pushback effect on guy
-            guy_vel.0 = intensity * diff_pos + Vec3::new(0., 0., 400. * intensity);
-            commands.entity(guy).insert(Gravity::default());
+    let events: Vec<_> = event_reader.iter().collect();
+    if events.is_empty() {
+        return;
+    }
+    // cheating from the console
+    #[cfg(feature = "console")]
+    if god_mode.is_some() {
+        return;
+    }
 
-            // send event (so that it affects score)
-            event_writer.send(GuyHurtEvent { from: *kind });
+    for (guy, mut guy_state, mut guy_vel, guy_pos, player) in &mut guy_query {
+        for event in &events {
+            let ExplodedEvent { kind, position } = event;
 
-            // schedule guy recovery
-            commands
-                .entity(guy)
-                .insert(GuyRecovery::new(Duration::from_secs_f32(0.92 * intensity)));
+            let guy_pos = guy_pos.0;
+            let diff_pos: Vec3 = guy_pos - *position;
+            let r = kind.blast_radius();
+            let intensity = match kind {
+                ExplosiveKind::Dynamite => 1.8,
+                ExplosiveKind::Bomb => 3.6,
+            };
+            if diff_pos.length_squared() < r * r {
+                *guy_state = GuyState::Ouch;
+
+                // add pushback effect on guy
+                guy_vel.0 = intensity * diff_pos + Vec3::new(0., 0., 400. * intensity);
+                commands.entity(guy).insert(Gravity::default());
+
+                // send event (so that it affects score)
+                event_writer.send(GuyHurtEvent {
+                    from: *kind,
+                    player: *player,
+                });
+
+                // schedule guy recovery
+                commands
+                    .entity(guy)
+                    .insert(GuyRecovery::new(Duration::from_secs_f32(0.92 * intensity)));
+            }
         }
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct GuyRecovery(pub Timer);
 
 impl GuyRecovery {
@@ -480,15 +761,18 @@ pub fn recover(
         &mut GuyState,
         &mut GuyRecovery,
         &mut SpatialVelocity,
+        &mut GuyPath,
     )>,
 ) {
-    for (entity, mut guy_state, mut guy_recovery, mut velocity) in &mut query {
+    for (entity, mut guy_state, mut guy_recovery, mut velocity, mut path) in &mut query {
         guy_recovery.0.tick(time.delta());
         if guy_recovery.0.just_finished() {
             if *guy_state == GuyState::Ouch {
                 *guy_state = GuyState::Idle;
             }
             velocity.0 = Vec3::new(0., 0., 0.);
+            // got pushed away, so plan again from here
+            *path = GuyPath::default();
             commands
                 .entity(entity)
                 .remove::<GuyRecovery>()
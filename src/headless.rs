@@ -0,0 +1,81 @@
+//! running the game without a window, for automated regression runs.
+//!
+//! Start the game with `--headless` to have the autopilot play a run
+//! straight away, with no window, graphics or sound.
+//! Once the run is over, the final score is printed
+//! to the standard output and the game exits.
+
+use bevy::{
+    app::{AppExit, ScheduleRunnerSettings},
+    asset::AssetPlugin,
+    audio::AudioPlugin,
+    input::InputPlugin,
+    log::LogPlugin,
+    prelude::*,
+    ui::UiScale,
+    utils::Duration,
+    window::WindowPlugin,
+};
+
+use crate::{
+    scores::{GameScores, Stats},
+    DefaultFont,
+};
+
+/// Whether the game was started with `--headless`
+pub fn is_requested() -> bool {
+    std::env::args().any(|arg| arg == "--headless")
+}
+
+/// The plugins to run the game with, in place of the default ones,
+/// when there is no window
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+            1. / 60.,
+        )))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(LogPlugin::default())
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(InputPlugin)
+        .add_plugin(WindowPlugin {
+            add_primary_window: false,
+            exit_on_all_closed: false,
+            ..default()
+        })
+        .add_plugin(AssetPlugin::default())
+        .add_plugin(AudioPlugin)
+        // what the rendering plugins would otherwise provide
+        .add_asset::<Image>()
+        .add_asset::<TextureAtlas>()
+        .add_asset::<Font>()
+        .add_asset::<Mesh>()
+        .add_asset::<ColorMaterial>()
+        .init_resource::<UiScale>()
+        .add_startup_system(load_font)
+        .add_system(exit_at_game_over);
+    }
+}
+
+/// system: load the font which the menu would otherwise load,
+/// since the game starts right away
+fn load_font(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(DefaultFont(asset_server.load("font/Pixelme.ttf")));
+}
+
+/// system: print the final score and exit once the run is over
+fn exit_at_game_over(
+    scores: Res<GameScores>,
+    query_game_over: Query<(), Added<Stats>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if query_game_over.is_empty() {
+        return;
+    }
+    info!("Final score: {}", scores.score);
+    println!("{}", scores.score);
+    exit.send(AppExit);
+}
@@ -2,14 +2,17 @@ use std::marker::PhantomData;
 
 use bevy::{prelude::*, utils::Duration};
 
-#[derive(Debug, Component, Deref, DerefMut)]
+#[derive(Debug, Default, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct AnimationTimer(pub Timer);
 
 /// A fixed component not affected by Z depth adjustments
-#[derive(Debug, Component)]
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct Fixed;
 
-#[derive(Debug, Default, Component)]
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct BaseTranslation(pub Vec2);
 
 pub fn z_depth(mut query: Query<(&mut Transform, &BaseTranslation, Without<Fixed>)>) {
@@ -19,7 +22,8 @@ pub fn z_depth(mut query: Query<(&mut Transform, &BaseTranslation, Without<Fixed
 }
 
 /// For entities that are destroyed at the given time of death
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Reflect)]
+#[reflect(Component)]
 pub struct TimeToLive(Timer);
 
 impl TimeToLive {
@@ -48,13 +52,23 @@ pub fn destroy_on_ttl(
 }
 
 /// a component which is inserted on an entity only after a while
-#[derive(Component)]
-pub struct DelayedComponent<T: Component> {
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct DelayedComponent<T: Component + FromReflect> {
     component: Option<T>,
     timer: Timer,
 }
 
-impl<T: Component> DelayedComponent<T> {
+impl<T: Component + FromReflect> Default for DelayedComponent<T> {
+    fn default() -> Self {
+        DelayedComponent {
+            component: None,
+            timer: Timer::default(),
+        }
+    }
+}
+
+impl<T: Component + FromReflect> DelayedComponent<T> {
     pub fn new(component: T, after: Duration) -> Self {
         DelayedComponent {
             component: Some(component),
@@ -69,7 +83,7 @@ pub fn delayed_insertion<T>(
     time: Res<Time>,
     mut query: Query<(Entity, &mut DelayedComponent<T>)>,
 ) where
-    T: Component,
+    T: Component + FromReflect,
 {
     for (entity, mut delayed_component) in &mut query {
         delayed_component.timer.tick(time.delta());
@@ -89,12 +103,23 @@ pub fn delayed_insertion<T>(
 }
 
 /// a component which removes a component by type after a while
-#[derive(Component)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct DelayedRemoval<T: Component> {
+    #[reflect(ignore)]
     component: PhantomData<T>,
     timer: Timer,
 }
 
+impl<T: Component> Default for DelayedRemoval<T> {
+    fn default() -> Self {
+        DelayedRemoval {
+            component: PhantomData,
+            timer: Timer::default(),
+        }
+    }
+}
+
 impl<T: Component> DelayedRemoval<T> {
     pub fn new(after: Duration) -> Self {
         DelayedRemoval {
@@ -127,26 +152,34 @@ pub fn delayed_removal<T>(
 
 /// A component describing a generic event
 /// to be triggered once at a later time.
-#[derive(Component)]
-pub struct ScheduledEvent<E: Send> {
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct ScheduledEvent<E: Reflect + Default> {
     timer: Timer,
     event: E,
 }
 
-impl<E: Send> ScheduledEvent<E> {
+impl<E: Reflect + Default> ScheduledEvent<E> {
     pub fn new(event: E, after: Duration) -> Self {
         ScheduledEvent {
             timer: Timer::new(after, TimerMode::Once),
             event,
         }
     }
+
+    /// The time left until the event is triggered
+    #[cfg(feature = "debug")]
+    pub fn remaining(&self) -> Duration {
+        self.timer.remaining()
+    }
 }
 
-#[derive(Default, Component)]
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct DespawnOnTrigger;
 
 /// system: run scheduled events if it's their time to trigger
-pub fn run_scheduled_events<E: 'static + Send + Sync + Clone>(
+pub fn run_scheduled_events<E: Reflect + Default + Clone>(
     mut commands: Commands,
     time: Res<Time>,
     mut query: Query<(Entity, &mut ScheduledEvent<E>, Option<&DespawnOnTrigger>)>,
@@ -0,0 +1,379 @@
+//! hot seat: two to four players taking turns on the same run.
+//!
+//! Every turn is played on the tournament's seed,
+//! so everyone gets the same things thrown at the same places.
+//! Between turns, a hand-off screen tells whose turn it is,
+//! and once everyone has played, a leaderboard compares the results.
+//! Results are kept for as long as the game is open.
+
+use bevy::prelude::*;
+
+use crate::{
+    audio::GameSoundSources,
+    events::WaveFinishedEvent,
+    guy::{Player, MAX_PLAYERS},
+    i18n::LocalizedText,
+    ingame::GameMode,
+    menu::{fade_to, spawn_back_button, spawn_text, HOVER_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON},
+    scores::GameScores,
+    spawner::NextSeed,
+    AppState, DefaultFont, DelayedStateChange,
+};
+
+/// How one player's turn went
+#[derive(Debug, Clone)]
+pub struct TurnResult {
+    pub player: Player,
+    pub scores: GameScores,
+    /// the points made in each wave
+    pub wave_points: Vec<i32>,
+}
+
+/// A hot-seat tournament, from the first turn to the leaderboard
+#[derive(Debug, Clone, Resource)]
+pub struct Tournament {
+    /// the seed which every turn is played on
+    pub seed: u64,
+    pub players: u8,
+    /// the turns played so far, in order
+    pub results: Vec<TurnResult>,
+    /// the points made in each wave of the turn being played
+    wave_points: Vec<i32>,
+}
+
+impl Tournament {
+    pub fn new(players: u8) -> Self {
+        Tournament {
+            seed: rand::random(),
+            players: players.clamp(2, MAX_PLAYERS),
+            results: Vec::new(),
+            wave_points: Vec::new(),
+        }
+    }
+
+    /// The player whose turn is next, if anyone is left
+    pub fn current_player(&self) -> Option<Player> {
+        let turn = self.results.len() as u8;
+        (turn < self.players).then_some(Player(turn))
+    }
+
+    /// The results of the turns played, best first.
+    /// Ties go to whoever took fewer blasts.
+    pub fn standings(&self) -> Vec<&TurnResult> {
+        let mut standings: Vec<_> = self.results.iter().collect();
+        standings.sort_by_key(|result| {
+            (
+                std::cmp::Reverse(result.scores.score),
+                result.scores.blasts_taken,
+            )
+        });
+        standings
+    }
+}
+
+/// system: note the points made in each wave of a hot-seat turn
+pub fn track_waves(
+    mode: Res<GameMode>,
+    scores: Res<GameScores>,
+    tournament: Option<ResMut<Tournament>>,
+    mut event_reader: EventReader<WaveFinishedEvent>,
+) {
+    let Some(mut tournament) = tournament else {
+        return;
+    };
+    for _ in event_reader.iter() {
+        if *mode != GameMode::HotSeat {
+            continue;
+        }
+        let so_far: i32 = tournament.wave_points.iter().sum();
+        tournament.wave_points.push(scores.score - so_far);
+    }
+}
+
+/// system: once a hot-seat turn is left, record how it went
+pub fn record_turn(
+    mode: Res<GameMode>,
+    scores: Res<GameScores>,
+    tournament: Option<ResMut<Tournament>>,
+) {
+    let Some(mut tournament) = tournament else {
+        return;
+    };
+    if *mode != GameMode::HotSeat {
+        return;
+    }
+    let wave_points = std::mem::take(&mut tournament.wave_points);
+    if let Some(player) = tournament.current_player() {
+        tournament.results.push(TurnResult {
+            player,
+            scores: scores.clone(),
+            wave_points,
+        });
+    }
+}
+
+/// The buttons of the hot-seat screen
+#[derive(Debug, Copy, Clone, PartialEq, Component)]
+pub enum HotSeatButton {
+    /// start a tournament with this many players
+    Players(u8),
+    /// the next player is ready to play
+    Ready,
+    /// the same players again, on a new seed
+    Rematch,
+    /// back to picking how many players
+    New,
+}
+
+/// The contents of the hot-seat screen, rebuilt as the tournament moves on
+#[derive(Debug, Component)]
+pub struct HotSeatUi;
+
+/// system: set up the hot-seat screen
+pub fn setup(mut commands: Commands) {
+    // UI camera
+    commands.spawn(crate::screen::camera_bundle());
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    font: &Handle<Font>,
+    text: LocalizedText,
+    button: HotSeatButton,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(220.0), Val::Px(46.0)),
+                    margin: UiRect {
+                        top: Val::Px(8.),
+                        bottom: Val::Px(8.),
+                        ..default()
+                    },
+                    // horizontally center child text
+                    justify_content: JustifyContent::Center,
+                    // vertically center child text
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: NORMAL_BUTTON.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 28.0,
+                        color: Color::rgba(1., 1., 0.8, 1.0),
+                    },
+                ),
+                text,
+            ));
+        });
+}
+
+/// spawn the results of the turns played, best first
+fn spawn_standings(parent: &mut ChildBuilder, font: &Handle<Font>, tournament: &Tournament) {
+    for (rank, result) in tournament.standings().into_iter().enumerate() {
+        let scores = &result.scores;
+        spawn_text(
+            parent,
+            font,
+            24.,
+            result.player.tint(),
+            LocalizedText::new("hot_seat_rank")
+                .with_arg("rank", rank + 1)
+                .with_arg("player", result.player.0 + 1)
+                .with_arg("score", scores.score),
+        );
+        spawn_text(
+            parent,
+            font,
+            16.,
+            Color::rgb(0.85, 0.85, 0.85),
+            LocalizedText::new("hot_seat_breakdown")
+                .with_arg("bombs", scores.bombs_disarmed)
+                .with_arg("dynamites", scores.dynamites_disarmed)
+                .with_arg("blasts", scores.blasts_taken),
+        );
+        let waves = result
+            .wave_points
+            .iter()
+            .map(i32::to_string)
+            .collect::<Vec<_>>()
+            .join(" / ");
+        spawn_text(
+            parent,
+            font,
+            16.,
+            Color::rgb(0.85, 0.85, 0.85),
+            LocalizedText::new("hot_seat_waves").with_arg("points", waves),
+        );
+    }
+}
+
+/// system: show what the tournament is up to:
+/// picking players, handing off to the next one, or the leaderboard
+pub fn refresh(
+    mut commands: Commands,
+    font: Res<DefaultFont>,
+    tournament: Option<Res<Tournament>>,
+    query_ui: Query<Entity, With<HotSeatUi>>,
+    // how many turns were played in which tournament, as shown
+    mut shown: Local<Option<Option<(u64, usize)>>>,
+) {
+    let now = tournament.as_ref().map(|t| (t.seed, t.results.len()));
+    if *shown == Some(now) && !query_ui.is_empty() {
+        return;
+    }
+    *shown = Some(now);
+
+    for entity in &query_ui {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let font = font.0.clone();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            HotSeatUi,
+        ))
+        .with_children(|parent| {
+            let title = Color::WHITE;
+            match tournament.as_deref() {
+                None => {
+                    spawn_text(parent, &font, 36., title, LocalizedText::new("hot_seat"));
+                    spawn_text(
+                        parent,
+                        &font,
+                        20.,
+                        Color::rgb(0.85, 0.85, 0.85),
+                        LocalizedText::new("hot_seat_players"),
+                    );
+                    for players in 2..=MAX_PLAYERS {
+                        spawn_button(
+                            parent,
+                            &font,
+                            LocalizedText::new("hot_seat_n_players").with_arg("n", players),
+                            HotSeatButton::Players(players),
+                        );
+                    }
+                }
+                Some(tournament) => match tournament.current_player() {
+                    Some(player) => {
+                        spawn_text(
+                            parent,
+                            &font,
+                            32.,
+                            player.tint(),
+                            LocalizedText::new("hot_seat_turn").with_arg("player", player.0 + 1),
+                        );
+                        spawn_text(
+                            parent,
+                            &font,
+                            18.,
+                            Color::rgb(0.85, 0.85, 0.85),
+                            LocalizedText::new("hot_seat_same_run"),
+                        );
+                        spawn_button(
+                            parent,
+                            &font,
+                            LocalizedText::new("ready"),
+                            HotSeatButton::Ready,
+                        );
+                        spawn_standings(parent, &font, tournament);
+                    }
+                    None => {
+                        spawn_text(
+                            parent,
+                            &font,
+                            36.,
+                            title,
+                            LocalizedText::new("hot_seat_results"),
+                        );
+                        spawn_standings(parent, &font, tournament);
+                        spawn_button(
+                            parent,
+                            &font,
+                            LocalizedText::new("rematch"),
+                            HotSeatButton::Rematch,
+                        );
+                        spawn_button(
+                            parent,
+                            &font,
+                            LocalizedText::new("new_tournament"),
+                            HotSeatButton::New,
+                        );
+                    }
+                },
+            }
+
+            spawn_back_button(parent, font.clone());
+        });
+}
+
+/// interactions with the buttons of the hot-seat screen
+type HotSeatButtonChanged = (Changed<Interaction>, With<Button>);
+
+pub fn button_system(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &HotSeatButton),
+        HotSeatButtonChanged,
+    >,
+    tournament: Option<Res<Tournament>>,
+    audio: Res<Audio>,
+    sound_sources: Res<GameSoundSources>,
+    transition_entity: Query<Entity, With<DelayedStateChange>>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+
+                audio.play(sound_sources.click.cast_weak());
+
+                match (button, tournament.as_deref()) {
+                    (HotSeatButton::Players(players), _) => {
+                        commands.insert_resource(Tournament::new(*players));
+                    }
+                    // ensure that we don't spawn more than one
+                    (HotSeatButton::Ready, Some(tournament))
+                        if transition_entity.get_single().is_err() =>
+                    {
+                        commands.insert_resource(GameMode::HotSeat);
+                        commands.insert_resource(NextSeed(tournament.seed));
+                        fade_to(&mut commands, AppState::InGame);
+                    }
+                    (HotSeatButton::Rematch, Some(tournament)) => {
+                        commands.insert_resource(Tournament::new(tournament.players));
+                    }
+                    (HotSeatButton::New, _) => {
+                        commands.remove_resource::<Tournament>();
+                    }
+                    _ => {}
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVER_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
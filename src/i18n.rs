@@ -0,0 +1,193 @@
+//! localization: per-language string tables and font fallback.
+//!
+//! Each language has a table of strings in `assets/i18n`,
+//! written in RON and keyed by an identifier, for instance:
+//!
+//! ```ron
+//! {
+//!     "start": "Start",
+//!     // placeholders in braces are filled in by the game
+//!     "wave": "WAVE {n}",
+//! }
+//! ```
+//!
+//! Strings missing from a table fall back to English,
+//! and then to the key itself.
+//!
+//! The pixel font lacks accented letters and the like,
+//! so text which it cannot render is shown in a fallback font instead.
+
+use ab_glyph::Font as _;
+use bevy::{ecs::system::SystemParam, prelude::*, reflect::TypeUuid, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{settings::Settings, DefaultFont};
+
+/// The languages which the game is translated to
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    Portuguese,
+    Spanish,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::English, Language::Portuguese, Language::Spanish];
+
+    fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Portuguese => "pt",
+            Language::Spanish => "es",
+        }
+    }
+
+    /// The name of the language, in that language
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Portuguese => "Português",
+            Language::Spanish => "Español",
+        }
+    }
+
+    /// The language after this one, for cycling through them
+    pub fn next(self) -> Self {
+        let i = Language::ALL.iter().position(|l| *l == self).unwrap_or(0);
+        Language::ALL[(i + 1) % Language::ALL.len()]
+    }
+}
+
+/// The strings of one language, by key
+#[derive(Debug, Deserialize, TypeUuid)]
+#[serde(transparent)]
+#[uuid = "2f9c6d1e-8a4b-4c7e-b3f0-5e1a9d7c2b64"]
+pub struct StringTable(HashMap<String, String>);
+
+/// The handles to all string tables
+#[derive(Debug, Resource)]
+pub struct StringTables(HashMap<Language, Handle<StringTable>>);
+
+/// A font with a wide range of glyphs,
+/// for text which the default font cannot render
+#[derive(Debug, Clone, Resource, Deref)]
+pub struct FallbackFont(pub Handle<Font>);
+
+/// system: load all string tables and the fallback font
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let tables = Language::ALL
+        .iter()
+        .map(|language| {
+            let handle: Handle<StringTable> =
+                asset_server.load(format!("i18n/{}.lang.ron", language.code()));
+            (*language, handle)
+        })
+        .collect();
+    commands.insert_resource(StringTables(tables));
+
+    commands.insert_resource(FallbackFont(asset_server.load("font/DejaVuSans-Bold.ttf")));
+}
+
+/// Replace each `{name}` in the template with the value of that argument
+fn substitute(template: &str, args: &[(&str, String)]) -> String {
+    args.iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+/// Looks up strings in the current language
+#[derive(SystemParam)]
+pub struct Localizer<'w, 's> {
+    settings: Res<'w, Settings>,
+    tables: Res<'w, StringTables>,
+    assets: Res<'w, Assets<StringTable>>,
+    #[system_param(ignore)]
+    marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl<'w, 's> Localizer<'w, 's> {
+    fn lookup(&self, language: Language, key: &str) -> Option<&str> {
+        let table = self.assets.get(self.tables.0.get(&language)?)?;
+        table.0.get(key).map(String::as_str)
+    }
+
+    /// The string under the given key in the current language,
+    /// with its placeholders filled in
+    pub fn format(&self, key: &str, args: &[(&str, String)]) -> String {
+        let template = self
+            .lookup(self.settings.language, key)
+            .or_else(|| self.lookup(Language::English, key))
+            .unwrap_or(key);
+        substitute(template, args)
+    }
+}
+
+/// Text which is shown in the player's language.
+/// The first section of the entity's `Text` is kept up to date.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub struct LocalizedText {
+    pub key: String,
+    pub args: Vec<(&'static str, String)>,
+}
+
+impl LocalizedText {
+    pub fn new(key: impl Into<String>) -> Self {
+        LocalizedText {
+            key: key.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Fill in the placeholder `{name}` with the given value
+    pub fn with_arg(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+}
+
+/// Whether the font has a glyph for every character in the text
+fn covers(font: &Font, text: &str) -> bool {
+    text.chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .all(|c| font.font.glyph_id(c).0 != 0)
+}
+
+/// system: fill in localized texts,
+/// whenever they change or the language does
+pub fn localize_texts(
+    localizer: Localizer,
+    default_font: Option<Res<DefaultFont>>,
+    fallback_font: Res<FallbackFont>,
+    fonts: Res<Assets<Font>>,
+    mut table_events: EventReader<AssetEvent<StringTable>>,
+    mut font_events: EventReader<AssetEvent<Font>>,
+    mut query: Query<(&LocalizedText, ChangeTrackers<LocalizedText>, &mut Text)>,
+) {
+    let everything = localizer.settings.is_changed()
+        | (table_events.iter().count() > 0)
+        | (font_events.iter().count() > 0);
+
+    for (localized, tracker, mut text) in &mut query {
+        if !everything && !tracker.is_changed() {
+            continue;
+        }
+        let Some(section) = text.sections.first_mut() else {
+            continue;
+        };
+
+        section.value = localizer.format(&localized.key, &localized.args);
+
+        // fall back to a font which can render the text
+        if let Some(default_font) = &default_font {
+            let font = match fonts.get(&default_font.0) {
+                Some(font) if !covers(font, &section.value) => fallback_font.0.clone(),
+                _ => default_font.0.clone(),
+            };
+            if section.style.font != font {
+                section.style.font = font;
+            }
+        }
+    }
+}
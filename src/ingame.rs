@@ -1,16 +1,29 @@
-use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use bevy::{ecs::system::SystemParam, prelude::*, sprite::MaterialMesh2dBundle};
 
-use bevy::utils::Duration;
+use bevy::utils::{Duration, HashMap};
 use bevy_ecs_tilemap::prelude::TilemapId;
-use bevy_ecs_tilemap::tiles::{TilePos, TileTextureIndex};
+use serde::{Deserialize, Serialize};
 
+use crate::accessibility::HudText;
 use crate::animation::{spawn_fade_in_black_screen, BaseScale};
 use crate::audio::GameSoundSources;
 use crate::background::Background;
-use crate::guy::GuyDestination;
+use crate::game_feel::ShakyCamera;
+use crate::guy::{
+    has_reached, GuyDestination, GuyTarget, GuyWaypoints, Player, PointerControlled, MAX_PLAYERS,
+};
 use crate::helper::BaseTranslation;
+use crate::level::{CurrentLevel, LevelLayouts};
 use crate::menu::{HOVER_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON};
+use crate::movement::{PlayBounds, SpatialPosition};
+use crate::pathfinding::NavGrid;
+use crate::resume::ResumeRun;
 use crate::scores::GameScores;
+use crate::screen::{window_to_world, InField};
+use crate::settings::{ControlScheme, Settings};
+use crate::targeting::TargetPicker;
+use crate::tutorial::SkipTutorialButton;
+use crate::waves::{PreviewWave, WaveList};
 use crate::{
     animation::{FadeOut, Wobbly},
     helper::TimeToLive,
@@ -24,15 +37,74 @@ pub struct WaveUi;
 #[derive(Default, Resource)]
 pub struct Wave(pub u16);
 
+/// The ways of playing a run
+#[derive(
+    Debug,
+    Default,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Resource,
+    Serialize,
+    Deserialize,
+)]
+pub enum GameMode {
+    /// one player, waves as designed
+    #[default]
+    Classic,
+    /// two or more players on the same field,
+    /// the first one with the mouse or touch, the others with keyboard or gamepads
+    Coop,
+    /// players taking turns on the same run, see [`crate::hotseat`]
+    HotSeat,
+    /// the challenge of the day, see [`crate::daily`]
+    Daily,
+}
+
+impl GameMode {
+    /// The key of the mode's name in the string tables
+    pub fn name_key(self) -> &'static str {
+        match self {
+            GameMode::Classic => "mode_classic",
+            GameMode::Coop => "mode_coop",
+            GameMode::HotSeat => "mode_hot_seat",
+            GameMode::Daily => "mode_daily",
+        }
+    }
+
+    /// Where to go once a run is over
+    pub fn after_run(self) -> AppState {
+        match self {
+            GameMode::Classic | GameMode::Coop => AppState::MainMenu,
+            GameMode::HotSeat => AppState::HotSeat,
+            GameMode::Daily => AppState::Daily,
+        }
+    }
+
+    /// How many guys take part in a run.
+    /// In co-op, there is one more than there are gamepads,
+    /// as the keyboard counts for the second player.
+    pub fn player_count(self, gamepads: &Gamepads) -> u8 {
+        match self {
+            GameMode::Classic | GameMode::HotSeat | GameMode::Daily => 1,
+            GameMode::Coop => (1 + gamepads.iter().count() as u8).clamp(2, MAX_PLAYERS),
+        }
+    }
+}
+
 pub fn setup(
     mut commands: Commands,
     asset_server: ResMut<AssetServer>,
-    windows: Res<Windows>,
     default_font: Option<Res<DefaultFont>>,
-    query_background: Query<(Entity, &mut Transform), With<Background>>,
-    query_tiles: Query<(&TilemapId, &TilePos, &mut TileTextureIndex)>,
+    layouts: Res<LevelLayouts>,
+    (mode, gamepads, resume): (Res<GameMode>, Res<Gamepads>, Option<Res<ResumeRun>>),
+    preview: Option<Res<PreviewWave>>,
+    waves: Res<WaveList>,
 ) {
-    let window = windows.get_primary().unwrap();
     // load assets
 
     // text font
@@ -43,20 +115,33 @@ pub fn setup(
         f
     });
 
+    // a resumed run picks up at the start of the saved wave
+    let wave = resume.as_ref().map_or(0, |resume| resume.0.wave);
+
     // build background
-    crate::background::reset_background(query_background, query_tiles, 0, 1);
+    let level = match &preview {
+        Some(preview) => &preview.0.level,
+        None => crate::waves::wave_level(wave),
+    };
+    commands.insert_resource(CurrentLevel(layouts.get(level)));
 
-    let wave = 0;
     // initialize wave
     commands.insert_resource(Wave(wave));
 
     // initialize scores
-    commands.insert_resource(GameScores::default());
+    let n_players = mode.player_count(&gamepads);
+    commands.insert_resource(match &resume {
+        Some(resume) => resume.0.scores.clone(),
+        None => GameScores::new(n_players),
+    });
+
+    // start with a clear floor
+    commands.insert_resource(NavGrid::default());
 
     // wave number text
     commands.spawn((
         TextBundle::from_section(
-            "WAVE 0",
+            "",
             TextStyle {
                 font: font.clone(),
                 font_size: 32.,
@@ -76,20 +161,34 @@ pub fn setup(
             ..default()
         }),
         WaveUi,
+        HudText { base_size: 32. },
+        InField,
+        crate::waves::wave_text(wave, waves.count()),
     ));
 
     // 2D camera
-    commands.spawn(Camera2dBundle {
-        transform: Transform::from_translation(Vec3::from_array([
-            window.width() as f32 / 2.,
-            window.height() as f32 / 2.,
-            999.,
-        ])),
-        ..Default::default()
-    });
+    commands.spawn((
+        crate::screen::camera_bundle(),
+        ShakyCamera {
+            base: crate::screen::CAMERA_TRANSLATION,
+        },
+    ));
 
-    crate::scores::spawn_game_score_ui(&mut commands, font);
-    crate::waves::WAVE_DESCRIPTORS[0](commands);
+    crate::scores::spawn_game_score_ui(&mut commands, font.clone());
+    if n_players > 1 {
+        for i in 0..n_players {
+            crate::scores::spawn_player_score_ui(&mut commands, font.clone(), Player(i));
+        }
+    }
+    // a wave laid out in the editor is played on its own
+    match preview {
+        Some(preview) => preview.0.spawn(&mut commands),
+        None => {
+            if let Some(descriptor) = waves.get(wave) {
+                descriptor.spawn(commands);
+            }
+        }
+    }
 }
 
 pub fn touch_system_create_squares(
@@ -97,9 +196,15 @@ pub fn touch_system_create_squares(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     touches: Res<Touches>,
+    query_camera: Query<(&Camera, &GlobalTransform)>,
 ) {
+    let Ok((camera, camera_transform)) = query_camera.get_single() else {
+        return;
+    };
     for touch in touches.iter_just_pressed() {
-        spawn_square(&mut commands, &mut meshes, &mut materials, touch.position());
+        if let Some(pos) = window_to_world(camera, camera_transform, touch.position()) {
+            spawn_square(&mut commands, &mut meshes, &mut materials, pos);
+        }
     }
 }
 
@@ -109,38 +214,61 @@ pub fn mouse_handler(
     mouse_button_input: Res<Input<MouseButton>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    query_camera: Query<(&Camera, &GlobalTransform)>,
 ) {
-    let window = windows.get_primary().unwrap();
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = query_camera.get_single() else {
+        return;
+    };
     if mouse_button_input.just_pressed(MouseButton::Left) {
-        if let Some(pos) = window.cursor_position() {
+        if let Some(pos) = window
+            .cursor_position()
+            .and_then(|pos| window_to_world(camera, camera_transform, pos))
+        {
             spawn_square(&mut commands, &mut meshes, &mut materials, pos);
         }
     }
 }
 
-pub fn spawn_square(
+/// spawn a cyan marker on the floor
+fn spawn_marker(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<ColorMaterial>>,
     pos: Vec2,
-) {
+) -> Entity {
     let base_scale = 16.;
     let transform = Transform {
         translation: pos.extend(-0.125),
         rotation: Default::default(),
         scale: Vec3::new(base_scale, base_scale, 1.),
     };
-    commands.spawn((
-        MaterialMesh2dBundle {
-            mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-            transform,
-            material: materials.add(ColorMaterial::from(Color::CYAN)),
-            ..Default::default()
-        },
-        // keep track of base scale
-        BaseScale(base_scale),
-        // make it "wobble"
-        Wobbly,
+    commands
+        .spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
+                transform,
+                material: materials.add(ColorMaterial::from(Color::CYAN)),
+                ..Default::default()
+            },
+            // keep track of base scale
+            BaseScale(base_scale),
+            // make it "wobble"
+            Wobbly,
+        ))
+        .id()
+}
+
+pub fn spawn_square(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    pos: Vec2,
+) {
+    let e = spawn_marker(commands, meshes, materials, pos);
+    commands.entity(e).insert((
         // fade out after a while
         FadeOut::new(Duration::from_millis(600)),
         // destroy after a while
@@ -148,44 +276,405 @@ pub fn spawn_square(
     ));
 }
 
+/// The mouse and touches, mapped to the world through the camera
+#[derive(SystemParam)]
+pub struct Pointer<'w, 's> {
+    windows: Res<'w, Windows>,
+    mouse_button_input: Res<'w, Input<MouseButton>>,
+    touches: Res<'w, Touches>,
+    query_camera: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+}
+
+impl<'w, 's> Pointer<'w, 's> {
+    pub fn touches(&self) -> &Touches {
+        &self.touches
+    }
+
+    /// Map a window position to the world
+    pub fn to_world(&self, pos: Vec2) -> Option<Vec2> {
+        let (camera, camera_transform) = self.query_camera.get_single().ok()?;
+        window_to_world(camera, camera_transform, pos)
+    }
+
+    /// Where the mouse was just clicked, if it was
+    pub fn just_clicked(&self) -> Option<Vec2> {
+        if !self.mouse_button_input.just_pressed(MouseButton::Left) {
+            return None;
+        }
+        self.to_world(self.windows.get_primary()?.cursor_position()?)
+    }
+
+    /// Where the mouse button or a finger is held down, if anywhere
+    pub fn held(&self) -> Option<Vec2> {
+        let pos = if self.mouse_button_input.pressed(MouseButton::Left) {
+            self.windows.get_primary()?.cursor_position()?
+        } else {
+            self.touches.first_pressed_position()?
+        };
+        self.to_world(pos)
+    }
+
+    /// Whether the mouse button or a finger was just pressed
+    pub fn just_pressed(&self) -> bool {
+        self.mouse_button_input.just_pressed(MouseButton::Left) || self.touches.any_just_pressed()
+    }
+}
+
+/// How long a touch must be held to queue up a destination
+const LONG_PRESS: Duration = Duration::from_millis(450);
+
+/// How far a touch may move and still count as a long press
+const TOUCH_SLOP: f32 = 12.;
+
+/// Touches currently held, by ID:
+/// when they started, and whether they were already handled
+#[derive(Debug, Default)]
+pub struct HeldTouches(HashMap<u64, (Duration, bool)>);
+
 pub fn touch_set_destination(
-    touches: Res<Touches>,
-    mut query: Query<(&mut GuyDestination, &BaseTranslation)>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    pointer: Pointer,
+    bounds: Res<PlayBounds>,
+    picker: TargetPicker,
+    mut query: Query<
+        (
+            &mut GuyDestination,
+            &mut GuyWaypoints,
+            &mut GuyTarget,
+            &BaseTranslation,
+        ),
+        With<PointerControlled>,
+    >,
+    mut held: Local<HeldTouches>,
 ) {
+    if settings.control_scheme != ControlScheme::Tap {
+        held.0.clear();
+        return;
+    }
+    let Ok((mut destination, mut waypoints, mut target, base_translation)) = query.get_single_mut()
+    else {
+        return;
+    };
+    let to_destination = |pos: Vec2| {
+        // clamp to floor
+        bounds.clamp(pos - base_translation.0)
+    };
+    let touches = pointer.touches();
+
     for touch in touches.iter_just_pressed() {
-        if let Ok((mut destination, base_translation)) = query.get_single_mut() {
-            destination.0 = touch.position() - base_translation.0;
-            // clamp to floor
-            destination.0.y = destination.0.y.min(490.);
+        held.0.insert(touch.id(), (time.elapsed(), false));
+    }
+
+    // long press: queue up another destination
+    for touch in touches.iter() {
+        let Some((start, handled)) = held.0.get_mut(&touch.id()) else {
+            continue;
+        };
+        if !*handled
+            && time.elapsed() - *start >= LONG_PRESS
+            && touch.distance().length() < TOUCH_SLOP
+        {
+            *handled = true;
+            if let Some(pos) = pointer.to_world(touch.position()) {
+                waypoints.0.push(to_destination(pos));
+            }
         }
     }
+
+    // tap: go there right away, dropping the queue
+    for touch in touches.iter_just_released() {
+        let Some((_, handled)) = held.0.remove(&touch.id()) else {
+            continue;
+        };
+        if !handled {
+            if let Some(pos) = pointer.to_world(touch.position()) {
+                // go for the bomb or item touched, if any
+                target.0 = picker.pick(pos);
+                if target.0.is_none() {
+                    destination.0 = to_destination(pos);
+                }
+                waypoints.0.clear();
+            }
+        }
+    }
+    for touch in touches.iter_just_cancelled() {
+        held.0.remove(&touch.id());
+    }
 }
 
 pub fn mouse_set_destination(
-    windows: Res<Windows>,
-    mouse_button_input: Res<Input<MouseButton>>,
-    mut query: Query<(&mut GuyDestination, &BaseTranslation)>,
+    settings: Res<Settings>,
+    pointer: Pointer,
+    keyboard_input: Res<Input<KeyCode>>,
+    bounds: Res<PlayBounds>,
+    picker: TargetPicker,
+    mut query: Query<
+        (
+            &mut GuyDestination,
+            &mut GuyWaypoints,
+            &mut GuyTarget,
+            &BaseTranslation,
+        ),
+        With<PointerControlled>,
+    >,
 ) {
-    let window = windows.get_primary().unwrap();
-    if mouse_button_input.just_pressed(MouseButton::Left) {
-        if let Some(pos) = window.cursor_position() {
-            if let Ok((mut destination, base_translation)) = query.get_single_mut() {
-                destination.0 = pos - base_translation.0;
-                // clamp to floor
-                destination.0.y = destination.0.y.min(490.);
+    if settings.control_scheme != ControlScheme::Tap {
+        return;
+    }
+    if let Some(pos) = pointer.just_clicked() {
+        if let Ok((mut destination, mut waypoints, mut target, base_translation)) =
+            query.get_single_mut()
+        {
+            let picked = picker.pick(pos);
+            // clamp to floor
+            let pos = bounds.clamp(pos - base_translation.0);
+            if keyboard_input.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+                // queue it up
+                waypoints.0.push(pos);
+            } else {
+                // go for the bomb or item clicked, if any
+                target.0 = picked;
+                if picked.is_none() {
+                    destination.0 = pos;
+                }
+                waypoints.0.clear();
             }
         }
     }
 }
 
-pub fn button_system(
+/// system: in the hold-to-steer control scheme,
+/// keep sending the guy towards the pointer while it is held down,
+/// and stop him where he is once it is released
+pub fn steer_with_pointer(
+    settings: Res<Settings>,
+    pointer: Pointer,
+    bounds: Res<PlayBounds>,
+    picker: TargetPicker,
+    mut query: Query<
+        (
+            &mut GuyDestination,
+            &mut GuyWaypoints,
+            &mut GuyTarget,
+            &SpatialPosition,
+            &BaseTranslation,
+        ),
+        With<PointerControlled>,
+    >,
+    // whether the pointer is held down, and if so,
+    // whether it locked onto a target instead of steering
+    mut holding: Local<Option<bool>>,
+) {
+    if settings.control_scheme != ControlScheme::HoldToSteer {
+        *holding = None;
+        return;
+    }
+    let Ok((mut destination, mut waypoints, mut target, position, base_translation)) =
+        query.get_single_mut()
+    else {
+        return;
+    };
+
+    let Some(pos) = pointer.held() else {
+        if holding.take() == Some(false) {
+            // released: stop right there
+            destination.0 = position.0.truncate();
+        }
+        return;
+    };
+
+    if pointer.just_pressed() || holding.is_none() {
+        // pressing on a bomb or item goes for it, as with tapping
+        let picked = picker.pick(pos);
+        *holding = Some(picked.is_some());
+        if picked.is_some() {
+            target.0 = picked;
+            waypoints.0.clear();
+        }
+    }
+    if *holding == Some(true) {
+        return;
+    }
+
+    if target.0.is_some() {
+        target.0 = None;
+    }
+    if !waypoints.0.is_empty() {
+        waypoints.0.clear();
+    }
+    // clamp to floor
+    let to = bounds.clamp(pos - base_translation.0);
+    if destination.0 != to {
+        destination.0 = to;
+    }
+}
+
+/// how far ahead of a guy steered with keys or a stick his destination is put
+const PAD_LEAD: f32 = 24.;
+/// stick positions closer to the middle than this are ignored
+const STICK_DEADZONE: f32 = 0.25;
+
+/// The direction in which keys or a gamepad are steering
+fn pad_direction(
+    keyboard: Option<&Input<KeyCode>>,
+    gamepad: Option<Gamepad>,
+    axes: &Axis<GamepadAxis>,
+    buttons: &Input<GamepadButton>,
+) -> Vec2 {
+    let mut dir = Vec2::ZERO;
+
+    if let Some(keyboard) = keyboard {
+        let key_axis = |neg: [KeyCode; 2], pos: [KeyCode; 2]| {
+            keyboard.any_pressed(pos) as i8 as f32 - keyboard.any_pressed(neg) as i8 as f32
+        };
+        dir.x += key_axis([KeyCode::A, KeyCode::Left], [KeyCode::D, KeyCode::Right]);
+        dir.y += key_axis([KeyCode::S, KeyCode::Down], [KeyCode::W, KeyCode::Up]);
+    }
+
+    if let Some(gamepad) = gamepad {
+        let axis = |axis_type| axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.);
+        let stick = Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        if stick.length() > STICK_DEADZONE {
+            dir += stick;
+        }
+
+        let button = |button_type| buttons.pressed(GamepadButton::new(gamepad, button_type));
+        dir.x += button(GamepadButtonType::DPadRight) as i8 as f32
+            - button(GamepadButtonType::DPadLeft) as i8 as f32;
+        dir.y += button(GamepadButtonType::DPadUp) as i8 as f32
+            - button(GamepadButtonType::DPadDown) as i8 as f32;
+    }
+
+    dir.clamp_length_max(1.)
+}
+
+/// system: in co-op, steer the guys not controlled by the pointer
+/// with the keyboard (second player only) and gamepads,
+/// stopping them where they are once let go
+pub fn steer_with_pad(
+    keyboard: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    (axes, buttons): (Res<Axis<GamepadAxis>>, Res<Input<GamepadButton>>),
+    bounds: Res<PlayBounds>,
+    mut query: Query<
+        (
+            &Player,
+            &mut GuyDestination,
+            &mut GuyWaypoints,
+            &mut GuyTarget,
+            &SpatialPosition,
+        ),
+        Without<PointerControlled>,
+    >,
+    // whether each player was steering in the last frame
+    mut steering: Local<HashMap<Player, bool>>,
+) {
+    for (player, mut destination, mut waypoints, mut target, position) in &mut query {
+        // the second player shares the keyboard with the first gamepad,
+        // every other player has a gamepad of their own
+        let keyboard = (player.0 == 1).then_some(&*keyboard);
+        let gamepad = gamepads.iter().nth(player.0.saturating_sub(1) as usize);
+        let dir = pad_direction(keyboard, gamepad, &axes, &buttons);
+
+        let was_steering = steering.insert(*player, dir != Vec2::ZERO) == Some(true);
+        if dir == Vec2::ZERO {
+            if was_steering {
+                // let go: stop right there
+                destination.0 = position.0.truncate();
+            }
+            continue;
+        }
+
+        if target.0.is_some() {
+            target.0 = None;
+        }
+        if !waypoints.0.is_empty() {
+            waypoints.0.clear();
+        }
+        destination.0 = bounds.clamp(position.0.truncate() + dir * PAD_LEAD);
+    }
+}
+
+/// Marks a queued destination of the guy
+#[derive(Debug, Component)]
+pub struct WaypointMarker(Vec2);
+
+/// system: show markers on the destinations queued up,
+/// until the guy gets there
+pub fn update_waypoint_markers(
     mut commands: Commands,
-    mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor, &Children),
-        (Changed<Interaction>, With<Button>),
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query_guy: Query<
+        (
+            &GuyDestination,
+            &GuyWaypoints,
+            &SpatialPosition,
+            &BaseTranslation,
+        ),
+        With<PointerControlled>,
     >,
+    query_markers: Query<(Entity, &WaypointMarker)>,
+) {
+    let Ok((destination, waypoints, position, base_translation)) = query_guy.get_single() else {
+        return;
+    };
+
+    let mut wanted: Vec<Vec2> = waypoints.0.to_vec();
+    // keep the marker of the queued destination being walked to
+    if !has_reached(position, destination)
+        && query_markers
+            .iter()
+            .any(|(_, marker)| marker.0 == destination.0)
+    {
+        wanted.push(destination.0);
+    }
+
+    for (entity, marker) in &query_markers {
+        if let Some(i) = wanted.iter().position(|pos| *pos == marker.0) {
+            wanted.swap_remove(i);
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for pos in wanted {
+        let e = spawn_marker(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            pos + base_translation.0,
+        );
+        commands.entity(e).insert(WaypointMarker(pos));
+    }
+}
+
+/// interactions with the button leaving the game over screen
+type GameOverButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut BackgroundColor,
+        &'static Children,
+    ),
+    (
+        Changed<Interaction>,
+        With<Button>,
+        Without<SkipTutorialButton>,
+    ),
+>;
+
+pub fn button_system(
+    mut commands: Commands,
+    mut interaction_query: GameOverButtonQuery,
     audio: Res<Audio>,
     sound_sources: Res<GameSoundSources>,
+    mode: Res<GameMode>,
     transition_entity: Query<Entity, With<DelayedStateChange>>,
 ) {
     for (interaction, mut color, _children) in &mut interaction_query {
@@ -200,7 +689,7 @@ pub fn button_system(
                     // schedule app state transition
                     let e = spawn_fade_in_black_screen(&mut commands, Duration::from_millis(400));
                     commands.entity(e).insert(DelayedStateChange::new(
-                        AppState::MainMenu,
+                        mode.after_run(),
                         Duration::from_millis(750),
                     ));
                 }
@@ -0,0 +1,549 @@
+//! online leaderboard: scores posted at the end of each run,
+//! and the best ones of the game mode shown on the game over screen.
+//!
+//! The service behind it is up to the [`LeaderboardBackend`].
+//! The one over HTTP speaks a small JSON protocol:
+//!
+//! - `POST {url}/scores` with a [`Submission`] posts the scores of a run;
+//! - `GET {url}/scores?mode=Classic&limit=10` gives the best submissions
+//!   of a game mode, as a list, best first.
+//!
+//! `cargo run --bin leaderboard-server` serves it locally for testing,
+//! and `--leaderboard local` keeps a leaderboard in memory instead,
+//! for when no network is at hand.
+
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, TryRecvError};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    achievements::RunRecord,
+    i18n::LocalizedText,
+    ingame::GameMode,
+    scores::{GameScores, Stats},
+    screen::InField,
+    spawner::RunSeed,
+    DefaultFont,
+};
+
+/// How many of the best submissions are shown
+const TOP_N: usize = 5;
+
+/// How long a request to the leaderboard may take, in seconds,
+/// before it is given up on
+#[cfg(not(target_arch = "wasm32"))]
+const REQUEST_TIMEOUT: u64 = 10;
+
+/// How a run scored
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub score: i32,
+    pub bombs_disarmed: u32,
+    pub dynamites_disarmed: u32,
+    pub blasts_taken: u32,
+}
+
+impl From<&GameScores> for ScoreBreakdown {
+    fn from(scores: &GameScores) -> Self {
+        ScoreBreakdown {
+            score: scores.score,
+            bombs_disarmed: scores.bombs_disarmed,
+            dynamites_disarmed: scores.dynamites_disarmed,
+            blasts_taken: scores.blasts_taken,
+        }
+    }
+}
+
+/// The scores of a run, as posted to a leaderboard
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Submission {
+    /// the seed which the run was played on
+    pub seed: u64,
+    pub mode: GameMode,
+    pub breakdown: ScoreBreakdown,
+}
+
+/// Sort submissions best first.
+/// Ties go to whoever took fewer blasts.
+fn rank(submissions: &mut [Submission]) {
+    submissions.sort_by_key(|submission| {
+        (
+            std::cmp::Reverse(submission.breakdown.score),
+            submission.breakdown.blasts_taken,
+        )
+    });
+}
+
+/// The answer to a request to a leaderboard, which may take a while to come
+pub struct Pending<T>(Receiver<Result<T, String>>);
+
+impl<T> Pending<T> {
+    /// A request answered right away
+    fn ready(result: Result<T, String>) -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded(1);
+        // cannot fail, the receiver is right here
+        let _ = sender.send(result);
+        Pending(receiver)
+    }
+
+    /// The answer, if it came
+    pub fn poll(&self) -> Option<Result<T, String>> {
+        match self.0.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("No answer".to_string())),
+        }
+    }
+}
+
+/// A service keeping the best scores
+pub trait LeaderboardBackend: Send + Sync + 'static {
+    /// Post the scores of a run
+    fn submit(&self, submission: Submission) -> Pending<()>;
+
+    /// The `n` best submissions of a game mode, best first
+    fn fetch_top(&self, mode: GameMode, n: usize) -> Pending<Vec<Submission>>;
+}
+
+/// A leaderboard kept in memory, for as long as the game is open
+#[derive(Debug, Default)]
+pub struct LocalLeaderboard {
+    submissions: Mutex<Vec<Submission>>,
+}
+
+impl LeaderboardBackend for LocalLeaderboard {
+    fn submit(&self, submission: Submission) -> Pending<()> {
+        let mut submissions = self.submissions.lock().unwrap();
+        submissions.push(submission);
+        rank(&mut submissions);
+        Pending::ready(Ok(()))
+    }
+
+    fn fetch_top(&self, mode: GameMode, n: usize) -> Pending<Vec<Submission>> {
+        let submissions = self.submissions.lock().unwrap();
+        let top = submissions
+            .iter()
+            .filter(|submission| submission.mode == mode)
+            .take(n)
+            .cloned()
+            .collect();
+        Pending::ready(Ok(top))
+    }
+}
+
+/// A leaderboard service over HTTP or HTTPS, see the module documentation
+#[derive(Debug, Clone)]
+pub struct HttpLeaderboard {
+    /// the URL which the paths of the protocol are appended to
+    pub base_url: String,
+}
+
+impl HttpLeaderboard {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        HttpLeaderboard {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Where to get the `n` best submissions of a game mode
+    fn top_url(&self, mode: GameMode, n: usize) -> Result<String, String> {
+        let mode = mode_name(mode)?;
+        Ok(format!(
+            "{}/scores?mode={}&limit={}",
+            self.base_url,
+            utf8_percent_encode(&mode, NON_ALPHANUMERIC),
+            n
+        ))
+    }
+}
+
+/// The name of a game mode as the leaderboard knows it,
+/// the same as the `mode` of a [`Submission`]
+fn mode_name(mode: GameMode) -> Result<String, String> {
+    match serde_json::to_value(mode) {
+        Ok(serde_json::Value::String(name)) => Ok(name),
+        Ok(value) => Err(format!("Unexpected game mode `{}`", value)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+impl LeaderboardBackend for HttpLeaderboard {
+    fn submit(&self, submission: Submission) -> Pending<()> {
+        let body = match serde_json::to_string(&submission) {
+            Ok(body) => body,
+            Err(e) => return Pending::ready(Err(e.to_string())),
+        };
+        let url = format!("{}/scores", self.base_url);
+        spawn_request("POST", url, Some(body), |_| Ok(()))
+    }
+
+    fn fetch_top(&self, mode: GameMode, n: usize) -> Pending<Vec<Submission>> {
+        let url = match self.top_url(mode, n) {
+            Ok(url) => url,
+            Err(e) => return Pending::ready(Err(e)),
+        };
+        spawn_request("GET", url, None, |body| {
+            serde_json::from_str(&body).map_err(|e| e.to_string())
+        })
+    }
+}
+
+/// Make an HTTP request in the background,
+/// with the body of the response parsed once it comes
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_request<T: Send + 'static>(
+    method: &'static str,
+    url: String,
+    body: Option<String>,
+    parse: fn(String) -> Result<T, String>,
+) -> Pending<T> {
+    let (sender, receiver) = crossbeam_channel::bounded(1);
+    std::thread::spawn(move || {
+        let result = request(method, &url, body.as_deref()).and_then(parse);
+        let _ = sender.send(result);
+    });
+    Pending(receiver)
+}
+
+/// A blocking HTTP request, returning the body of the response
+#[cfg(not(target_arch = "wasm32"))]
+fn request(method: &str, url: &str, body: Option<&str>) -> Result<String, String> {
+    let method = match method {
+        "GET" => minreq::Method::Get,
+        "POST" => minreq::Method::Post,
+        method => minreq::Method::Custom(method.to_string()),
+    };
+    let mut request = minreq::Request::new(method, url).with_timeout(REQUEST_TIMEOUT);
+    if let Some(body) = body {
+        request = request
+            .with_header("Content-Type", "application/json")
+            .with_body(body);
+    }
+    let response = request.send().map_err(|e| e.to_string())?;
+    if !(200..300).contains(&response.status_code) {
+        return Err(format!("HTTP status {}", response.status_code));
+    }
+    response
+        .as_str()
+        .map(String::from)
+        .map_err(|e| e.to_string())
+}
+
+/// Make an HTTP request in the background,
+/// with the body of the response parsed once it comes
+#[cfg(target_arch = "wasm32")]
+fn spawn_request<T: Send + 'static>(
+    method: &'static str,
+    url: String,
+    body: Option<String>,
+    parse: fn(String) -> Result<T, String>,
+) -> Pending<T> {
+    let (sender, receiver) = crossbeam_channel::bounded(1);
+    wasm_bindgen_futures::spawn_local(async move {
+        let result = fetch(method, &url, body).await.and_then(parse);
+        let _ = sender.send(result);
+    });
+    Pending(receiver)
+}
+
+/// An HTTP request with `fetch`, returning the body of the response
+#[cfg(target_arch = "wasm32")]
+async fn fetch(method: &str, url: &str, body: Option<String>) -> Result<String, String> {
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+
+    let js_error = |e: JsValue| format!("{:?}", e);
+
+    let mut init = web_sys::RequestInit::new();
+    init.method(method);
+    if let Some(body) = &body {
+        init.body(Some(&JsValue::from_str(body)));
+    }
+    let request = web_sys::Request::new_with_str_and_init(url, &init).map_err(js_error)?;
+    request
+        .headers()
+        .set("Content-Type", "application/json")
+        .map_err(js_error)?;
+
+    let window = web_sys::window().ok_or("No window")?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_request(&request))
+        .await
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(js_error)?;
+    if !response.ok() {
+        return Err(format!("HTTP status {}", response.status()));
+    }
+    let text = JsFuture::from(response.text().map_err(js_error)?)
+        .await
+        .map_err(js_error)?;
+    text.as_string()
+        .ok_or_else(|| "Malformed response".to_string())
+}
+
+/// The leaderboard which scores are posted to
+#[derive(Resource)]
+pub struct Leaderboard(pub Box<dyn LeaderboardBackend>);
+
+impl Leaderboard {
+    /// The leaderboard asked for with `--leaderboard <url>`,
+    /// or `--leaderboard local` for one kept in memory,
+    /// falling back to the URL in `TIMELY_DEFUSE_LEADERBOARD` at build time
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args().skip_while(|arg| arg != "--leaderboard");
+        let url = args
+            .nth(1)
+            .or_else(|| option_env!("TIMELY_DEFUSE_LEADERBOARD").map(String::from))?;
+        let backend: Box<dyn LeaderboardBackend> = if url == "local" {
+            Box::<LocalLeaderboard>::default()
+        } else {
+            Box::new(HttpLeaderboard::new(url))
+        };
+        Some(Leaderboard(backend))
+    }
+}
+
+/// system: bring in the leaderboard, if there is one
+pub fn setup(mut commands: Commands) {
+    if let Some(leaderboard) = Leaderboard::from_args() {
+        commands.insert_resource(leaderboard);
+    }
+}
+
+/// The leaderboard part of the game over screen
+#[derive(Component)]
+pub struct LeaderboardUi {
+    /// the scores of this run, to be highlighted
+    submission: Submission,
+}
+
+/// The scores of this run, being posted
+#[derive(Component)]
+pub struct PendingSubmit(Pending<()>);
+
+/// The best scores, being fetched
+#[derive(Component)]
+pub struct PendingTop(Pending<Vec<Submission>>);
+
+fn spawn_line(parent: &mut ChildBuilder, font: &Handle<Font>, color: Color, text: LocalizedText) {
+    parent.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 16.,
+                color,
+            },
+        ),
+        text,
+    ));
+}
+
+/// system: once the game is over, post the scores of the run
+pub fn submit_run(
+    mut commands: Commands,
+    leaderboard: Option<Res<Leaderboard>>,
+    font: Res<DefaultFont>,
+    (mode, run_seed, scores, record): (
+        Res<GameMode>,
+        Res<RunSeed>,
+        Res<GameScores>,
+        Res<RunRecord>,
+    ),
+    query_game_over: Query<(), Added<Stats>>,
+) {
+    let Some(leaderboard) = leaderboard else {
+        return;
+    };
+    // the autopilot's runs are not posted
+    if query_game_over.is_empty() || record.assisted {
+        return;
+    }
+
+    let submission = Submission {
+        seed: run_seed.seed,
+        mode: *mode,
+        breakdown: ScoreBreakdown::from(&*scores),
+    };
+    let pending = leaderboard.0.submit(submission.clone());
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(12.),
+                        ..default()
+                    },
+                    size: Size::new(Val::Percent(100.), Val::Auto),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            LeaderboardUi { submission },
+            PendingSubmit(pending),
+            InField,
+        ))
+        .with_children(|parent| {
+            spawn_line(
+                parent,
+                &font.0,
+                Color::rgb(0.85, 0.85, 0.85),
+                LocalizedText::new("leaderboard_submitting"),
+            );
+        });
+}
+
+/// system: see whether the leaderboard answered,
+/// and show the best scores once they come
+pub fn poll(
+    mut commands: Commands,
+    leaderboard: Option<Res<Leaderboard>>,
+    font: Res<DefaultFont>,
+    query_submit: Query<(Entity, &LeaderboardUi, &PendingSubmit)>,
+    query_top: Query<(Entity, &LeaderboardUi, &PendingTop)>,
+) {
+    let Some(leaderboard) = leaderboard else {
+        return;
+    };
+    let dim = Color::rgb(0.85, 0.85, 0.85);
+
+    for (entity, ui, pending) in &query_submit {
+        let Some(result) = pending.0.poll() else {
+            continue;
+        };
+        commands.entity(entity).remove::<PendingSubmit>();
+        match result {
+            Ok(()) => {
+                let pending = leaderboard.0.fetch_top(ui.submission.mode, TOP_N);
+                commands.entity(entity).insert(PendingTop(pending));
+            }
+            Err(e) => {
+                warn!("Could not post scores: {}", e);
+                commands.entity(entity).despawn_descendants();
+                commands.entity(entity).with_children(|parent| {
+                    spawn_line(
+                        parent,
+                        &font.0,
+                        dim,
+                        LocalizedText::new("leaderboard_unavailable"),
+                    );
+                });
+            }
+        }
+    }
+
+    for (entity, ui, pending) in &query_top {
+        let Some(result) = pending.0.poll() else {
+            continue;
+        };
+        commands.entity(entity).remove::<PendingTop>();
+        commands.entity(entity).despawn_descendants();
+        match result {
+            Ok(top) => {
+                commands.entity(entity).with_children(|parent| {
+                    spawn_line(
+                        parent,
+                        &font.0,
+                        Color::WHITE,
+                        LocalizedText::new("leaderboard_top"),
+                    );
+                    for (i, submission) in top.iter().enumerate() {
+                        let color = if *submission == ui.submission {
+                            Color::rgb(1., 1., 0.5)
+                        } else {
+                            dim
+                        };
+                        spawn_line(
+                            parent,
+                            &font.0,
+                            color,
+                            LocalizedText::new("leaderboard_rank")
+                                .with_arg("rank", i + 1)
+                                .with_arg("score", submission.breakdown.score),
+                        );
+                    }
+                });
+            }
+            Err(e) => {
+                warn!("Could not fetch the leaderboard: {}", e);
+                commands.entity(entity).with_children(|parent| {
+                    spawn_line(
+                        parent,
+                        &font.0,
+                        dim,
+                        LocalizedText::new("leaderboard_unavailable"),
+                    );
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submission(score: i32, blasts_taken: u32) -> Submission {
+        Submission {
+            seed: 0,
+            mode: GameMode::Classic,
+            breakdown: ScoreBreakdown {
+                score,
+                blasts_taken,
+                ..default()
+            },
+        }
+    }
+
+    #[test]
+    fn rank_puts_the_best_first() {
+        let mut submissions = vec![submission(100, 0), submission(300, 2), submission(-20, 5)];
+        rank(&mut submissions);
+        let scores: Vec<i32> = submissions.iter().map(|s| s.breakdown.score).collect();
+        assert_eq!(scores, vec![300, 100, -20]);
+    }
+
+    #[test]
+    fn rank_breaks_ties_by_blasts_taken() {
+        let mut submissions = vec![submission(200, 3), submission(200, 1), submission(200, 2)];
+        rank(&mut submissions);
+        let blasts: Vec<u32> = submissions
+            .iter()
+            .map(|s| s.breakdown.blasts_taken)
+            .collect();
+        assert_eq!(blasts, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn mode_name_is_as_submitted() {
+        for mode in [
+            GameMode::Classic,
+            GameMode::Coop,
+            GameMode::HotSeat,
+            GameMode::Daily,
+        ] {
+            let submission = Submission {
+                mode,
+                ..submission(0, 0)
+            };
+            let json = serde_json::to_value(&submission).unwrap();
+            assert_eq!(json["mode"], mode_name(mode).unwrap());
+        }
+        assert_eq!(mode_name(GameMode::HotSeat), Ok("HotSeat".to_string()));
+    }
+
+    #[test]
+    fn top_url_has_the_mode_and_limit() {
+        let leaderboard = HttpLeaderboard::new("https://example.com/board/");
+        assert_eq!(
+            leaderboard.top_url(GameMode::Daily, 5),
+            Ok("https://example.com/board/scores?mode=Daily&limit=5".to_string())
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Level layouts, which describe the background tiles,
+//! the walkable area and fixed obstacles of the play field.
+//!
+//! Layouts are RON files in `assets/levels`, for instance:
+//!
+//! ```ron
+//! (
+//!     // one string per row of tiles, from top to bottom,
+//!     // each digit is an index into the tilesheet
+//!     ground: [
+//!         "000000",
+//!         "111111",
+//!     ],
+//!     // same format, `.` for no decoration
+//!     decorations: [
+//!         "......",
+//!         "..22..",
+//!     ],
+//!     walkable: (min: (0., 0.), max: (380., 496.)),
+//!     obstacles: [(Crate, (96., 180.))],
+//! )
+//! ```
+//!
+//! Rows are aligned to the bottom of the tilemap
+//! (the top row is repeated upwards if the tilemap is taller),
+//! and repeat horizontally.
+
+use bevy::{prelude::*, reflect::TypeUuid, utils::HashMap};
+use bevy_ecs_tilemap::{
+    prelude::TilemapId,
+    tiles::{TilePos, TileTextureIndex, TileVisible},
+};
+use serde::Deserialize;
+
+use crate::{
+    background::{Background, DecorationLayer},
+    movement::PlayBounds,
+    obstacle::{spawn_obstacle, ObstacleKind},
+};
+
+/// All level layouts available
+pub static LEVELS: &[&str] = &["site", "plaza", "warehouse"];
+
+/// The level layout used in the main menu
+pub const MENU_LEVEL: &str = "site";
+
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "5d4ab0c8-7c43-4b8a-9e0c-3f1d2a6b8e71"]
+pub struct LevelLayout {
+    /// ground tile indices, one string per row from top to bottom
+    pub ground: Vec<String>,
+    /// decoration tile indices, `.` for no decoration
+    #[serde(default)]
+    pub decorations: Vec<String>,
+    /// the area where the guy and the items can move
+    pub walkable: WalkableArea,
+    /// obstacles which are always present in this level
+    #[serde(default)]
+    pub obstacles: Vec<(ObstacleKind, (f32, f32))>,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct WalkableArea {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+impl From<WalkableArea> for PlayBounds {
+    fn from(area: WalkableArea) -> Self {
+        PlayBounds {
+            min: Vec2::new(area.min.0, area.min.1),
+            max: Vec2::new(area.max.0, area.max.1),
+        }
+    }
+}
+
+impl LevelLayout {
+    /// the ground tile at the given tilemap position
+    pub fn ground_tile(&self, x: u32, y: u32) -> Option<u32> {
+        tile_at(&self.ground, x, y)
+    }
+
+    /// the decoration tile at the given tilemap position, if any
+    pub fn decoration_tile(&self, x: u32, y: u32) -> Option<u32> {
+        tile_at(&self.decorations, x, y)
+    }
+}
+
+fn tile_at(rows: &[String], x: u32, y: u32) -> Option<u32> {
+    if rows.is_empty() {
+        return None;
+    }
+    // rows are written from top to bottom
+    let row = rows.len() - 1 - (y as usize).min(rows.len() - 1);
+    let row = rows[row].as_bytes();
+    if row.is_empty() {
+        return None;
+    }
+    (row[x as usize % row.len()] as char).to_digit(10)
+}
+
+/// The handles to all level layouts, by name
+#[derive(Debug, Resource)]
+pub struct LevelLayouts(HashMap<&'static str, Handle<LevelLayout>>);
+
+impl LevelLayouts {
+    pub fn get(&self, name: &str) -> Handle<LevelLayout> {
+        self.0.get(name).cloned().unwrap_or_else(|| {
+            warn!("No such level layout `{}`", name);
+            self.0[MENU_LEVEL].clone()
+        })
+    }
+}
+
+/// The level layout currently in place.
+/// Insert this resource to switch layouts.
+#[derive(Debug, Resource)]
+pub struct CurrentLevel(pub Handle<LevelLayout>);
+
+/// Marker for obstacles which belong to the level layout
+/// (rather than to a wave)
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct LevelObstacle;
+
+/// system: load all level layouts
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let layouts: HashMap<_, _> = LEVELS
+        .iter()
+        .map(|name| {
+            let handle: Handle<LevelLayout> =
+                asset_server.load(format!("levels/{}.level.ron", name));
+            (*name, handle)
+        })
+        .collect();
+
+    commands.insert_resource(CurrentLevel(layouts[MENU_LEVEL].clone()));
+    commands.insert_resource(LevelLayouts(layouts));
+}
+
+/// system: apply the current level layout
+/// once it is set and loaded (or reloaded)
+#[allow(clippy::too_many_arguments)]
+pub fn apply_level_layout(
+    mut commands: Commands,
+    current: Res<CurrentLevel>,
+    layouts: Res<Assets<LevelLayout>>,
+    mut asset_events: EventReader<AssetEvent<LevelLayout>>,
+    query_background: Query<(Entity, &mut Transform, Option<&DecorationLayer>), With<Background>>,
+    query_tiles: Query<(
+        &TilemapId,
+        &TilePos,
+        &mut TileTextureIndex,
+        &mut TileVisible,
+    )>,
+    query_level_obstacles: Query<Entity, With<LevelObstacle>>,
+    mut pending: Local<bool>,
+) {
+    let reloaded = asset_events.iter().any(|ev| match ev {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => *handle == current.0,
+        AssetEvent::Removed { .. } => false,
+    });
+    if current.is_changed() || reloaded {
+        *pending = true;
+    }
+    if !*pending {
+        return;
+    }
+
+    let Some(layout) = layouts.get(&current.0) else {
+        // not loaded yet
+        return;
+    };
+    *pending = false;
+
+    crate::background::reset_background(query_background, query_tiles, layout);
+
+    commands.insert_resource(PlayBounds::from(layout.walkable));
+
+    for entity in &query_level_obstacles {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (kind, (x, y)) in &layout.obstacles {
+        let e = spawn_obstacle(&mut commands, *kind, Vec2::new(*x, *y));
+        commands.entity(e).insert(LevelObstacle);
+    }
+}
@@ -0,0 +1,443 @@
+//! lifetime statistics: totals over every run ever played,
+//! per game mode, and the screen showing them.
+//!
+//! A run is added to the statistics as each wave is finished
+//! and once it is left, whether it was played to the end or not,
+//! so that a run resumed later is only counted once.
+//! Once the autopilot played any part of a run, the rest of it does not count.
+
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    achievements::RunRecord,
+    events::WaveFinishedEvent,
+    guy::{GuyState, Player},
+    i18n::LocalizedText,
+    ingame::{GameMode, Wave},
+    menu::spawn_back_button,
+    resume::ResumeRun,
+    scores::Stats,
+    DefaultFont,
+};
+
+/// The key under which lifetime statistics are stored
+const STATS_KEY: &str = "stats";
+
+/// Statistics summed up over the runs of one game mode
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModeStats {
+    pub runs: u32,
+    /// runs which ended with the guy victorious
+    pub wins: u32,
+    /// runs which ended with the guy a loser
+    pub losses: u32,
+    pub bombs_disarmed: u32,
+    pub dynamites_defused: u32,
+    pub blasts_taken: u32,
+    pub coffees: u32,
+    pub playtime_secs: f64,
+    pub best_wave: u16,
+}
+
+impl ModeStats {
+    /// Add up the statistics of other runs
+    fn add(&mut self, other: &ModeStats) {
+        self.runs += other.runs;
+        self.wins += other.wins;
+        self.losses += other.losses;
+        self.bombs_disarmed += other.bombs_disarmed;
+        self.dynamites_defused += other.dynamites_defused;
+        self.blasts_taken += other.blasts_taken;
+        self.coffees += other.coffees;
+        self.playtime_secs += other.playtime_secs;
+        self.best_wave = self.best_wave.max(other.best_wave);
+    }
+
+    /// The statistics of the same run, less what was already counted of it
+    fn since(&self, counted: &ModeStats) -> ModeStats {
+        ModeStats {
+            runs: self.runs.saturating_sub(counted.runs),
+            wins: self.wins.saturating_sub(counted.wins),
+            losses: self.losses.saturating_sub(counted.losses),
+            bombs_disarmed: self.bombs_disarmed.saturating_sub(counted.bombs_disarmed),
+            dynamites_defused: self
+                .dynamites_defused
+                .saturating_sub(counted.dynamites_defused),
+            blasts_taken: self.blasts_taken.saturating_sub(counted.blasts_taken),
+            coffees: self.coffees.saturating_sub(counted.coffees),
+            playtime_secs: (self.playtime_secs - counted.playtime_secs).max(0.),
+            best_wave: self.best_wave,
+        }
+    }
+
+    /// Wins over finished runs, in percent
+    fn win_percent(&self) -> Option<u32> {
+        let finished = self.wins + self.losses;
+        (finished > 0).then(|| (self.wins * 100 + finished / 2) / finished)
+    }
+}
+
+/// Statistics of all runs ever played, by game mode
+#[derive(Debug, Default, Clone, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub modes: BTreeMap<GameMode, ModeStats>,
+}
+
+impl LifetimeStats {
+    /// Load lifetime statistics from storage
+    pub fn load() -> Self {
+        crate::storage::load(STATS_KEY).unwrap_or_default()
+    }
+
+    /// The statistics of all game modes together
+    pub fn total(&self) -> ModeStats {
+        let mut total = ModeStats::default();
+        for stats in self.modes.values() {
+            total.add(stats);
+        }
+        total
+    }
+}
+
+/// How a finished run ended
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum Outcome {
+    Won,
+    Lost,
+}
+
+/// What the lifetime statistics need to know about the current run,
+/// on top of its [`RunRecord`]
+#[derive(Debug, Default, Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunSummary {
+    playtime: Duration,
+    outcome: Option<Outcome>,
+    /// what was already added to the lifetime statistics
+    counted: ModeStats,
+}
+
+impl RunSummary {
+    /// Add what is new in the run to the lifetime statistics
+    fn count(&mut self, stats: &mut LifetimeStats, mode: GameMode, wave: u16, record: &RunRecord) {
+        let run = ModeStats {
+            runs: 1,
+            wins: (self.outcome == Some(Outcome::Won)) as u32,
+            losses: (self.outcome == Some(Outcome::Lost)) as u32,
+            bombs_disarmed: record.bombs,
+            dynamites_defused: record.dynamites,
+            blasts_taken: record.blasts,
+            coffees: record.coffees,
+            playtime_secs: self.playtime.as_secs_f64(),
+            best_wave: wave,
+        };
+        stats
+            .modes
+            .entry(mode)
+            .or_default()
+            .add(&run.since(&self.counted));
+        self.counted = run;
+    }
+}
+
+/// system: start summing up a new run,
+/// or pick up the summary of the run being resumed
+pub fn start_run(mut commands: Commands, resume: Option<Res<ResumeRun>>) {
+    let summary = resume
+        .map(|resume| resume.0.summary.clone())
+        .unwrap_or_default();
+    commands.insert_resource(summary);
+}
+
+/// system: keep track of the time spent in the run
+pub fn tick_playtime(time: Res<Time>, mut summary: ResMut<RunSummary>) {
+    summary.playtime += time.delta();
+}
+
+/// system: take note of how the run ended, once the game is over
+pub fn note_outcome(
+    mut summary: ResMut<RunSummary>,
+    query_game_over: Query<(), Added<Stats>>,
+    query_guy: Query<&GuyState, With<Player>>,
+) {
+    if query_game_over.is_empty() || summary.outcome.is_some() {
+        return;
+    }
+
+    if query_guy.iter().any(|state| *state == GuyState::Victorious) {
+        summary.outcome = Some(Outcome::Won);
+    } else if query_guy.iter().any(|state| *state == GuyState::Loser) {
+        summary.outcome = Some(Outcome::Lost);
+    }
+}
+
+/// system: add the run so far to the lifetime statistics
+/// as each wave is finished, as the run may be saved and resumed
+pub fn record_wave(
+    mut stats: ResMut<LifetimeStats>,
+    mode: Res<GameMode>,
+    wave: Res<Wave>,
+    record: Res<RunRecord>,
+    mut summary: ResMut<RunSummary>,
+    mut event_reader: EventReader<WaveFinishedEvent>,
+) {
+    if event_reader.iter().count() == 0 || record.assisted {
+        return;
+    }
+
+    summary.count(&mut stats, *mode, wave.0, &record);
+    crate::storage::save(STATS_KEY, &*stats);
+}
+
+/// system: add the rest of the run which was just left to the lifetime statistics
+pub fn record_run(
+    mut stats: ResMut<LifetimeStats>,
+    mode: Res<GameMode>,
+    wave: Res<Wave>,
+    record: Res<RunRecord>,
+    mut summary: ResMut<RunSummary>,
+) {
+    if record.assisted {
+        return;
+    }
+
+    summary.count(&mut stats, *mode, wave.0, &record);
+    crate::storage::save(STATS_KEY, &*stats);
+}
+
+/// Time played, as hours, minutes and seconds
+fn format_playtime(secs: f64) -> String {
+    let secs = secs as u64;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// spawn a line with a label on the left and a value on the right
+fn spawn_stat_row(parent: &mut ChildBuilder, font: &Handle<Font>, label_key: &str, value: String) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(320.), Val::Auto),
+                justify_content: JustifyContent::SpaceBetween,
+                margin: UiRect {
+                    top: Val::Px(3.),
+                    bottom: Val::Px(3.),
+                    ..default()
+                },
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 20.,
+                        color: Color::rgb(0.85, 0.85, 0.85),
+                    },
+                ),
+                LocalizedText::new(label_key),
+            ));
+            parent.spawn(TextBundle::from_section(
+                value,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+}
+
+/// system: show the lifetime statistics,
+/// in total and for each game mode played
+pub fn setup(mut commands: Commands, font: Res<DefaultFont>, stats: Res<LifetimeStats>) {
+    // UI camera
+    commands.spawn(crate::screen::camera_bundle());
+
+    let font = font.0.clone();
+    let total = stats.total();
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 36.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect {
+                        bottom: Val::Px(16.),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                LocalizedText::new("stats"),
+            ));
+
+            let record = match total.win_percent() {
+                Some(percent) => format!("{} / {} ({}%)", total.wins, total.losses, percent),
+                None => format!("{} / {}", total.wins, total.losses),
+            };
+            let rows = [
+                ("stats_runs", total.runs.to_string()),
+                ("stats_record", record),
+                ("stats_bombs", total.bombs_disarmed.to_string()),
+                ("stats_dynamites", total.dynamites_defused.to_string()),
+                ("stats_blasts", total.blasts_taken.to_string()),
+                ("stats_coffees", total.coffees.to_string()),
+                ("stats_playtime", format_playtime(total.playtime_secs)),
+                ("stats_best_wave", total.best_wave.to_string()),
+            ];
+            for (label_key, value) in rows {
+                spawn_stat_row(parent, &font, label_key, value);
+            }
+
+            // breakdown per game mode
+            for (mode, stats) in &stats.modes {
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 22.,
+                            color: Color::rgb(1., 0.85, 0.3),
+                        },
+                    )
+                    .with_style(Style {
+                        margin: UiRect {
+                            top: Val::Px(16.),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                    LocalizedText::new(mode.name_key()),
+                ));
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 16.,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::CENTER)
+                    .with_style(Style {
+                        // wrap long lines
+                        max_size: Size::new(Val::Px(340.), Val::Undefined),
+                        ..default()
+                    }),
+                    LocalizedText::new("stats_mode_summary")
+                        .with_arg("runs", stats.runs)
+                        .with_arg("wins", stats.wins)
+                        .with_arg("losses", stats.losses)
+                        .with_arg("wave", stats.best_wave),
+                ));
+            }
+
+            spawn_back_button(parent, font.clone());
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_sums_up_and_keeps_the_best_wave() {
+        let mut stats = ModeStats {
+            runs: 2,
+            wins: 1,
+            losses: 1,
+            bombs_disarmed: 10,
+            playtime_secs: 90.5,
+            best_wave: 5,
+            ..default()
+        };
+        stats.add(&ModeStats {
+            runs: 1,
+            losses: 1,
+            bombs_disarmed: 3,
+            dynamites_defused: 7,
+            blasts_taken: 2,
+            coffees: 4,
+            playtime_secs: 30.,
+            best_wave: 3,
+            ..default()
+        });
+        assert_eq!(
+            stats,
+            ModeStats {
+                runs: 3,
+                wins: 1,
+                losses: 2,
+                bombs_disarmed: 13,
+                dynamites_defused: 7,
+                blasts_taken: 2,
+                coffees: 4,
+                playtime_secs: 120.5,
+                best_wave: 5,
+            }
+        );
+    }
+
+    #[test]
+    fn total_adds_up_every_mode() {
+        let mut lifetime = LifetimeStats::default();
+        for (mode, best_wave) in [(GameMode::Classic, 6), (GameMode::Coop, 4)] {
+            lifetime.modes.insert(
+                mode,
+                ModeStats {
+                    runs: 2,
+                    best_wave,
+                    ..default()
+                },
+            );
+        }
+        let total = lifetime.total();
+        assert_eq!(total.runs, 4);
+        assert_eq!(total.best_wave, 6);
+    }
+
+    #[test]
+    fn win_percent_of_finished_runs() {
+        let percent = |wins, losses| {
+            ModeStats {
+                runs: wins + losses + 1,
+                wins,
+                losses,
+                ..default()
+            }
+            .win_percent()
+        };
+        assert_eq!(percent(0, 0), None);
+        assert_eq!(percent(3, 0), Some(100));
+        assert_eq!(percent(0, 2), Some(0));
+        assert_eq!(percent(1, 1), Some(50));
+        // rounded to the nearest
+        assert_eq!(percent(1, 2), Some(33));
+        assert_eq!(percent(2, 1), Some(67));
+        assert_eq!(percent(1, 7), Some(13));
+    }
+}
@@ -1,30 +1,61 @@
-use animation::ToggleVisibility;
+use animation::{FadeOut, ToggleVisibility};
 use bevy::prelude::*;
 use bevy::utils::Duration;
 use bevy::window::PresentMode;
 use bevy_ecs_tilemap::TilemapPlugin;
 use events::{
-    BombDisarmedEvent, BombThrownEvent, CoffeePickedUpEvent, CoffeeThrownEvent, CoffeeWornOffEvent,
-    DisarmCancelledEvent, DisarmProgressEvent, DynamiteDefusedEvent, DynamiteThrownEvent,
+    BombDisarmedEvent, BombThrownEvent, BouncedEvent, CoffeePickedUpEvent, CoffeeThrownEvent,
+    CoffeeWornOffEvent, DisarmCancelledEvent, DynamiteDefusedEvent, DynamiteThrownEvent,
     ExplodedEvent, GuyHurtEvent, NextWaveEvent, WaveFinishedEvent,
 };
 
+mod accessibility;
+mod achievements;
 mod animation;
+mod attract;
 mod audio;
+mod autopilot;
 mod background;
 mod bomb;
 mod coffee;
+#[cfg(feature = "console")]
+mod console;
+mod daily;
+#[cfg(feature = "debug")]
+mod debug;
 mod dynamite;
+#[cfg(feature = "editor")]
+mod editor;
 mod events;
+mod game_feel;
+mod ghost;
 mod guy;
+mod headless;
 mod helper;
+mod hotseat;
+mod i18n;
 mod ingame;
+mod leaderboard;
+mod level;
+mod lifetime;
 mod menu;
 mod movement;
+mod obstacle;
+mod particles;
+mod pathfinding;
 mod poptext;
 mod progress_bar;
+mod resume;
+mod ron_asset;
 mod scores;
+mod screen;
+mod settings;
+#[cfg(feature = "debug")]
+mod snapshot;
 mod spawner;
+mod storage;
+mod targeting;
+mod tutorial;
 mod waves;
 
 #[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
@@ -33,18 +64,28 @@ pub enum AppState {
     InGame,
     Paused,
     Settings,
+    Achievements,
+    Stats,
+    HotSeat,
+    Daily,
+    #[cfg(feature = "editor")]
+    Editor,
 }
 
 fn main() {
-    App::new()
-        .add_plugins(
+    let headless = headless::is_requested();
+    let mut app = App::new();
+    if headless {
+        app.add_plugin(headless::HeadlessPlugin);
+    } else {
+        app.add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
                     window: WindowDescriptor {
-                        width: 380.,
-                        height: 660.,
+                        width: screen::FIELD_SIZE.x,
+                        height: screen::FIELD_SIZE.y,
                         present_mode: PresentMode::AutoVsync,
-                        resizable: false,
+                        resizable: true,
                         title: "Timely Defuse".to_string(),
                         ..default()
                     },
@@ -52,12 +93,35 @@ fn main() {
                 })
                 .set(ImagePlugin::default_nearest()),
         )
-        .add_plugin(TilemapPlugin)
+        .add_plugin(TilemapPlugin);
+    }
+
+    app.add_plugin(DevToolsPlugin)
+        .add_asset::<level::LevelLayout>()
+        .add_asset_loader(ron_asset::RonAssetLoader::<level::LevelLayout>::new(&[
+            "level.ron",
+        ]))
+        .add_asset::<i18n::StringTable>()
+        .add_asset_loader(ron_asset::RonAssetLoader::<i18n::StringTable>::new(&[
+            "lang.ron",
+        ]))
+        .add_asset::<waves::WaveDefinition>()
+        .add_asset_loader(ron_asset::RonAssetLoader::<waves::WaveDefinition>::new(&[
+            "wave.ron",
+        ]))
+        .add_asset::<particles::EmitterConfig>()
+        .add_asset_loader(ron_asset::RonAssetLoader::<particles::EmitterConfig>::new(
+            &["particle.ron"],
+        ))
         // add the app state type
-        .add_state(AppState::MainMenu)
+        // (without a window, the game starts right away)
+        .add_state(if headless {
+            AppState::InGame
+        } else {
+            AppState::MainMenu
+        })
         .add_event::<BombDisarmedEvent>()
         .add_event::<ExplodedEvent>()
-        .add_event::<DisarmProgressEvent>()
         .add_event::<DisarmCancelledEvent>()
         .add_event::<DynamiteDefusedEvent>()
         .add_event::<WaveFinishedEvent>()
@@ -68,49 +132,250 @@ fn main() {
         .add_event::<CoffeePickedUpEvent>()
         .add_event::<CoffeeWornOffEvent>()
         .add_event::<GuyHurtEvent>()
+        .add_event::<BouncedEvent>()
+        // reflect the play field, so that it can be saved in snapshots
+        .register_type::<bevy::time::TimerMode>()
+        .register_type::<Option<Vec2>>()
+        .register_type::<Vec<Vec2>>()
+        .register_type::<(f32, f32)>()
+        .register_type::<[f32; 4]>()
+        .register_type::<Option<Entity>>()
+        .register_type::<TextureAtlasSprite>()
+        .register_type_data::<TextureAtlasSprite, ReflectComponent>()
+        .register_type::<DynamiteThrownEvent>()
+        .register_type::<BombThrownEvent>()
+        .register_type::<CoffeeThrownEvent>()
+        .register_type::<NextWaveEvent>()
+        .register_type::<animation::Rotating>()
+        .register_type::<animation::BaseScale>()
+        .register_type::<animation::Wobbly>()
+        .register_type::<animation::OneShotAnimationTimer>()
+        .register_type::<ToggleVisibility>()
+        .register_type::<Option<ToggleVisibility>>()
+        .register_type::<animation::LoopedAnimationTimer>()
+        .register_type::<FadeOut>()
+        .register_type::<Option<FadeOut>>()
+        .register_type::<animation::FadeIn>()
+        .register_type::<audio::BounceAudio>()
+        .register_type::<bomb::BombState>()
+        .register_type::<bomb::BombTick>()
+        .register_type::<coffee::Coffee>()
+        .register_type::<coffee::CoffeeEffect>()
+        .register_type::<dynamite::Dynamite>()
+        .register_type::<dynamite::TimeToExplode>()
+        .register_type::<guy::Player>()
+        .register_type::<guy::DisarmHurry>()
+        .register_type::<guy::PointerControlled>()
+        .register_type::<guy::GuyState>()
+        .register_type::<guy::GuyPerformance>()
+        .register_type::<guy::GuyDestination>()
+        .register_type::<guy::GuyWaypoints>()
+        .register_type::<guy::GuyTarget>()
+        .register_type::<guy::GuyPath>()
+        .register_type::<guy::GuyAnimationTimer>()
+        .register_type::<guy::GuyRecovery>()
+        .register_type::<helper::AnimationTimer>()
+        .register_type::<helper::Fixed>()
+        .register_type::<helper::BaseTranslation>()
+        .register_type::<helper::TimeToLive>()
+        .register_type::<helper::DespawnOnTrigger>()
+        .register_type::<helper::DelayedComponent<ToggleVisibility>>()
+        .register_type::<helper::DelayedComponent<FadeOut>>()
+        .register_type::<helper::DelayedRemoval<ToggleVisibility>>()
+        .register_type::<helper::ScheduledEvent<DynamiteThrownEvent>>()
+        .register_type::<helper::ScheduledEvent<BombThrownEvent>>()
+        .register_type::<helper::ScheduledEvent<CoffeeThrownEvent>>()
+        .register_type::<helper::ScheduledEvent<NextWaveEvent>>()
+        .register_type::<level::LevelObstacle>()
+        .register_type::<movement::Velocity>()
+        .register_type::<movement::MaxSpeed>()
+        .register_type::<movement::SpatialPosition>()
+        .register_type::<movement::SpatialVelocity>()
+        .register_type::<movement::Gravity>()
+        .register_type::<obstacle::Obstacle>()
+        .register_type::<obstacle::ObstacleKind>()
+        .register_type::<particles::Particle>()
+        .register_type::<particles::ParticleEmitter>()
+        .register_type::<particles::ParticleEffect>()
+        .register_type::<progress_bar::ProgressBarOuterMesh>()
+        .register_type::<progress_bar::ProgressBarInnerMesh>()
+        .register_type::<spawner::Spawner>()
+        .register_type::<spawner::SpawnerCooldown>()
+        .register_type::<spawner::PendingThrow>()
+        .register_type::<spawner::Throwable>()
+        .register_type::<spawner::AimedThrow>()
+        .register_type::<spawner::RandomEventProducer<DynamiteThrownEvent>>()
+        .register_type::<spawner::RandomEventProducer<BombThrownEvent>>()
+        .register_type::<spawner::RandomEventProducer<CoffeeThrownEvent>>()
+        .register_type::<targeting::Targetable>()
         .init_resource::<scores::GameScores>()
+        .init_resource::<achievements::RunRecord>()
+        .init_resource::<lifetime::RunSummary>()
+        .init_resource::<ingame::GameMode>()
         .init_resource::<spawner::Rng>()
+        .init_resource::<daily::Modifiers>()
+        .init_resource::<pathfinding::NavGrid>()
+        .init_resource::<movement::PlayBounds>()
+        .init_resource::<game_feel::Trauma>()
+        .init_resource::<game_feel::HitStop>()
+        .init_resource::<particles::ParticlePool>()
+        .init_resource::<screen::ScreenFit>()
+        .init_resource::<waves::WaveList>()
+        .insert_resource(settings::Settings::load())
+        .insert_resource(autopilot::Autopilot::from_args())
+        .insert_resource(tutorial::TutorialProgress::load())
+        .insert_resource(achievements::UnlockedAchievements::load())
+        .insert_resource(lifetime::LifetimeStats::load())
+        .insert_resource(daily::DailyHistory::load())
         .add_startup_system(background::setup)
+        .add_startup_system(level::setup)
+        .add_startup_system(particles::setup)
+        .add_startup_system(i18n::setup)
         .add_startup_system(audio::setup)
+        .add_startup_system(leaderboard::setup)
         .add_system(handle_state_changes)
         .add_system(animation::fade_out)
         .add_system(animation::fade_out_on_text)
         .add_system(animation::wobble)
+        .add_system(accessibility::style_hud_text)
         .add_system(animation::make_things_rotate)
         .add_system(animation::fade_in_ui)
         .add_system(animation::animate_loops)
+        .add_system(level::apply_level_layout)
+        .add_system(obstacle::attach_obstacle_visuals)
+        .add_system(settings::save_settings)
+        .add_system(game_feel::fade_screen_flash)
+        .add_system(screen::fit_to_window)
+        .add_system(screen::attach_letterbox)
+        .add_system(screen::fit_field_ui)
+        .add_system(screen::toggle_fullscreen)
         .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(menu::setup))
         .add_system_set(
             SystemSet::on_update(AppState::MainMenu)
                 .with_system(menu::button_system)
-                .with_system(menu::animate_background),
+                .with_system(menu::animate_background)
+                .with_system(attract::start_demo_when_idle),
         )
         .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(menu::destroy))
+        .add_system_set(SystemSet::on_enter(AppState::Settings).with_system(settings::setup))
+        .add_system_set(
+            SystemSet::on_update(AppState::Settings)
+                .with_system(settings::button_system)
+                .with_system(settings::update_labels)
+                .with_system(menu::animate_background),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Settings).with_system(menu::destroy))
+        .add_system_set(
+            SystemSet::on_enter(AppState::Achievements).with_system(achievements::setup_gallery),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Achievements)
+                .with_system(menu::back_button_system)
+                .with_system(menu::animate_background),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Achievements).with_system(menu::destroy))
+        .add_system_set(SystemSet::on_enter(AppState::Stats).with_system(lifetime::setup))
+        .add_system_set(
+            SystemSet::on_update(AppState::Stats)
+                .with_system(menu::back_button_system)
+                .with_system(menu::animate_background),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Stats).with_system(menu::destroy))
+        .add_system_set(SystemSet::on_enter(AppState::HotSeat).with_system(hotseat::setup))
+        .add_system_set(
+            SystemSet::on_update(AppState::HotSeat)
+                .with_system(hotseat::refresh)
+                .with_system(hotseat::button_system)
+                .with_system(menu::back_button_system)
+                .with_system(menu::animate_background),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::HotSeat).with_system(menu::destroy))
+        .add_system_set(SystemSet::on_enter(AppState::Daily).with_system(daily::setup))
+        .add_system_set(
+            SystemSet::on_update(AppState::Daily)
+                .with_system(daily::button_system)
+                .with_system(menu::back_button_system)
+                .with_system(menu::animate_background),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Daily).with_system(menu::destroy))
         .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(ingame::setup))
-        .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(ingame::destroy))
+        .add_system_set(
+            SystemSet::on_exit(AppState::InGame)
+                .with_system(ingame::destroy)
+                .with_system(game_feel::reset)
+                .with_system(particles::reset)
+                .with_system(autopilot::reset)
+                .with_system(lifetime::record_run)
+                .with_system(hotseat::record_turn)
+                .with_system(ghost::save_if_best)
+                .with_system(ghost::end_race)
+                .with_system(daily::finish_run)
+                .with_system(resume::end_resume)
+                .with_system(attract::end_demo),
+        )
         .add_system_set(
             SystemSet::on_update(AppState::InGame)
                 .with_system(ingame::mouse_handler)
                 .with_system(ingame::mouse_set_destination)
                 .with_system(ingame::touch_system_create_squares)
                 .with_system(ingame::touch_set_destination)
+                .with_system(ingame::steer_with_pointer)
+                .with_system(ingame::steer_with_pad)
                 .with_system(animation::animate_one_shot)
                 .with_system(animation::animate_loops)
                 .with_system(animation::toggle_visibility)
                 .with_system(movement::apply_velocity)
                 .with_system(movement::apply_velocity_to_text_styles)
+                .with_system(
+                    poptext::place_in_field.before(movement::apply_velocity_to_text_styles),
+                )
                 .with_system(movement::spatial_position_to_transform)
                 .with_system(movement::apply_spatial_velocity)
                 .with_system(movement::apply_gravity)
                 .with_system(movement::collide_on_floor)
                 .with_system(movement::apply_boundaries.after(movement::apply_velocity))
+                .with_system(
+                    obstacle::collide_with_obstacles
+                        .after(movement::apply_spatial_velocity)
+                        .before(movement::apply_boundaries),
+                )
+                .with_system(obstacle::clear_obstacles_on_next_wave.before(waves::on_next_wave))
                 .with_system(helper::destroy_on_ttl)
                 .with_system(helper::z_depth)
-                .with_system(progress_bar::update_progress_bar)
+                .with_system(progress_bar::attach_progress_bars)
+                .with_system(progress_bar::update_progress_bar.after(guy::disarming_bomb))
+                .with_system(guy::animate_guy)
+                .with_system(guy::follow_waypoints.after(guy::disarming_bomb))
                 .with_system(
-                    progress_bar::clear_progress_bar.after(progress_bar::update_progress_bar),
+                    targeting::follow_target
+                        .after(ingame::mouse_set_destination)
+                        .after(ingame::touch_set_destination)
+                        .after(ingame::steer_with_pointer)
+                        .after(ingame::steer_with_pad)
+                        .after(autopilot::drive)
+                        .before(guy::disarming_bomb)
+                        .before(guy::plan_path),
+                )
+                .with_system(autopilot::toggle)
+                .with_system(attract::leave_demo)
+                .with_system(tutorial::begin)
+                .with_system(tutorial::advance)
+                .with_system(tutorial::throw_items.after(tutorial::advance))
+                .with_system(tutorial::point_arrow)
+                .with_system(tutorial::finish)
+                .with_system(
+                    autopilot::drive
+                        .after(ingame::mouse_set_destination)
+                        .after(ingame::touch_set_destination)
+                        .after(ingame::steer_with_pointer),
+                )
+                .with_system(targeting::update_target_outline)
+                .with_system(ingame::update_waypoint_markers.after(guy::follow_waypoints))
+                .with_system(
+                    guy::plan_path
+                        .after(guy::follow_waypoints)
+                        .before(guy::walk_to_destination),
                 )
-                .with_system(guy::animate_guy)
                 .with_system(guy::walk_to_destination)
                 .with_system(guy::recover)
                 .with_system(guy::disarming_bomb)
@@ -129,6 +394,12 @@ fn main() {
                 .with_system(scores::on_pickup_dynamite)
                 .with_system(scores::on_bomb_explode)
                 .with_system(scores::on_guy_hurt)
+                .with_system(
+                    scores::update_player_scores
+                        .after(scores::on_disarm_bomb)
+                        .after(scores::on_pickup_dynamite)
+                        .after(scores::on_guy_hurt),
+                )
                 .with_system(scores::update_stats)
                 .with_system(helper::run_scheduled_events::<DynamiteThrownEvent>)
                 .with_system(helper::run_scheduled_events::<BombThrownEvent>)
@@ -147,22 +418,65 @@ fn main() {
                 .with_system(spawner::throw_bomb)
                 .with_system(spawner::throw_dynamite)
                 .with_system(spawner::throw_coffee)
+                .with_system(spawner::throw_aimed)
                 .with_system(waves::detect_wave_finish)
+                .with_system(waves::start_awaited_waves.before(waves::detect_wave_finish))
                 .with_system(waves::change_background_per_wave.before(waves::on_next_wave))
                 .with_system(waves::on_next_wave.after(waves::detect_wave_finish))
                 .with_system(ingame::button_system)
-                .with_system(helper::delayed_removal::<ToggleVisibility>),
+                .with_system(game_feel::add_trauma)
+                .with_system(game_feel::shake_camera.after(game_feel::add_trauma))
+                .with_system(game_feel::start_hit_stop)
+                .with_system(game_feel::update_hit_stop.before(game_feel::start_hit_stop))
+                .with_system(game_feel::start_screen_flash)
+                .with_system(particles::emit_on_explosion)
+                .with_system(particles::emit_on_bounce.after(movement::collide_on_floor))
+                .with_system(particles::emit_continuous)
+                .with_system(
+                    particles::update_particles
+                        .after(movement::apply_spatial_velocity)
+                        .after(movement::apply_gravity),
+                )
+                .with_system(helper::delayed_removal::<ToggleVisibility>)
+                .with_system(achievements::record_run)
+                .with_system(achievements::unlock_achievements.after(achievements::record_run))
+                .with_system(lifetime::tick_playtime)
+                .with_system(lifetime::note_outcome)
+                .with_system(lifetime::record_wave.after(achievements::record_run))
+                .with_system(hotseat::track_waves)
+                .with_system(ghost::record)
+                .with_system(ghost::spawn_ghost)
+                .with_system(ghost::play_ghost)
+                .with_system(ghost::show_delta)
+                .with_system(daily::apply_modifiers)
+                .with_system(leaderboard::submit_run)
+                .with_system(leaderboard::poll)
+                .with_system(
+                    resume::save_run
+                        .after(achievements::record_run)
+                        .after(lifetime::record_wave),
+                )
+                .with_system(resume::discard_on_game_over),
         )
         .add_system_set_to_stage(
             CoreStage::PostUpdate,
             SystemSet::new()
+                .with_system(i18n::localize_texts.before(bevy::ui::UiSystem::Flex))
                 .with_system(animation::detect_toggle_visibility_removal)
-                .with_system(helper::delayed_insertion::<ToggleVisibility>),
+                .with_system(pathfinding::rebuild_nav_grid)
+                .with_system(helper::delayed_insertion::<ToggleVisibility>)
+                .with_system(helper::delayed_insertion::<FadeOut>),
         )
         .add_system_set(
             SystemSet::on_enter(AppState::InGame)
                 .with_system(setup)
+                .with_system(spawner::seed_run)
                 .with_system(guy::setup)
+                .with_system(attract::setup)
+                .with_system(achievements::start_run)
+                .with_system(lifetime::start_run)
+                .with_system(ghost::start_recording)
+                .with_system(daily::start_run)
                 .with_system(bomb::setup)
                 .with_system(dynamite::setup)
                 .with_system(coffee::setup),
@@ -170,6 +484,23 @@ fn main() {
         .run();
 }
 
+/// The tools for developing the game which are compiled in,
+/// as chosen by cargo features
+struct DevToolsPlugin;
+
+impl Plugin for DevToolsPlugin {
+    #[allow(unused_variables)]
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "debug")]
+        app.add_plugin(debug::DebugOverlayPlugin)
+            .add_plugin(snapshot::SnapshotPlugin);
+        #[cfg(feature = "console")]
+        app.add_plugin(console::ConsolePlugin);
+        #[cfg(feature = "editor")]
+        app.add_plugin(editor::EditorPlugin);
+    }
+}
+
 #[derive(Debug, Clone, Resource, Deref)]
 pub struct DefaultFont(Handle<Font>);
 
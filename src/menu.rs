@@ -1,12 +1,17 @@
 use bevy::prelude::*;
 use bevy::utils::Duration;
-use bevy_ecs_tilemap::{
-    prelude::TilemapId,
-    tiles::{TilePos, TileTextureIndex},
-};
+use bevy_ecs_tilemap::prelude::TilemapId;
 
 use crate::{
-    animation::spawn_fade_in_black_screen, audio::GameSoundSources, background::Background,
+    animation::spawn_fade_in_black_screen,
+    audio::GameSoundSources,
+    background::Background,
+    ghost::{GhostRace, GhostRun},
+    i18n::LocalizedText,
+    ingame::GameMode,
+    level::{CurrentLevel, LevelLayouts, MENU_LEVEL},
+    resume::{ResumeRun, SavedRun},
+    spawner::NextSeed,
     AppState, DefaultFont, DelayedStateChange,
 };
 
@@ -14,15 +19,7 @@ pub const NORMAL_BUTTON: Color = Color::rgb(0.3, 0.3, 0.8);
 pub const HOVER_BUTTON: Color = Color::rgb(0.4, 0.5, 0.9);
 pub const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
 
-pub fn setup(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    windows: Res<Windows>,
-    query_background: Query<(Entity, &mut Transform), With<Background>>,
-    query_tiles: Query<(&TilemapId, &TilePos, &mut TileTextureIndex)>,
-) {
-    let window = windows.get_primary().unwrap();
-
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>, layouts: Res<LevelLayouts>) {
     // text font
     let font: Handle<Font> = asset_server.load("font/Pixelme.ttf");
 
@@ -33,17 +30,10 @@ pub fn setup(
     let title: Handle<Image> = asset_server.load("img/cool-title.png");
 
     // reset background
-    crate::background::reset_background(query_background, query_tiles, 0, 1);
+    commands.insert_resource(CurrentLevel(layouts.get(MENU_LEVEL)));
 
     // UI camera
-    commands.spawn(Camera2dBundle {
-        transform: Transform::from_translation(Vec3::from_array([
-            window.width() as f32 / 2.,
-            window.height() as f32 / 2.,
-            999.,
-        ])),
-        ..Default::default()
-    });
+    commands.spawn(crate::screen::camera_bundle());
 
     // UI title
     commands
@@ -77,47 +67,334 @@ pub fn setup(
                 ..default()
             });
 
-            // UI start button
+            // UI buttons
             parent
-                .spawn(ButtonBundle {
+                .spawn(NodeBundle {
                     style: Style {
                         flex_direction: FlexDirection::Column,
-                        // center button
-                        margin: UiRect::all(Val::Auto),
-                        size: Size::new(Val::Px(160.0), Val::Px(64.0)),
-                        // horizontally center child text
-                        justify_content: JustifyContent::Center,
-                        // vertically center child text
                         align_items: AlignItems::Center,
+                        // center buttons
+                        margin: UiRect::all(Val::Auto),
                         ..default()
                     },
-                    background_color: NORMAL_BUTTON.into(),
                     ..default()
                 })
                 .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Start",
-                        TextStyle {
-                            font,
-                            font_size: 38.0,
-                            color: Color::rgba(1., 1., 0.8, 1.0),
-                        },
-                    ));
+                    if SavedRun::load().is_some() {
+                        spawn_menu_button(
+                            parent,
+                            font.clone(),
+                            "continue",
+                            28.,
+                            Size::new(Val::Px(220.0), Val::Px(44.0)),
+                            MenuButton::Continue,
+                        );
+                    }
+                    spawn_menu_button(
+                        parent,
+                        font.clone(),
+                        "start",
+                        38.,
+                        Size::new(Val::Px(160.0), Val::Px(64.0)),
+                        MenuButton::Start,
+                    );
+                    spawn_menu_button(
+                        parent,
+                        font.clone(),
+                        "coop",
+                        28.,
+                        Size::new(Val::Px(220.0), Val::Px(44.0)),
+                        MenuButton::Coop,
+                    );
+                    spawn_menu_button(
+                        parent,
+                        font.clone(),
+                        "hot_seat",
+                        28.,
+                        Size::new(Val::Px(220.0), Val::Px(44.0)),
+                        MenuButton::HotSeat,
+                    );
+                    spawn_menu_button(
+                        parent,
+                        font.clone(),
+                        "daily",
+                        28.,
+                        Size::new(Val::Px(220.0), Val::Px(44.0)),
+                        MenuButton::Daily,
+                    );
+                    if GhostRun::load().is_some() {
+                        spawn_menu_button(
+                            parent,
+                            font.clone(),
+                            "ghost_race",
+                            28.,
+                            Size::new(Val::Px(220.0), Val::Px(44.0)),
+                            MenuButton::Ghost,
+                        );
+                    }
+                    spawn_menu_button(
+                        parent,
+                        font.clone(),
+                        "settings",
+                        28.,
+                        Size::new(Val::Px(220.0), Val::Px(44.0)),
+                        MenuButton::Settings,
+                    );
+                    spawn_menu_button(
+                        parent,
+                        font.clone(),
+                        "achievements",
+                        28.,
+                        Size::new(Val::Px(220.0), Val::Px(44.0)),
+                        MenuButton::Achievements,
+                    );
+                    spawn_menu_button(
+                        parent,
+                        font,
+                        "stats",
+                        28.,
+                        Size::new(Val::Px(220.0), Val::Px(44.0)),
+                        MenuButton::Stats,
+                    );
                 });
         });
 }
 
+/// The buttons in the main menu
+#[derive(Debug, Copy, Clone, PartialEq, Component)]
+pub enum MenuButton {
+    /// resume the saved run
+    Continue,
+    Start,
+    Coop,
+    HotSeat,
+    /// today's challenge, see [`crate::daily`]
+    Daily,
+    /// race the ghost of the best run
+    Ghost,
+    Settings,
+    Achievements,
+    Stats,
+}
+
+impl MenuButton {
+    /// the app state which this button leads to
+    fn target_state(self) -> AppState {
+        match self {
+            MenuButton::Continue | MenuButton::Start | MenuButton::Coop | MenuButton::Ghost => {
+                AppState::InGame
+            }
+            MenuButton::HotSeat => AppState::HotSeat,
+            MenuButton::Daily => AppState::Daily,
+            MenuButton::Settings => AppState::Settings,
+            MenuButton::Achievements => AppState::Achievements,
+            MenuButton::Stats => AppState::Stats,
+        }
+    }
+
+    /// the game mode which this button starts, if any
+    fn game_mode(self) -> Option<GameMode> {
+        match self {
+            MenuButton::Start | MenuButton::Ghost => Some(GameMode::Classic),
+            MenuButton::Coop => Some(GameMode::Coop),
+            _ => None,
+        }
+    }
+}
+
+fn spawn_menu_button(
+    parent: &mut ChildBuilder,
+    font: Handle<Font>,
+    label_key: &str,
+    font_size: f32,
+    size: Size,
+    button: MenuButton,
+) -> Entity {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    margin: UiRect {
+                        top: Val::Px(8.),
+                        bottom: Val::Px(8.),
+                        ..default()
+                    },
+                    size,
+                    // horizontally center child text
+                    justify_content: JustifyContent::Center,
+                    // vertically center child text
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: NORMAL_BUTTON.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font,
+                        font_size,
+                        color: Color::rgba(1., 1., 0.8, 1.0),
+                    },
+                ),
+                LocalizedText::new(label_key),
+            ));
+        })
+        .id()
+}
+
+/// interactions with the buttons of the main menu
+type MenuButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut BackgroundColor,
+        &'static MenuButton,
+    ),
+    (Changed<Interaction>, With<Button>),
+>;
+
 pub fn button_system(
     mut commands: Commands,
-    mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor, &Children),
-        (Changed<Interaction>, With<Button>),
-    >,
+    mut interaction_query: MenuButtonQuery,
+    audio: Res<Audio>,
+    sound_sources: Res<GameSoundSources>,
+    transition_entity: Query<Entity, With<DelayedStateChange>>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+
+                audio.play(sound_sources.click.cast_weak());
+
+                // ensure that we don't spawn more than one
+                if transition_entity.get_single().is_err() {
+                    if let Some(mode) = button.game_mode() {
+                        commands.insert_resource(mode);
+                    }
+                    if *button == MenuButton::Continue {
+                        if let Some(saved) = SavedRun::load() {
+                            ResumeRun::insert(&mut commands, saved);
+                        }
+                    }
+                    if *button == MenuButton::Ghost {
+                        if let Some(run) = GhostRun::load() {
+                            commands.insert_resource(NextSeed(run.seed));
+                            commands.insert_resource(GhostRace::new(run));
+                        }
+                    }
+                    fade_to(&mut commands, button.target_state());
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVER_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Fade out and go to another state
+pub fn fade_to(commands: &mut Commands, state: AppState) {
+    let e = spawn_fade_in_black_screen(commands, Duration::from_millis(400));
+    commands
+        .entity(e)
+        .insert(DelayedStateChange::new(state, Duration::from_millis(750)));
+}
+
+/// A line of centered text on a menu screen, wrapped if too long
+pub fn spawn_text(
+    parent: &mut ChildBuilder,
+    font: &Handle<Font>,
+    size: f32,
+    color: Color,
+    text: LocalizedText,
+) {
+    parent.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: size,
+                color,
+            },
+        )
+        .with_text_alignment(TextAlignment::CENTER)
+        .with_style(Style {
+            margin: UiRect {
+                top: Val::Px(4.),
+                bottom: Val::Px(4.),
+                ..default()
+            },
+            // wrap long lines
+            max_size: Size::new(Val::Px(340.), Val::Undefined),
+            ..default()
+        }),
+        text,
+    ));
+}
+
+/// The button leading back to the main menu from the other menu screens
+#[derive(Debug, Component)]
+pub struct BackButton;
+
+pub fn spawn_back_button(parent: &mut ChildBuilder, font: Handle<Font>) -> Entity {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(160.0), Val::Px(46.0)),
+                    margin: UiRect {
+                        top: Val::Px(24.),
+                        ..default()
+                    },
+                    // horizontally center child text
+                    justify_content: JustifyContent::Center,
+                    // vertically center child text
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: NORMAL_BUTTON.into(),
+                ..default()
+            },
+            BackButton,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font,
+                        font_size: 28.0,
+                        color: Color::rgba(1., 1., 0.8, 1.0),
+                    },
+                ),
+                LocalizedText::new("back"),
+            ));
+        })
+        .id()
+}
+
+/// interactions with the back button
+type BackButtonChanged = (Changed<Interaction>, With<BackButton>);
+
+pub fn back_button_system(
+    mut commands: Commands,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor), BackButtonChanged>,
     audio: Res<Audio>,
     sound_sources: Res<GameSoundSources>,
     transition_entity: Query<Entity, With<DelayedStateChange>>,
 ) {
-    for (interaction, mut color, _children) in &mut interaction_query {
+    for (interaction, mut color) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
                 *color = PRESSED_BUTTON.into();
@@ -126,12 +403,7 @@ pub fn button_system(
 
                 // ensure that we don't spawn more than one
                 if transition_entity.get_single().is_err() {
-                    // schedule app state transition
-                    let e = spawn_fade_in_black_screen(&mut commands, Duration::from_millis(400));
-                    commands.entity(e).insert(DelayedStateChange::new(
-                        AppState::InGame,
-                        Duration::from_millis(750),
-                    ));
+                    fade_to(&mut commands, AppState::MainMenu);
                 }
             }
             Interaction::Hovered => {
@@ -1,8 +1,11 @@
 use bevy::prelude::*;
 
 use crate::audio::BounceAudio;
+use crate::events::BouncedEvent;
+use crate::particles::Particle;
 
-#[derive(Debug, Default, Component, Deref, DerefMut)]
+#[derive(Debug, Default, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct Velocity(pub Vec2);
 
 pub fn apply_velocity(time: Res<Time>, mut query: Query<(&Velocity, &mut Transform)>) {
@@ -26,7 +29,8 @@ pub fn apply_velocity_to_text_styles(time: Res<Time>, mut query: Query<(&Velocit
 }
 
 /// The maximum speed at which something can move
-#[derive(Debug, Component)]
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct MaxSpeed(pub f32);
 
 pub fn move_towards(
@@ -52,7 +56,8 @@ pub fn move_towards(
 }
 
 /// Full position, for entities which may also be above the ground.
-#[derive(Debug, Default, Component, Deref, DerefMut)]
+#[derive(Debug, Default, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct SpatialPosition(pub Vec3);
 
 pub fn spatial_position_to_transform(mut query: Query<(&mut Transform, &SpatialPosition)>) {
@@ -62,7 +67,8 @@ pub fn spatial_position_to_transform(mut query: Query<(&mut Transform, &SpatialP
     }
 }
 
-#[derive(Debug, Default, Component, Deref, DerefMut)]
+#[derive(Debug, Default, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct SpatialVelocity(pub Vec3);
 
 pub fn apply_spatial_velocity(
@@ -79,7 +85,8 @@ pub fn apply_spatial_velocity(
 /// A gravity pull,
 /// made as a component so that we can control gravity per entity
 /// and remove it at will
-#[derive(Deref, DerefMut, Component)]
+#[derive(Deref, DerefMut, Component, Reflect)]
+#[reflect(Component)]
 pub struct Gravity(pub f32);
 
 impl Default for Gravity {
@@ -99,17 +106,26 @@ pub fn apply_gravity(time: Res<Time>, mut query: Query<(&mut SpatialVelocity, &G
 /// implement floor collision
 pub fn collide_on_floor(
     audio: Res<Audio>,
-    mut query: Query<(
-        &mut SpatialPosition,
-        &mut SpatialVelocity,
-        Option<&BounceAudio>,
-    )>,
+    mut query: Query<
+        (
+            &mut SpatialPosition,
+            &mut SpatialVelocity,
+            Option<&BounceAudio>,
+        ),
+        Without<Particle>,
+    >,
+    mut event_writer: EventWriter<BouncedEvent>,
 ) {
     for (mut pos, mut vel, bounce_sound) in &mut query {
         if pos.z <= 0. {
             pos.z = 0.;
             // check whether we're falling fast
             if vel.z < -500. {
+                event_writer.send(BouncedEvent {
+                    position: pos.0,
+                    speed: -vel.z,
+                });
+
                 // bounce! (dampened)
                 vel.z = -vel.z * 0.325;
 
@@ -125,12 +141,33 @@ pub fn collide_on_floor(
     }
 }
 
-const BOUND_W: f32 = 380.;
-const BOUND_H: f32 = 496.;
+/// The area in which things can move around,
+/// as defined by the current level layout
+#[derive(Debug, Copy, Clone, PartialEq, Resource)]
+pub struct PlayBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl PlayBounds {
+    /// the closest point within bounds
+    pub fn clamp(&self, pos: Vec2) -> Vec2 {
+        pos.clamp(self.min, self.max)
+    }
+}
+
+impl Default for PlayBounds {
+    fn default() -> Self {
+        PlayBounds {
+            min: Vec2::ZERO,
+            max: Vec2::new(380., 496.),
+        }
+    }
+}
 
-pub fn apply_boundaries(mut query: Query<&mut SpatialPosition>) {
+pub fn apply_boundaries(bounds: Res<PlayBounds>, mut query: Query<&mut SpatialPosition>) {
     for mut pos in &mut query {
-        pos.0.x = pos.0.x.clamp(0., BOUND_W);
-        pos.0.y = pos.0.y.clamp(0., BOUND_H);
+        pos.0.x = pos.0.x.clamp(bounds.min.x, bounds.max.x);
+        pos.0.y = pos.0.y.clamp(bounds.min.y, bounds.max.y);
     }
 }
@@ -0,0 +1,176 @@
+//! static obstacles on the play field, which block the guy and thrown items
+
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::NextWaveEvent,
+    helper::BaseTranslation,
+    level::LevelObstacle,
+    movement::{SpatialPosition, SpatialVelocity},
+};
+
+/// The kinds of obstacles found around the build site
+#[derive(
+    Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Reflect, FromReflect,
+)]
+pub enum ObstacleKind {
+    #[default]
+    Crate,
+    CementMixer,
+    Scaffolding,
+}
+
+impl ObstacleKind {
+    /// half the width and depth of the obstacle's footprint on the floor
+    pub fn half_size(self) -> Vec2 {
+        match self {
+            ObstacleKind::Crate => Vec2::new(18., 12.),
+            ObstacleKind::CementMixer => Vec2::new(24., 16.),
+            ObstacleKind::Scaffolding => Vec2::new(56., 10.),
+        }
+    }
+
+    /// how tall the obstacle looks (and how high items must be to fly over it)
+    pub fn height(self) -> f32 {
+        match self {
+            ObstacleKind::Crate => 28.,
+            ObstacleKind::CementMixer => 40.,
+            ObstacleKind::Scaffolding => 72.,
+        }
+    }
+
+    /// colors of the front and top faces
+    fn colors(self) -> (Color, Color) {
+        match self {
+            ObstacleKind::Crate => (Color::rgb(0.55, 0.36, 0.18), Color::rgb(0.7, 0.5, 0.28)),
+            ObstacleKind::CementMixer => (Color::rgb(0.85, 0.45, 0.12), Color::rgb(0.6, 0.6, 0.62)),
+            ObstacleKind::Scaffolding => (Color::rgb(0.42, 0.44, 0.5), Color::rgb(0.62, 0.64, 0.7)),
+        }
+    }
+}
+
+/// A static obstacle.
+/// The entity's translation marks the center of its footprint on the floor.
+#[derive(Debug, Component, Reflect)]
+#[reflect(Component)]
+pub struct Obstacle {
+    pub kind: ObstacleKind,
+    pub half_size: Vec2,
+    pub height: f32,
+}
+
+impl Default for Obstacle {
+    fn default() -> Self {
+        Obstacle::new(ObstacleKind::default())
+    }
+}
+
+impl Obstacle {
+    pub fn new(kind: ObstacleKind) -> Self {
+        Obstacle {
+            kind,
+            half_size: kind.half_size(),
+            height: kind.height(),
+        }
+    }
+
+    /// Whether the given floor position is inside the footprint.
+    pub fn contains(&self, center: Vec2, pos: Vec2) -> bool {
+        let rel = (pos - center).abs();
+        rel.x < self.half_size.x && rel.y < self.half_size.y
+    }
+}
+
+/// Spawn an obstacle at the given floor position.
+/// Visuals are attached later by [`attach_obstacle_visuals`].
+pub fn spawn_obstacle(commands: &mut Commands, kind: ObstacleKind, pos: Vec2) -> Entity {
+    commands
+        .spawn((
+            Obstacle::new(kind),
+            SpatialBundle::from_transform(Transform::from_translation(pos.extend(0.125))),
+            BaseTranslation(Vec2::new(0., -kind.half_size().y)),
+        ))
+        .id()
+}
+
+/// system: give newly spawned obstacles their looks
+pub fn attach_obstacle_visuals(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(Entity, &Obstacle), Added<Obstacle>>,
+) {
+    for (entity, obstacle) in &query {
+        let (front_color, top_color) = obstacle.kind.colors();
+        let w = obstacle.half_size.x * 2.;
+        let d = obstacle.half_size.y * 2.;
+        let h = obstacle.height * 0.5;
+
+        // the front face rises from the bottom edge of the footprint
+        let front = commands
+            .spawn(MaterialMesh2dBundle {
+                mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
+                material: materials.add(ColorMaterial::from(front_color)),
+                transform: Transform::from_xyz(0., -obstacle.half_size.y + h * 0.5, 0.)
+                    .with_scale(Vec3::new(w, h, 1.)),
+                ..default()
+            })
+            .id();
+        // the top face covers the footprint, lifted by the obstacle's height
+        let top = commands
+            .spawn(MaterialMesh2dBundle {
+                mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
+                material: materials.add(ColorMaterial::from(top_color)),
+                transform: Transform::from_xyz(0., h, 0.0001).with_scale(Vec3::new(w, d, 1.)),
+                ..default()
+            })
+            .id();
+
+        commands.entity(entity).push_children(&[front, top]);
+    }
+}
+
+/// system: push the guy and items out of obstacles
+pub fn collide_with_obstacles(
+    query_obstacles: Query<(&Obstacle, &Transform)>,
+    mut query: Query<(&mut SpatialPosition, Option<&BaseTranslation>), With<SpatialVelocity>>,
+) {
+    for (mut pos, base_translation) in &mut query {
+        let offset = base_translation.map(|b| b.0).unwrap_or_default();
+        for (obstacle, transform) in &query_obstacles {
+            // things flying high enough pass over it
+            if pos.z >= obstacle.height {
+                continue;
+            }
+            let center = transform.translation.truncate();
+            let feet = pos.truncate() + offset;
+            if !obstacle.contains(center, feet) {
+                continue;
+            }
+
+            // push out along the axis of least penetration
+            let rel = feet - center;
+            let overlap = obstacle.half_size - rel.abs();
+            if overlap.x < overlap.y {
+                pos.x += overlap.x * rel.x.signum();
+            } else {
+                pos.y += overlap.y * rel.y.signum();
+            }
+        }
+    }
+}
+
+/// system: clear the obstacles of the previous wave
+/// (those of the level layout stay in place)
+pub fn clear_obstacles_on_next_wave(
+    mut commands: Commands,
+    mut event_reader: EventReader<NextWaveEvent>,
+    query: Query<Entity, (With<Obstacle>, Without<LevelObstacle>)>,
+) {
+    if event_reader.iter().next().is_some() {
+        for entity in &query {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,371 @@
+//! a small CPU particle system for debris, dust, smoke and the like.
+//!
+//! Particles are plain colored sprites moving with
+//! `SpatialPosition`, `SpatialVelocity` and `Gravity`.
+//! They are pooled: dead particles are hidden and reused
+//! instead of being despawned.
+//!
+//! Each kind of effect is configured in a RON file
+//! in `assets/particles`, for instance:
+//!
+//! ```ron
+//! (
+//!     // particles emitted at once, for bursts
+//!     burst: 12,
+//!     // particles emitted per second, for continuous emitters
+//!     rate: 0.,
+//!     // ranges to pick from, in seconds and pixels per second
+//!     lifetime: (0.4, 0.8),
+//!     speed: (40., 160.),
+//!     lift: (200., 500.),
+//!     gravity: 1600.,
+//!     // fraction of velocity lost per second
+//!     drag: 0.5,
+//!     // size at birth and at death
+//!     size: (4., 2.),
+//!     start_color: (0.5, 0.4, 0.3, 1.),
+//!     end_color: (0.5, 0.4, 0.3, 0.),
+//! )
+//! ```
+
+use bevy::{prelude::*, reflect::TypeUuid, utils::HashMap};
+use rand::Rng as _;
+use serde::Deserialize;
+
+use crate::{
+    events::{BouncedEvent, ExplodedEvent, ExplosiveKind},
+    helper::BaseTranslation,
+    movement::{Gravity, SpatialPosition, SpatialVelocity},
+};
+
+/// The maximum number of particles alive at once
+const MAX_PARTICLES: usize = 400;
+
+/// The kinds of particle effects in the game
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect, FromReflect)]
+pub enum ParticleEffect {
+    #[default]
+    Debris,
+    Smoke,
+    Dust,
+    Sparks,
+    Steam,
+}
+
+impl ParticleEffect {
+    pub const ALL: [ParticleEffect; 5] = [
+        ParticleEffect::Debris,
+        ParticleEffect::Smoke,
+        ParticleEffect::Dust,
+        ParticleEffect::Sparks,
+        ParticleEffect::Steam,
+    ];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            ParticleEffect::Debris => "debris",
+            ParticleEffect::Smoke => "smoke",
+            ParticleEffect::Dust => "dust",
+            ParticleEffect::Sparks => "sparks",
+            ParticleEffect::Steam => "steam",
+        }
+    }
+}
+
+/// How an effect emits its particles
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "b8e4f7a2-3c6d-4e1f-9a0b-6d2c8e5f1a47"]
+pub struct EmitterConfig {
+    /// number of particles emitted at once in a burst
+    #[serde(default)]
+    pub burst: u32,
+    /// number of particles emitted per second by continuous emitters
+    #[serde(default)]
+    pub rate: f32,
+    /// how long each particle lives, in seconds
+    pub lifetime: (f32, f32),
+    /// horizontal speed
+    pub speed: (f32, f32),
+    /// vertical (upwards) speed
+    #[serde(default)]
+    pub lift: (f32, f32),
+    #[serde(default)]
+    pub gravity: f32,
+    /// fraction of velocity lost per second
+    #[serde(default)]
+    pub drag: f32,
+    /// size at birth and at death
+    pub size: (f32, f32),
+    pub start_color: (f32, f32, f32, f32),
+    pub end_color: (f32, f32, f32, f32),
+}
+
+/// The handles to all emitter configurations
+#[derive(Debug, Resource)]
+pub struct ParticleEffects(HashMap<ParticleEffect, Handle<EmitterConfig>>);
+
+/// A single particle
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct Particle {
+    active: bool,
+    age: f32,
+    lifetime: f32,
+    drag: f32,
+    size: (f32, f32),
+    start_color: [f32; 4],
+    end_color: [f32; 4],
+}
+
+/// Particles which are dead and ready to be reused
+#[derive(Debug, Default, Resource)]
+pub struct ParticlePool {
+    free: Vec<Entity>,
+    /// number of particles currently alive
+    live: usize,
+}
+
+/// Continuously emits particles at the entity's position
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct ParticleEmitter {
+    pub effect: ParticleEffect,
+    /// where to emit from, relative to the entity's position
+    pub offset: Vec3,
+    /// particles owed since the last emission
+    pending: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(effect: ParticleEffect, offset: Vec3) -> Self {
+        ParticleEmitter {
+            effect,
+            offset,
+            pending: 0.,
+        }
+    }
+}
+
+/// system: load all emitter configurations
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let effects = ParticleEffect::ALL
+        .iter()
+        .map(|effect| {
+            let handle: Handle<EmitterConfig> =
+                asset_server.load(format!("particles/{}.particle.ron", effect.file_name()));
+            (*effect, handle)
+        })
+        .collect();
+
+    commands.insert_resource(ParticleEffects(effects));
+}
+
+/// Emit `count` particles of the given configuration at the given position,
+/// reusing dead particles when possible.
+fn emit(
+    commands: &mut Commands,
+    pool: &mut ParticlePool,
+    config: &EmitterConfig,
+    position: Vec3,
+    count: u32,
+) {
+    let mut rng = rand::thread_rng();
+    let mut pick = |(min, max): (f32, f32)| {
+        if max > min {
+            rng.gen_range(min..max)
+        } else {
+            min
+        }
+    };
+
+    for _ in 0..count {
+        let entity = match pool.free.pop() {
+            Some(entity) => entity,
+            None if pool.live < MAX_PARTICLES => commands
+                .spawn((
+                    SpriteBundle {
+                        // translate off screen,
+                        // letting spatial position system take care of it
+                        transform: Transform::from_translation(Vec3::new(0., 9e7, 0.)),
+                        ..default()
+                    },
+                    BaseTranslation::default(),
+                ))
+                .id(),
+            // too many particles already
+            None => return,
+        };
+        pool.live += 1;
+
+        let angle = pick((0., std::f32::consts::TAU));
+        let velocity = (Vec2::from_angle(angle) * pick(config.speed)).extend(pick(config.lift));
+        let start_color = [
+            config.start_color.0,
+            config.start_color.1,
+            config.start_color.2,
+            config.start_color.3,
+        ];
+
+        commands.entity(entity).insert((
+            Particle {
+                active: true,
+                age: 0.,
+                lifetime: pick(config.lifetime).max(0.01),
+                drag: config.drag,
+                size: config.size,
+                start_color,
+                end_color: [
+                    config.end_color.0,
+                    config.end_color.1,
+                    config.end_color.2,
+                    config.end_color.3,
+                ],
+            },
+            SpatialPosition(position),
+            SpatialVelocity(velocity),
+            Gravity(config.gravity),
+            Sprite {
+                color: Color::from(start_color),
+                custom_size: Some(Vec2::splat(config.size.0)),
+                ..default()
+            },
+            Visibility::VISIBLE,
+        ));
+    }
+}
+
+/// system: debris and smoke on explosions
+pub fn emit_on_explosion(
+    mut commands: Commands,
+    mut pool: ResMut<ParticlePool>,
+    effects: Res<ParticleEffects>,
+    configs: Res<Assets<EmitterConfig>>,
+    mut event_reader: EventReader<ExplodedEvent>,
+) {
+    for ExplodedEvent { kind, position } in event_reader.iter() {
+        // dynamite makes for a smaller mess
+        let amount = match kind {
+            ExplosiveKind::Dynamite => 0.5,
+            ExplosiveKind::Bomb => 1.,
+        };
+        for effect in [ParticleEffect::Debris, ParticleEffect::Smoke] {
+            if let Some(config) = configs.get(&effects.0[&effect]) {
+                let count = (config.burst as f32 * amount).round() as u32;
+                emit(&mut commands, &mut pool, config, *position, count);
+            }
+        }
+    }
+}
+
+/// system: dust puffs when things bounce off the floor
+pub fn emit_on_bounce(
+    mut commands: Commands,
+    mut pool: ResMut<ParticlePool>,
+    effects: Res<ParticleEffects>,
+    configs: Res<Assets<EmitterConfig>>,
+    mut event_reader: EventReader<BouncedEvent>,
+) {
+    let Some(config) = configs.get(&effects.0[&ParticleEffect::Dust]) else {
+        event_reader.clear();
+        return;
+    };
+
+    for BouncedEvent { position, speed } in event_reader.iter() {
+        // harder landings raise more dust
+        let amount = (speed / 1500.).clamp(0.3, 1.);
+        let count = (config.burst as f32 * amount).round() as u32;
+        emit(&mut commands, &mut pool, config, *position, count);
+    }
+}
+
+/// system: emit particles from continuous emitters
+pub fn emit_continuous(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pool: ResMut<ParticlePool>,
+    effects: Res<ParticleEffects>,
+    configs: Res<Assets<EmitterConfig>>,
+    mut query: Query<(&mut ParticleEmitter, &SpatialPosition)>,
+) {
+    for (mut emitter, pos) in &mut query {
+        let Some(config) = configs.get(&effects.0[&emitter.effect]) else {
+            continue;
+        };
+        emitter.pending += config.rate * time.delta_seconds();
+        let count = emitter.pending.floor();
+        if count >= 1. {
+            emitter.pending -= count;
+            emit(
+                &mut commands,
+                &mut pool,
+                config,
+                pos.0 + emitter.offset,
+                count as u32,
+            );
+        }
+    }
+}
+
+/// system: age particles, fade them out,
+/// and put them back in the pool when they die
+pub fn update_particles(
+    time: Res<Time>,
+    mut pool: ResMut<ParticlePool>,
+    mut query: Query<(
+        Entity,
+        &mut Particle,
+        &mut SpatialPosition,
+        &mut SpatialVelocity,
+        &mut Gravity,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut particle, mut pos, mut vel, mut gravity, mut sprite, mut visibility) in
+        &mut query
+    {
+        if !particle.active {
+            continue;
+        }
+
+        particle.age += delta;
+        if particle.age >= particle.lifetime {
+            // back to the pool
+            particle.active = false;
+            visibility.is_visible = false;
+            vel.0 = Vec3::ZERO;
+            gravity.0 = 0.;
+            pool.live -= 1;
+            pool.free.push(entity);
+            continue;
+        }
+
+        vel.0 *= (1. - particle.drag * delta).max(0.);
+
+        // rest on the floor, sliding to a stop
+        if pos.z <= 0. && vel.z <= 0. {
+            pos.z = 0.;
+            vel.z = 0.;
+            vel.0 *= (1. - 8. * delta).max(0.);
+        }
+
+        let t = particle.age / particle.lifetime;
+        let [r0, g0, b0, a0] = particle.start_color;
+        let [r1, g1, b1, a1] = particle.end_color;
+        sprite.color = Color::rgba(
+            r0 + (r1 - r0) * t,
+            g0 + (g1 - g0) * t,
+            b0 + (b1 - b0) * t,
+            a0 + (a1 - a0) * t,
+        );
+        let (size0, size1) = particle.size;
+        sprite.custom_size = Some(Vec2::splat(size0 + (size1 - size0) * t));
+    }
+}
+
+/// system: forget about pooled particles when leaving the game
+/// (they are despawned along with everything else)
+pub fn reset(mut pool: ResMut<ParticlePool>) {
+    *pool = ParticlePool::default();
+}
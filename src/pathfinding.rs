@@ -0,0 +1,381 @@
+//! Grid-based pathfinding, so that the guy can walk around obstacles.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use bevy::prelude::*;
+
+use crate::{movement::PlayBounds, obstacle::Obstacle};
+
+/// The size of each navigation cell, in world units
+const CELL_SIZE: f32 = 12.;
+
+/// Extra clearance around obstacles, roughly half the width of the guy
+const CLEARANCE: f32 = 10.;
+
+/// A navigation grid covering the walkable floor.
+/// Cells overlapping an obstacle (plus some clearance) are blocked.
+#[derive(Debug, Resource)]
+pub struct NavGrid {
+    origin: Vec2,
+    cols: i32,
+    rows: i32,
+    blocked: Vec<bool>,
+}
+
+impl Default for NavGrid {
+    fn default() -> Self {
+        let bounds = PlayBounds::default();
+        NavGrid::new(bounds.min, bounds.max)
+    }
+}
+
+impl NavGrid {
+    /// Create an empty navigation grid covering the given area.
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        let size = (max - min).max(Vec2::splat(CELL_SIZE));
+        let cols = (size.x / CELL_SIZE).ceil() as i32;
+        let rows = (size.y / CELL_SIZE).ceil() as i32;
+        NavGrid {
+            origin: min,
+            cols,
+            rows,
+            blocked: vec![false; (cols * rows) as usize],
+        }
+    }
+
+    /// Mark all cells touched by the given rectangle (plus clearance) as blocked.
+    pub fn block_rect(&mut self, center: Vec2, half_size: Vec2) {
+        let min = self.cell_of(center - half_size - CLEARANCE);
+        let max = self.cell_of(center + half_size + CLEARANCE);
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                if let Some(i) = self.index(IVec2::new(x, y)) {
+                    self.blocked[i] = true;
+                }
+            }
+        }
+    }
+
+    fn cell_of(&self, pos: Vec2) -> IVec2 {
+        let rel = (pos - self.origin) / CELL_SIZE;
+        IVec2::new(
+            (rel.x.floor() as i32).clamp(0, self.cols - 1),
+            (rel.y.floor() as i32).clamp(0, self.rows - 1),
+        )
+    }
+
+    fn center_of(&self, cell: IVec2) -> Vec2 {
+        self.origin + (cell.as_vec2() + 0.5) * CELL_SIZE
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        if cell.x < 0 || cell.y < 0 || cell.x >= self.cols || cell.y >= self.rows {
+            None
+        } else {
+            Some((cell.y * self.cols + cell.x) as usize)
+        }
+    }
+
+    fn is_free(&self, cell: IVec2) -> bool {
+        self.index(cell).map(|i| !self.blocked[i]).unwrap_or(false)
+    }
+
+    /// Whether the given position is not blocked by any obstacle.
+    pub fn is_walkable(&self, pos: Vec2) -> bool {
+        self.is_free(self.cell_of(pos))
+    }
+
+    /// Find the free cell closest to the given one (breadth-first).
+    fn nearest_free(&self, cell: IVec2) -> Option<IVec2> {
+        if self.is_free(cell) {
+            return Some(cell);
+        }
+        let max_radius = self.cols.max(self.rows);
+        (1..max_radius).find_map(|r| {
+            let mut candidates = (-r..=r)
+                .flat_map(|d| {
+                    [
+                        IVec2::new(cell.x + d, cell.y - r),
+                        IVec2::new(cell.x + d, cell.y + r),
+                        IVec2::new(cell.x - r, cell.y + d),
+                        IVec2::new(cell.x + r, cell.y + d),
+                    ]
+                })
+                .filter(|c| self.is_free(*c))
+                .collect::<Vec<_>>();
+            candidates.sort_by_key(|c| (*c - cell).as_vec2().length_squared() as i32);
+            candidates.first().copied()
+        })
+    }
+
+    /// Whether a straight line between two points crosses no blocked cell.
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        let steps = ((to - from).length() / (CELL_SIZE * 0.25)).ceil() as i32;
+        (0..=steps).all(|i| {
+            let t = if steps == 0 {
+                0.
+            } else {
+                i as f32 / steps as f32
+            };
+            self.is_walkable(from.lerp(to, t))
+        })
+    }
+
+    /// Find a path between two floor positions.
+    ///
+    /// Returns the list of waypoints to walk through, in order,
+    /// ending at the target (or the closest reachable position to it).
+    /// The starting position is not included.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        // quick path: nothing in the way
+        if self.line_of_sight(from, to) {
+            return Some(vec![to]);
+        }
+
+        let start = self.nearest_free(self.cell_of(from))?;
+        let goal_cell = self.cell_of(to);
+        let goal = self.nearest_free(goal_cell)?;
+        // if the target is inside an obstacle, stop at the closest free spot
+        let target = if goal == goal_cell {
+            to
+        } else {
+            self.center_of(goal)
+        };
+
+        let cells = self.a_star(start, goal)?;
+
+        // convert to world positions, then cut corners
+        // whenever there is a clear line of sight
+        let mut waypoints: Vec<Vec2> = cells
+            .into_iter()
+            .skip(1)
+            .map(|cell| self.center_of(cell))
+            .collect();
+        waypoints.pop();
+        waypoints.push(target);
+
+        let mut smoothed = Vec::with_capacity(waypoints.len());
+        let mut current = from;
+        let mut i = 0;
+        while i < waypoints.len() {
+            // look for the furthest waypoint in direct sight
+            let mut furthest = i;
+            for j in (i + 1..waypoints.len()).rev() {
+                if self.line_of_sight(current, waypoints[j]) {
+                    furthest = j;
+                    break;
+                }
+            }
+            current = waypoints[furthest];
+            smoothed.push(current);
+            i = furthest + 1;
+        }
+
+        Some(smoothed)
+    }
+
+    /// A* search over the grid with 8-way movement (no corner cutting).
+    fn a_star(&self, start: IVec2, goal: IVec2) -> Option<Vec<IVec2>> {
+        let n = self.blocked.len();
+        let mut g_score = vec![f32::INFINITY; n];
+        let mut came_from: Vec<Option<usize>> = vec![None; n];
+        let mut open = BinaryHeap::new();
+
+        let start_i = self.index(start)?;
+        let goal_i = self.index(goal)?;
+        g_score[start_i] = 0.;
+        open.push(OpenNode {
+            f_score: octile_distance(start, goal),
+            cell: start,
+        });
+
+        while let Some(OpenNode { cell, .. }) = open.pop() {
+            let i = self.index(cell).unwrap();
+            if i == goal_i {
+                // rebuild path
+                let mut path = vec![cell];
+                let mut current = i;
+                while let Some(prev) = came_from[current] {
+                    current = prev;
+                    path.push(IVec2::new(prev as i32 % self.cols, prev as i32 / self.cols));
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for (dx, dy) in NEIGHBOURS {
+                let next = IVec2::new(cell.x + dx, cell.y + dy);
+                if !self.is_free(next) {
+                    continue;
+                }
+                // diagonal moves must not cut through blocked corners
+                if dx != 0
+                    && dy != 0
+                    && (!self.is_free(IVec2::new(cell.x + dx, cell.y))
+                        || !self.is_free(IVec2::new(cell.x, cell.y + dy)))
+                {
+                    continue;
+                }
+                let next_i = self.index(next).unwrap();
+                let step = if dx != 0 && dy != 0 {
+                    std::f32::consts::SQRT_2
+                } else {
+                    1.
+                };
+                let tentative = g_score[i] + step;
+                if tentative < g_score[next_i] {
+                    g_score[next_i] = tentative;
+                    came_from[next_i] = Some(i);
+                    open.push(OpenNode {
+                        f_score: tentative + octile_distance(next, goal),
+                        cell: next,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+fn octile_distance(a: IVec2, b: IVec2) -> f32 {
+    let d = (a - b).abs();
+    let (min, max) = (d.x.min(d.y) as f32, d.x.max(d.y) as f32);
+    max + (std::f32::consts::SQRT_2 - 1.) * min
+}
+
+#[derive(Debug, PartialEq)]
+struct OpenNode {
+    f_score: f32,
+    cell: IVec2,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed, so that the binary heap pops the lowest score first
+        other.f_score.total_cmp(&self.f_score)
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// system: rebuild the navigation grid whenever obstacles come and go
+/// or the walkable area changes
+pub fn rebuild_nav_grid(
+    mut nav_grid: ResMut<NavGrid>,
+    bounds: Res<PlayBounds>,
+    query_added: Query<(), Added<Obstacle>>,
+    removed: RemovedComponents<Obstacle>,
+    query: Query<(&Obstacle, &Transform)>,
+) {
+    if !bounds.is_changed() && query_added.is_empty() && removed.iter().next().is_none() {
+        return;
+    }
+
+    let mut grid = NavGrid::new(bounds.min, bounds.max);
+    for (obstacle, transform) in &query {
+        grid.block_rect(transform.translation.truncate(), obstacle.half_size);
+    }
+    *nav_grid = grid;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 120x120 area (10x10 cells),
+    /// with a wall down the middle leaving the top and bottom rows free
+    fn walled_grid() -> NavGrid {
+        let mut grid = NavGrid::new(Vec2::ZERO, Vec2::splat(120.));
+        grid.block_rect(Vec2::new(60., 60.), Vec2::new(6., 30.));
+        grid
+    }
+
+    #[test]
+    fn straight_path_when_nothing_in_the_way() {
+        let grid = NavGrid::new(Vec2::ZERO, Vec2::splat(120.));
+        let to = Vec2::new(100., 90.);
+        assert_eq!(grid.find_path(Vec2::new(10., 10.), to), Some(vec![to]));
+    }
+
+    #[test]
+    fn path_goes_around_obstacles() {
+        let grid = walled_grid();
+        let from = Vec2::new(12., 60.);
+        let to = Vec2::new(108., 60.);
+        assert!(!grid.line_of_sight(from, to));
+
+        let path = grid.find_path(from, to).unwrap();
+        assert_eq!(path.last(), Some(&to));
+        let mut current = from;
+        for waypoint in &path {
+            assert!(grid.line_of_sight(current, *waypoint));
+            current = *waypoint;
+        }
+    }
+
+    #[test]
+    fn path_is_smoothed() {
+        let grid = walled_grid();
+        let from = Vec2::new(12., 60.);
+        let path = grid.find_path(from, Vec2::new(108., 60.)).unwrap();
+        // no waypoint could have been skipped
+        let mut previous = from;
+        for pair in path.windows(2) {
+            assert!(!grid.line_of_sight(previous, pair[1]));
+            previous = pair[0];
+        }
+        assert!(path.len() < 6, "{:?}", path);
+    }
+
+    #[test]
+    fn path_stops_short_of_a_blocked_target() {
+        let grid = walled_grid();
+        let path = grid
+            .find_path(Vec2::new(12., 60.), Vec2::new(60., 60.))
+            .unwrap();
+        let end = *path.last().unwrap();
+        assert!(grid.is_walkable(end));
+        assert!(end.distance(Vec2::new(60., 60.)) < 36.);
+    }
+
+    #[test]
+    fn no_path_through_a_closed_wall() {
+        let mut grid = walled_grid();
+        grid.block_rect(Vec2::new(60., 60.), Vec2::new(6., 60.));
+        assert_eq!(
+            grid.find_path(Vec2::new(12., 60.), Vec2::new(108., 60.)),
+            None
+        );
+    }
+
+    #[test]
+    fn a_star_does_not_cut_corners() {
+        let mut grid = NavGrid::new(Vec2::ZERO, Vec2::splat(36.));
+        // block the middle cell, with no clearance beyond it
+        grid.blocked[4] = true;
+        let path = grid.a_star(IVec2::new(0, 1), IVec2::new(1, 0)).unwrap();
+        assert_eq!(
+            path,
+            vec![IVec2::new(0, 1), IVec2::new(0, 0), IVec2::new(1, 0)]
+        );
+    }
+}
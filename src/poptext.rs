@@ -6,6 +6,7 @@ use crate::{
     animation::FadeOut,
     helper::{Fixed, TimeToLive},
     movement::Velocity,
+    screen::ScreenFit,
 };
 use bevy::utils::Duration;
 
@@ -62,3 +63,16 @@ pub fn spawn_popup_text(
         })
         .id()
 }
+
+/// system: move new popup texts from play field coordinates
+/// to where the play field is in the UI
+pub fn place_in_field(fit: Res<ScreenFit>, mut query: Query<&mut Style, Added<PopupText>>) {
+    for mut style in &mut query {
+        let (Val::Px(left), Val::Px(bottom)) = (style.position.left, style.position.bottom) else {
+            continue;
+        };
+        let pos = fit.world_to_ui(Vec2::new(left, bottom));
+        style.position.left = Val::Px(pos.x);
+        style.position.bottom = Val::Px(pos.y);
+    }
+}
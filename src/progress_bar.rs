@@ -3,8 +3,10 @@
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 
 use crate::{
-    events::{BombDisarmedEvent, DisarmCancelledEvent, DisarmProgressEvent, GuyHurtEvent},
+    accessibility::Palette,
+    guy::{GuyState, Player},
     helper::Fixed,
+    settings::Settings,
 };
 
 #[derive(Bundle)]
@@ -19,20 +21,32 @@ where
     material_mesh_2d: MaterialMesh2dBundle<ColorMaterial>,
 }
 
-#[derive(Default, Component)]
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct ProgressBarOuterMesh;
 
-#[derive(Default, Component)]
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct ProgressBarInnerMesh;
 
 const PROGRESS_BAR_W: f32 = 30.;
 const PROGRESS_BAR_H: f32 = 6.;
+/// a thicker bar, for palettes which do not rely on color alone
+const PROGRESS_BAR_H_THICK: f32 = 9.;
 
 pub fn spawn_progress_bar(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<ColorMaterial>>,
+    palette: Palette,
 ) -> Entity {
+    let (back_color, fill_color) = palette.progress_colors();
+    let height = if palette.has_shape_cues() {
+        PROGRESS_BAR_H_THICK
+    } else {
+        PROGRESS_BAR_H
+    };
+
     let mesh = Mesh::from(shape::Quad::new(Vec2::from_array([1., 1.])));
 
     let outer = commands
@@ -41,9 +55,9 @@ pub fn spawn_progress_bar(
             fixed: Fixed,
             material_mesh_2d: MaterialMesh2dBundle {
                 transform: Transform::from_translation([0., 15., 0.5].into())
-                    .with_scale([PROGRESS_BAR_W, PROGRESS_BAR_H, 1.].into()),
+                    .with_scale([PROGRESS_BAR_W, height, 1.].into()),
                 mesh: meshes.add(mesh).into(),
-                material: materials.add(ColorMaterial::from(Color::RED)),
+                material: materials.add(ColorMaterial::from(back_color)),
                 visibility: Visibility { is_visible: false },
                 ..default()
             },
@@ -60,7 +74,7 @@ pub fn spawn_progress_bar(
                 transform: Transform::from_translation([0., 0., 0.25].into())
                     .with_scale([0., 1., 1.].into()),
                 mesh: meshes.add(mesh).into(),
-                material: materials.add(ColorMaterial::from(Color::GREEN)),
+                material: materials.add(ColorMaterial::from(fill_color)),
                 ..default()
             },
         })
@@ -71,41 +85,51 @@ pub fn spawn_progress_bar(
     outer
 }
 
-pub fn update_progress_bar(
-    mut disarm_progress_event_reader: EventReader<DisarmProgressEvent>,
-    mut outer_query: Query<&mut Visibility, With<ProgressBarOuterMesh>>,
-    mut inner_query: Query<&mut Transform, With<ProgressBarInnerMesh>>,
+/// system: give each guy joining the field a progress bar,
+/// be it newly spawned or loaded from a snapshot
+pub fn attach_progress_bars(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<Settings>,
+    query: Query<Entity, Added<Player>>,
 ) {
-    for DisarmProgressEvent(progress) in disarm_progress_event_reader.iter() {
-        if let Ok(mut visibility) = outer_query.get_single_mut() {
-            visibility.is_visible = true;
-        }
-        if let Ok(mut transform) = inner_query.get_single_mut() {
-            transform.scale.x = *progress;
-            transform.translation.x = (progress - 1.) * 0.5;
-        }
+    for guy in &query {
+        let progress_bar =
+            spawn_progress_bar(&mut commands, &mut meshes, &mut materials, settings.palette);
+        commands.entity(guy).add_child(progress_bar);
     }
-
-    disarm_progress_event_reader.clear();
 }
 
-/// system: hide progress bar when not disarming
-pub fn clear_progress_bar(
-    mut disarmed_event_reader: EventReader<BombDisarmedEvent>,
-    mut cancelled_event_reader: EventReader<DisarmCancelledEvent>,
-    mut guy_hurt_event_reader: EventReader<GuyHurtEvent>,
-    mut query: Query<&mut Visibility, With<ProgressBarOuterMesh>>,
+/// system: show each guy's progress bar while disarming,
+/// filled as far as the disarming has got
+pub fn update_progress_bar(
+    query_guy: Query<(&GuyState, &Children)>,
+    mut outer_query: Query<(&mut Visibility, &Children), With<ProgressBarOuterMesh>>,
+    mut inner_query: Query<&mut Transform, With<ProgressBarInnerMesh>>,
 ) {
-    let event_chain = disarmed_event_reader
-        .iter()
-        .map(|_| ())
-        .chain(cancelled_event_reader.iter().map(|_| ()))
-        .chain(guy_hurt_event_reader.iter().map(|_| ()));
-
-    for _ in event_chain {
-        if let Ok(mut visibility) = query.get_single_mut() {
-            visibility.is_visible = false;
-            break;
+    for (guy_state, children) in &query_guy {
+        let progress = match guy_state {
+            GuyState::Disarming { progress, .. } => Some(progress.min(1.)),
+            _ => None,
+        };
+
+        for child in children {
+            let Ok((mut visibility, bar_children)) = outer_query.get_mut(*child) else {
+                continue;
+            };
+            if visibility.is_visible != progress.is_some() {
+                visibility.is_visible = progress.is_some();
+            }
+            let Some(progress) = progress else {
+                continue;
+            };
+            for bar_child in bar_children {
+                if let Ok(mut transform) = inner_query.get_mut(*bar_child) {
+                    transform.scale.x = progress;
+                    transform.translation.x = (progress - 1.) * 0.5;
+                }
+            }
         }
     }
 }
@@ -0,0 +1,260 @@
+//! saving a run in progress, to pick it up again later.
+//!
+//! The run is saved each time a wave is finished,
+//! with everything needed to play on from the start of the next wave
+//! as if the game had never been closed:
+//! the scores, the state of the random number generators, the modifiers,
+//! what counts towards achievements and lifetime statistics, ...
+//! Once the game is over, the saved run is gone.
+//!
+//! Hot-seat turns and demos are not saved,
+//! and neither is the last wave, which only leads to the game over screen.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    achievements::RunRecord,
+    attract::DemoMode,
+    daily::{DailyRun, Modifiers},
+    events::WaveFinishedEvent,
+    ingame::{GameMode, Wave},
+    lifetime::RunSummary,
+    scores::{GameScores, Stats},
+    spawner::{Rng, RunSeed},
+    waves::{PreviewWave, WAVE_DESCRIPTORS},
+};
+
+/// The key under which the run in progress is saved
+const SAVE_KEY: &str = "run";
+
+/// The version of what is saved.
+/// Bump it whenever [`SavedRun`] changes in a way which older saves would not fit,
+/// so that those are discarded instead of resumed wrongly.
+const SAVE_VERSION: u32 = 2;
+
+/// A run saved in between waves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedRun {
+    version: u32,
+    pub mode: GameMode,
+    /// the wave to start at
+    pub wave: u16,
+    pub scores: GameScores,
+    pub rng: Rng,
+    pub run_seed: RunSeed,
+    pub modifiers: Modifiers,
+    pub daily: Option<DailyRun>,
+    /// the record towards achievements
+    pub record: RunRecord,
+    /// the playtime, and what was already added to the lifetime statistics
+    pub summary: RunSummary,
+}
+
+/// Just the version of a saved run, to be checked before the rest is read
+#[derive(Deserialize)]
+struct SavedVersion {
+    version: u32,
+}
+
+/// Why a saved run cannot be resumed
+#[derive(Debug, PartialEq)]
+enum ResumeError {
+    /// saved by another version of the game
+    OtherVersion(u32),
+    Malformed(String),
+    /// saved at a wave which does not exist
+    NoSuchWave(u16),
+}
+
+impl SavedRun {
+    /// Load the saved run, if there is one which this version of the game can resume
+    pub fn load() -> Option<Self> {
+        let data = crate::storage::load_text(SAVE_KEY)?;
+        match Self::from_ron(&data) {
+            Ok(saved) => Some(saved),
+            Err(ResumeError::OtherVersion(_)) => {
+                info!("Discarding a run saved by another version of the game");
+                crate::storage::remove(SAVE_KEY);
+                None
+            }
+            Err(e) => {
+                warn!("Could not resume the saved run: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// Read a saved run, checking that it can be resumed
+    fn from_ron(data: &str) -> Result<Self, ResumeError> {
+        let malformed = |e: ron::error::SpannedError| ResumeError::Malformed(e.to_string());
+        let SavedVersion { version } = ron::from_str(data).map_err(malformed)?;
+        if version != SAVE_VERSION {
+            return Err(ResumeError::OtherVersion(version));
+        }
+        let saved: SavedRun = ron::from_str(data).map_err(malformed)?;
+        if usize::from(saved.wave) >= WAVE_DESCRIPTORS.len() {
+            return Err(ResumeError::NoSuchWave(saved.wave));
+        }
+        Ok(saved)
+    }
+}
+
+/// A saved run to be resumed, as the next run begins
+#[derive(Debug, Resource)]
+pub struct ResumeRun(pub SavedRun);
+
+impl ResumeRun {
+    /// Resume the saved run, with everything it needs from the start
+    pub fn insert(commands: &mut Commands, saved: SavedRun) {
+        commands.insert_resource(saved.mode);
+        commands.insert_resource(saved.modifiers.clone());
+        if let Some(daily_run) = saved.daily {
+            commands.insert_resource(daily_run);
+        }
+        commands.insert_resource(ResumeRun(saved));
+    }
+}
+
+/// Whether the run being played may be saved
+fn is_saved(
+    mode: GameMode,
+    demo: Option<Res<DemoMode>>,
+    preview: Option<Res<PreviewWave>>,
+) -> bool {
+    // hot-seat turns belong to a tournament, which is not kept,
+    // and waves previewed in the editor are not runs at all
+    mode != GameMode::HotSeat && demo.is_none() && preview.is_none()
+}
+
+/// system: save the run as each wave is finished
+pub fn save_run(
+    mode: Res<GameMode>,
+    (wave, scores): (Res<Wave>, Res<GameScores>),
+    (rng, run_seed, modifiers): (Res<Rng>, Res<RunSeed>, Res<Modifiers>),
+    daily_run: Option<Res<DailyRun>>,
+    (record, summary): (Res<RunRecord>, Res<RunSummary>),
+    (demo, preview): (Option<Res<DemoMode>>, Option<Res<PreviewWave>>),
+    mut event_reader: EventReader<WaveFinishedEvent>,
+) {
+    if event_reader.iter().count() == 0 || !is_saved(*mode, demo, preview) {
+        return;
+    }
+    let next_wave = wave.0 + 1;
+    // only the shipped waves are resumed,
+    // as those saved from the wave editor may change in between
+    if usize::from(next_wave) >= WAVE_DESCRIPTORS.len() {
+        return;
+    }
+
+    let saved = SavedRun {
+        version: SAVE_VERSION,
+        mode: *mode,
+        wave: next_wave,
+        scores: scores.clone(),
+        rng: rng.clone(),
+        run_seed: *run_seed,
+        modifiers: modifiers.clone(),
+        daily: daily_run.map(|daily_run| *daily_run),
+        record: record.clone(),
+        summary: summary.clone(),
+    };
+    crate::storage::save(SAVE_KEY, &saved);
+}
+
+/// system: once the game is over, there is nothing left to resume
+pub fn discard_on_game_over(
+    mode: Res<GameMode>,
+    demo: Option<Res<DemoMode>>,
+    preview: Option<Res<PreviewWave>>,
+    query_game_over: Query<(), Added<Stats>>,
+) {
+    if !query_game_over.is_empty() && is_saved(*mode, demo, preview) {
+        crate::storage::remove(SAVE_KEY);
+    }
+}
+
+/// system: the run was resumed, or left
+pub fn end_resume(mut commands: Commands) {
+    commands.remove_resource::<ResumeRun>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved_run(version: u32, wave: u16) -> String {
+        let saved = SavedRun {
+            version,
+            mode: GameMode::Coop,
+            wave,
+            scores: GameScores {
+                score: 120,
+                ..default()
+            },
+            rng: Rng::from_seed(7),
+            run_seed: RunSeed {
+                seed: 7,
+                shared: false,
+            },
+            modifiers: Modifiers::default(),
+            daily: None,
+            record: RunRecord::default(),
+            summary: RunSummary::default(),
+        };
+        ron::to_string(&saved).unwrap()
+    }
+
+    #[test]
+    fn resumes_a_run_of_this_version() {
+        let saved = SavedRun::from_ron(&saved_run(SAVE_VERSION, 3)).unwrap();
+        assert_eq!(saved.mode, GameMode::Coop);
+        assert_eq!(saved.wave, 3);
+        assert_eq!(saved.scores.score, 120);
+        assert_eq!(saved.run_seed.seed, 7);
+    }
+
+    #[test]
+    fn discards_a_run_of_another_version() {
+        assert_eq!(
+            SavedRun::from_ron(&saved_run(SAVE_VERSION - 1, 3)).unwrap_err(),
+            ResumeError::OtherVersion(SAVE_VERSION - 1)
+        );
+        assert_eq!(
+            SavedRun::from_ron(&saved_run(SAVE_VERSION + 1, 3)).unwrap_err(),
+            ResumeError::OtherVersion(SAVE_VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn checks_the_version_before_the_rest() {
+        // an older save may not fit at all
+        let old = "(version: 1, mode: Classic, wave: 2)";
+        assert_eq!(
+            SavedRun::from_ron(old).unwrap_err(),
+            ResumeError::OtherVersion(1)
+        );
+    }
+
+    #[test]
+    fn does_not_resume_a_malformed_run() {
+        assert!(matches!(
+            SavedRun::from_ron("(mode: Classic, wave: 2)"),
+            Err(ResumeError::Malformed(_))
+        ));
+        let missing_fields = format!("(version: {}, wave: 2)", SAVE_VERSION);
+        assert!(matches!(
+            SavedRun::from_ron(&missing_fields),
+            Err(ResumeError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn does_not_resume_past_the_last_wave() {
+        let last = WAVE_DESCRIPTORS.len() as u16;
+        assert_eq!(
+            SavedRun::from_ron(&saved_run(SAVE_VERSION, last)).unwrap_err(),
+            ResumeError::NoSuchWave(last)
+        );
+    }
+}
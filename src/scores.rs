@@ -1,27 +1,80 @@
+use crate::accessibility::HudText;
 use crate::events::{
     BombDisarmedEvent, DynamiteDefusedEvent, ExplodedEvent, ExplosiveKind, GuyHurtEvent,
 };
+use crate::guy::Player;
+use crate::i18n::LocalizedText;
+use crate::screen::InField;
 use bevy::{prelude::*, time::Stopwatch};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Resource)]
+#[derive(Debug, Default, Clone, Resource, Serialize, Deserialize)]
 pub struct GameScores {
     pub dynamites_disarmed: u32,
     pub bombs_disarmed: u32,
     pub blasts_taken: u32,
     pub score: i32,
+    /// the share of each player, in co-op
+    pub players: Vec<PlayerScores>,
+}
+
+/// The scores earned by a single player.
+/// Bombs going off cost the whole team, so they are not in here.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PlayerScores {
+    pub dynamites_disarmed: u32,
+    pub bombs_disarmed: u32,
+    pub blasts_taken: u32,
+    pub score: i32,
 }
 
 impl GameScores {
+    /// Scores for a game with this many players
+    pub fn new(n_players: u8) -> Self {
+        GameScores {
+            players: vec![PlayerScores::default(); n_players as usize],
+            ..default()
+        }
+    }
+
+    /// The scores of the given player
+    pub fn player_mut(&mut self, player: Player) -> &mut PlayerScores {
+        let index = player.0 as usize;
+        if self.players.len() <= index {
+            self.players.resize(index + 1, PlayerScores::default());
+        }
+        &mut self.players[index]
+    }
+
     #[inline]
-    pub fn add_dynamite_defused(&mut self) {
+    pub fn add_dynamite_defused(&mut self, player: Player) {
         self.dynamites_disarmed += 1;
         self.score += 1;
+        let player = self.player_mut(player);
+        player.dynamites_disarmed += 1;
+        player.score += 1;
     }
 
     #[inline]
-    pub fn add_bomb_disarmed(&mut self) {
+    pub fn add_bomb_disarmed(&mut self, player: Player) {
         self.bombs_disarmed += 1;
         self.score += 10;
+        let player = self.player_mut(player);
+        player.bombs_disarmed += 1;
+        player.score += 10;
+    }
+
+    #[inline]
+    pub fn add_blast_taken(&mut self, player: Player, from: ExplosiveKind) {
+        let penalty = match from {
+            ExplosiveKind::Bomb => 5,
+            ExplosiveKind::Dynamite => 1,
+        };
+        self.blasts_taken += 1;
+        self.score -= penalty;
+        let player = self.player_mut(player);
+        player.blasts_taken += 1;
+        player.score -= penalty;
     }
 }
 
@@ -31,8 +84,8 @@ pub fn on_disarm_bomb(
     mut event_reader: EventReader<BombDisarmedEvent>,
     mut query: Query<&mut Text, With<GameScoreUi>>,
 ) {
-    for _ in event_reader.iter() {
-        scores.add_bomb_disarmed();
+    for BombDisarmedEvent(_, player) in event_reader.iter() {
+        scores.add_bomb_disarmed(*player);
         update_score(&scores, &mut query);
     }
 }
@@ -58,11 +111,7 @@ pub fn on_guy_hurt(
     mut query: Query<&mut Text, With<GameScoreUi>>,
 ) {
     for ev in event_reader.iter() {
-        scores.score -= match ev.from {
-            ExplosiveKind::Bomb => 5,
-            ExplosiveKind::Dynamite => 1,
-        };
-        scores.blasts_taken += 1;
+        scores.add_blast_taken(ev.player, ev.from);
         update_score(&scores, &mut query);
     }
 }
@@ -73,8 +122,8 @@ pub fn on_pickup_dynamite(
     mut event_reader: EventReader<DynamiteDefusedEvent>,
     mut query: Query<&mut Text, With<GameScoreUi>>,
 ) {
-    for _ in event_reader.iter() {
-        scores.add_dynamite_defused();
+    for DynamiteDefusedEvent(_, player) in event_reader.iter() {
+        scores.add_dynamite_defused(*player);
         update_score(&scores, &mut query);
     }
 }
@@ -85,9 +134,71 @@ fn update_score(scores: &GameScores, query: &mut Query<&mut Text, With<GameScore
     }
 }
 
+/// system: keep the score of each player up to date in co-op
+pub fn update_player_scores(
+    scores: Res<GameScores>,
+    mut query: Query<(&PlayerScoreUi, &mut LocalizedText)>,
+) {
+    if !scores.is_changed() {
+        return;
+    }
+    for (PlayerScoreUi(player), mut text) in &mut query {
+        let score = scores
+            .players
+            .get(player.0 as usize)
+            .map_or(0, |scores| scores.score);
+        let new_text = LocalizedText::new("player_score")
+            .with_arg("player", player.0 + 1)
+            .with_arg("score", score);
+        if *text != new_text {
+            *text = new_text;
+        }
+    }
+}
+
 #[derive(Debug, Default, Component)]
 pub struct GameScoreUi;
 
+/// The score of one player, shown below the team score in co-op
+#[derive(Debug, Component)]
+pub struct PlayerScoreUi(pub Player);
+
+pub fn spawn_player_score_ui(
+    commands: &mut Commands,
+    font: Handle<Font>,
+    player: Player,
+) -> Entity {
+    commands
+        .spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font,
+                    font_size: 18.,
+                    color: player.tint(),
+                },
+            )
+            .with_text_alignment(TextAlignment::CENTER_RIGHT)
+            .with_style(Style {
+                align_self: AlignSelf::FlexEnd,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(56. + 28. * player.0 as f32),
+                    right: Val::Px(16.0),
+                    ..default()
+                },
+                ..default()
+            }),
+            PlayerScoreUi(player),
+            HudText { base_size: 18. },
+            InField,
+            LocalizedText::new("player_score")
+                .with_arg("player", player.0 + 1)
+                .with_arg("score", 0),
+        ))
+        .id()
+}
+
 #[derive(Default, Bundle)]
 pub struct GameScoreUiBundle {
     pub game_score_ui: GameScoreUi,
@@ -123,6 +234,7 @@ pub fn spawn_game_score_ui(commands: &mut Commands, font: Handle<Font>) -> Entit
             game_score_ui: GameScoreUi,
             text_bundle,
         })
+        .insert((HudText { base_size: 32. }, InField))
         .id()
 }
 
@@ -199,78 +311,90 @@ pub fn spawn_stats(commands: &mut ChildBuilder, font: Handle<Font>) -> Entity {
                 })
                 .with_children(|p| {
                     // 3 rows
-                    p.spawn(TextBundle {
-                        text: Text::from_section(
-                            "Bombs disarmed:",
-                            TextStyle {
-                                font: font.clone(),
-                                font_size: 24.,
-                                color: Color::WHITE,
-                            },
-                        ),
-                        style: Style {
-                            margin: UiRect {
-                                bottom: Val::Px(6.),
+                    p.spawn((
+                        TextBundle {
+                            text: Text::from_section(
+                                "",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 24.,
+                                    color: Color::WHITE,
+                                },
+                            ),
+                            style: Style {
+                                margin: UiRect {
+                                    bottom: Val::Px(6.),
+                                    ..default()
+                                },
                                 ..default()
                             },
                             ..default()
                         },
-                        ..default()
-                    });
-                    p.spawn(TextBundle {
-                        text: Text::from_section(
-                            "Dynamites defused:",
-                            TextStyle {
-                                font: font.clone(),
-                                font_size: 24.,
-                                color: Color::WHITE,
-                            },
-                        ),
-                        style: Style {
-                            margin: UiRect {
-                                bottom: Val::Px(6.),
+                        LocalizedText::new("stats_bombs"),
+                    ));
+                    p.spawn((
+                        TextBundle {
+                            text: Text::from_section(
+                                "",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 24.,
+                                    color: Color::WHITE,
+                                },
+                            ),
+                            style: Style {
+                                margin: UiRect {
+                                    bottom: Val::Px(6.),
+                                    ..default()
+                                },
                                 ..default()
                             },
                             ..default()
                         },
-                        ..default()
-                    });
-                    p.spawn(TextBundle {
-                        text: Text::from_section(
-                            "Blasts taken:",
-                            TextStyle {
-                                font: font.clone(),
-                                font_size: 24.,
-                                color: Color::WHITE,
-                            },
-                        ),
-                        style: Style {
-                            margin: UiRect {
-                                bottom: Val::Px(6.),
+                        LocalizedText::new("stats_dynamites"),
+                    ));
+                    p.spawn((
+                        TextBundle {
+                            text: Text::from_section(
+                                "",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 24.,
+                                    color: Color::WHITE,
+                                },
+                            ),
+                            style: Style {
+                                margin: UiRect {
+                                    bottom: Val::Px(6.),
+                                    ..default()
+                                },
                                 ..default()
                             },
                             ..default()
                         },
-                        ..default()
-                    });
-                    p.spawn(TextBundle {
-                        text: Text::from_section(
-                            "Total score:",
-                            TextStyle {
-                                font: font.clone(),
-                                font_size: 32.,
-                                color: Color::WHITE,
-                            },
-                        ),
-                        style: Style {
-                            margin: UiRect {
-                                top: Val::Px(12.),
+                        LocalizedText::new("stats_blasts"),
+                    ));
+                    p.spawn((
+                        TextBundle {
+                            text: Text::from_section(
+                                "",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 32.,
+                                    color: Color::WHITE,
+                                },
+                            ),
+                            style: Style {
+                                margin: UiRect {
+                                    top: Val::Px(12.),
+                                    ..default()
+                                },
                                 ..default()
                             },
                             ..default()
                         },
-                        ..default()
-                    });
+                        LocalizedText::new("stats_total"),
+                    ));
                 });
             // column 2: the actual scores
             parent
@@ -377,18 +501,26 @@ pub fn spawn_stats(commands: &mut ChildBuilder, font: Handle<Font>) -> Entity {
         .id()
 }
 
+/// the texts of the final stats UI
+type StatsTexts<'w, 's> = ParamSet<
+    'w,
+    's,
+    (
+        Query<'w, 's, &'static mut Text, With<BombsScoreUi>>,
+        Query<'w, 's, &'static mut Text, With<DynamitesScoreUi>>,
+        Query<'w, 's, &'static mut Text, With<BlastsScoreUi>>,
+        Query<'w, 's, &'static mut Text, With<TotalScoreUi>>,
+        Query<'w, 's, (Entity, &'static mut Text), With<RemarkUi>>,
+    ),
+>;
+
 /// system: update the final stats UI
 pub fn update_stats(
+    mut commands: Commands,
     time: Res<Time>,
     scores: Res<GameScores>,
     mut query_stats: Query<&mut Stats>,
-    mut query: ParamSet<(
-        Query<&mut Text, With<BombsScoreUi>>,
-        Query<&mut Text, With<DynamitesScoreUi>>,
-        Query<&mut Text, With<BlastsScoreUi>>,
-        Query<&mut Text, With<TotalScoreUi>>,
-        Query<&mut Text, With<RemarkUi>>,
-    )>,
+    mut query: StatsTexts,
 ) {
     // update stopwatch and fetch time elapsed
     let Ok(mut stats) = query_stats.get_single_mut() else {
@@ -455,7 +587,7 @@ pub fn update_stats(
         text.sections[0].value = value.to_string();
     }
 
-    for mut text in &mut query.p4() {
+    for (entity, mut text) in &mut query.p4() {
         let base_time_to_appear = 3.125;
         if elapsed < base_time_to_appear {
             break;
@@ -463,25 +595,30 @@ pub fn update_stats(
 
         // if not yet set
         if text.sections[0].value.is_empty() {
-            if scores.score >= 512 {
+            let remark = if scores.score >= 512 {
                 text.sections[0].style.color = Color::GREEN;
-                text.sections[0].value = "LUDICROUS SCORE!".to_string();
+                Some("remark_ludicrous")
             } else if scores.score >= 400 {
                 text.sections[0].style.color = Color::rgb(0.4, 1., 0.2);
-                text.sections[0].value = "Great score!".to_string();
+                Some("remark_great")
             } else if scores.dynamites_disarmed == 0 {
                 text.sections[0].style.color = Color::rgb(0.82, 0.72, 0.28);
-                text.sections[0].value = "Psst. Grab the dynamites!".to_string();
+                Some("remark_grab_dynamites")
             } else if scores.score <= 50 {
-                text.sections[0].value = "Better luck next time!".to_string();
+                Some("remark_better_luck")
             } else if scores.score <= -100 {
                 text.sections[0].style.color = Color::TOMATO;
-                text.sections[0].value = "...Seriously?".to_string();
+                Some("remark_seriously")
             } else if scores.blasts_taken == 0 {
                 text.sections[0].style.color = Color::rgb(0.8, 0.8, 0.8);
-                text.sections[0].value = "Can't touch this fella!".to_string();
+                Some("remark_untouchable")
             } else {
-                text.sections[0].value = "\0".to_string();
+                None
+            };
+            // filled in by the localized text, if there is a remark
+            text.sections[0].value = "\0".to_string();
+            if let Some(remark) = remark {
+                commands.entity(entity).insert(LocalizedText::new(remark));
             }
         }
     }
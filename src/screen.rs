@@ -0,0 +1,235 @@
+//! fitting the play field to the window.
+//!
+//! The game is laid out on a logical play field of a fixed size.
+//! The field is scaled up to fill the window
+//! (by integer factors where the window is big enough,
+//! so that pixel art stays crisp),
+//! centered, and letterboxed with black bars.
+//! The HUD is laid out against the play field as well,
+//! rather than against the whole window.
+
+use bevy::{prelude::*, ui::FocusPolicy, window::WindowMode};
+
+/// The size of the logical play field, in world units
+pub const FIELD_SIZE: Vec2 = Vec2::new(380., 660.);
+
+/// The translation of every camera, looking at the center of the field
+pub const CAMERA_TRANSLATION: Vec3 = Vec3::new(FIELD_SIZE.x / 2., FIELD_SIZE.y / 2., 999.);
+
+/// How the play field currently fits in the window
+#[derive(Debug, Copy, Clone, PartialEq, Resource)]
+pub struct ScreenFit {
+    /// window pixels per world unit
+    pub scale: f32,
+    /// window size, in logical pixels
+    pub window_size: Vec2,
+}
+
+impl Default for ScreenFit {
+    fn default() -> Self {
+        ScreenFit {
+            scale: 1.,
+            window_size: FIELD_SIZE,
+        }
+    }
+}
+
+impl ScreenFit {
+    fn for_window(window_size: Vec2) -> Self {
+        let fit = (window_size / FIELD_SIZE).min_element();
+        // stick to integer scales unless the window is too small
+        let scale = if fit >= 1. { fit.floor() } else { fit.max(0.1) };
+        ScreenFit { scale, window_size }
+    }
+
+    /// The bottom left corner of the play field, in window pixels
+    pub fn origin(&self) -> Vec2 {
+        (self.window_size - FIELD_SIZE * self.scale) / 2.
+    }
+
+    /// Map a position in the play field to a position in the UI.
+    /// The UI is scaled by the same factor,
+    /// so this is just an offset.
+    pub fn world_to_ui(&self, pos: Vec2) -> Vec2 {
+        pos + self.origin() / self.scale
+    }
+}
+
+/// Map a window position (such as the cursor or a touch)
+/// to a position in the world, through the given camera.
+pub fn window_to_world(camera: &Camera, transform: &GlobalTransform, pos: Vec2) -> Option<Vec2> {
+    camera
+        .viewport_to_world(transform, pos)
+        .map(|ray| ray.origin.truncate())
+}
+
+/// A camera looking at the play field,
+/// to be fitted to the window
+pub fn camera_bundle() -> Camera2dBundle {
+    Camera2dBundle {
+        transform: Transform::from_translation(CAMERA_TRANSLATION),
+        ..default()
+    }
+}
+
+/// Marker for UI nodes laid out against the play field,
+/// such as the HUD, rather than against the whole window.
+/// They are moved into the [`FieldUi`] node as they are spawned.
+#[derive(Debug, Default, Component)]
+pub struct InField;
+
+/// The UI node covering the play field,
+/// which holds the nodes laid out against it
+#[derive(Debug, Component)]
+pub struct FieldUi;
+
+impl ScreenFit {
+    /// The style of the UI node covering the play field
+    fn field_ui_style(&self) -> Style {
+        let origin = self.origin() / self.scale;
+        Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(origin.x),
+                bottom: Val::Px(origin.y),
+                ..default()
+            },
+            size: Size::new(Val::Px(FIELD_SIZE.x), Val::Px(FIELD_SIZE.y)),
+            ..default()
+        }
+    }
+}
+
+/// system: keep the node covering the play field in place,
+/// and move new nodes laid out against the play field into it
+pub fn fit_field_ui(
+    mut commands: Commands,
+    fit: Res<ScreenFit>,
+    mut query_field_ui: Query<(Entity, &mut Style), With<FieldUi>>,
+    query_new: Query<Entity, (Added<InField>, Without<Parent>)>,
+) {
+    let field_ui = match query_field_ui.get_single_mut() {
+        Ok((entity, mut style)) => {
+            if fit.is_changed() {
+                *style = fit.field_ui_style();
+            }
+            entity
+        }
+        Err(_) => {
+            // it goes away with everything else when the screen changes
+            if query_new.is_empty() {
+                return;
+            }
+            commands
+                .spawn((
+                    NodeBundle {
+                        style: fit.field_ui_style(),
+                        focus_policy: FocusPolicy::Pass,
+                        ..default()
+                    },
+                    FieldUi,
+                ))
+                .id()
+        }
+    };
+
+    for entity in &query_new {
+        commands.entity(field_ui).add_child(entity);
+    }
+}
+
+/// Marker for the black bars around the play field
+#[derive(Debug, Component)]
+pub struct Letterbox;
+
+/// system: keep the play field fitted to the window
+pub fn fit_to_window(
+    windows: Res<Windows>,
+    mut fit: ResMut<ScreenFit>,
+    mut ui_scale: ResMut<UiScale>,
+    mut query_projection: Query<&mut OrthographicProjection>,
+    query_new_cameras: Query<(), Added<Camera2d>>,
+) {
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+
+    let new_fit = ScreenFit::for_window(Vec2::new(window.width(), window.height()));
+    if new_fit != *fit {
+        *fit = new_fit;
+        ui_scale.scale = new_fit.scale as f64;
+    } else if query_new_cameras.is_empty() {
+        return;
+    }
+
+    for mut projection in &mut query_projection {
+        projection.scale = 1. / fit.scale;
+    }
+}
+
+/// system: add black bars around the play field to new cameras
+pub fn attach_letterbox(mut commands: Commands, query: Query<Entity, Added<Camera2d>>) {
+    // large enough to cover any window
+    const EXTENT: f32 = 8000.;
+
+    let half = FIELD_SIZE / 2.;
+    let bars = [
+        // left and right
+        (
+            Vec2::new(-half.x - EXTENT / 2., 0.),
+            Vec2::new(EXTENT, EXTENT * 2.),
+        ),
+        (
+            Vec2::new(half.x + EXTENT / 2., 0.),
+            Vec2::new(EXTENT, EXTENT * 2.),
+        ),
+        // top and bottom
+        (
+            Vec2::new(0., half.y + EXTENT / 2.),
+            Vec2::new(FIELD_SIZE.x, EXTENT),
+        ),
+        (
+            Vec2::new(0., -half.y - EXTENT / 2.),
+            Vec2::new(FIELD_SIZE.x, EXTENT),
+        ),
+    ];
+
+    for camera in &query {
+        commands.entity(camera).with_children(|parent| {
+            for (pos, size) in bars {
+                parent.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::BLACK,
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        // just in front of the camera
+                        transform: Transform::from_translation(pos.extend(-1.)),
+                        ..default()
+                    },
+                    Letterbox,
+                ));
+            }
+        });
+    }
+}
+
+/// system: toggle fullscreen with F11 (or Alt+Enter)
+pub fn toggle_fullscreen(keyboard_input: Res<Input<KeyCode>>, mut windows: ResMut<Windows>) {
+    let alt = keyboard_input.any_pressed([KeyCode::LAlt, KeyCode::RAlt]);
+    let toggle = keyboard_input.just_pressed(KeyCode::F11)
+        || (alt && keyboard_input.just_pressed(KeyCode::Return));
+    if !toggle {
+        return;
+    }
+
+    let Some(window) = windows.get_primary_mut() else {
+        return;
+    };
+    let mode = match window.mode() {
+        WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+        _ => WindowMode::Windowed,
+    };
+    window.set_mode(mode);
+}
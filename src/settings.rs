@@ -0,0 +1,309 @@
+//! game settings, and the screen for changing them
+
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility::Palette,
+    animation::spawn_fade_in_black_screen,
+    audio::GameSoundSources,
+    i18n::{Language, LocalizedText},
+    menu::{HOVER_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON},
+    AppState, DefaultFont, DelayedStateChange,
+};
+
+/// The key under which settings are stored
+const SETTINGS_KEY: &str = "settings";
+
+#[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// how strong screen shake, hit-stop and flashes are (0 to 1)
+    pub feel_intensity: f32,
+    /// turn off effects with a lot of motion,
+    /// and show steady indicators instead of blinking or wobbling
+    pub reduced_motion: bool,
+    /// how the guy is moved around with the mouse or touch
+    pub control_scheme: ControlScheme,
+    pub language: Language,
+    /// the colors used for things with meaning
+    pub palette: Palette,
+    /// bigger text in the HUD
+    pub large_text: bool,
+}
+
+/// The ways of moving the guy around
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ControlScheme {
+    /// click or tap where the guy should go
+    #[default]
+    Tap,
+    /// the guy follows the pointer while it is held down
+    HoldToSteer,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            feel_intensity: 1.,
+            reduced_motion: false,
+            control_scheme: ControlScheme::Tap,
+            language: Language::English,
+            palette: Palette::Default,
+            large_text: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Load settings from storage, or use the defaults
+    pub fn load() -> Self {
+        crate::storage::load(SETTINGS_KEY).unwrap_or_default()
+    }
+
+    /// The intensity of game feel effects,
+    /// taking reduced motion into account
+    pub fn effective_feel_intensity(&self) -> f32 {
+        if self.reduced_motion {
+            0.
+        } else {
+            self.feel_intensity
+        }
+    }
+}
+
+/// system: save settings whenever they change
+pub fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        crate::storage::save(SETTINGS_KEY, &*settings);
+    }
+}
+
+/// The buttons in the settings screen
+#[derive(Debug, Copy, Clone, PartialEq, Component)]
+pub enum SettingsButton {
+    FeelIntensity,
+    ReducedMotion,
+    ControlScheme,
+    Language,
+    Palette,
+    LargeText,
+    Back,
+}
+
+impl SettingsButton {
+    fn label(self, settings: &Settings) -> LocalizedText {
+        match self {
+            SettingsButton::FeelIntensity => LocalizedText::new("shake")
+                .with_arg("percent", (settings.feel_intensity * 100.).round()),
+            SettingsButton::ReducedMotion => LocalizedText::new(if settings.reduced_motion {
+                "reduced_motion_on"
+            } else {
+                "reduced_motion_off"
+            }),
+            SettingsButton::ControlScheme => LocalizedText::new(match settings.control_scheme {
+                ControlScheme::Tap => "controls_tap",
+                ControlScheme::HoldToSteer => "controls_steer",
+            }),
+            SettingsButton::Language => {
+                LocalizedText::new("language").with_arg("name", settings.language.name())
+            }
+            SettingsButton::Palette => LocalizedText::new(settings.palette.label_key()),
+            SettingsButton::LargeText => LocalizedText::new(if settings.large_text {
+                "large_text_on"
+            } else {
+                "large_text_off"
+            }),
+            SettingsButton::Back => LocalizedText::new("back"),
+        }
+    }
+}
+
+pub fn setup(mut commands: Commands, font: Res<DefaultFont>, settings: Res<Settings>) {
+    // UI camera
+    commands.spawn(crate::screen::camera_bundle());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 40.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect {
+                        bottom: Val::Px(24.),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                LocalizedText::new("settings"),
+            ));
+
+            for button in [
+                SettingsButton::FeelIntensity,
+                SettingsButton::ReducedMotion,
+                SettingsButton::ControlScheme,
+                SettingsButton::Language,
+                SettingsButton::Palette,
+                SettingsButton::LargeText,
+                SettingsButton::Back,
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(340.0), Val::Px(46.0)),
+                                margin: UiRect {
+                                    top: Val::Px(if button == SettingsButton::Back {
+                                        28.
+                                    } else {
+                                        4.
+                                    }),
+                                    bottom: Val::Px(4.),
+                                    ..default()
+                                },
+                                // horizontally center child text
+                                justify_content: JustifyContent::Center,
+                                // vertically center child text
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: NORMAL_BUTTON.into(),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font: font.0.clone(),
+                                    font_size: 28.0,
+                                    color: Color::rgba(1., 1., 0.8, 1.0),
+                                },
+                            ),
+                            button.label(&settings),
+                        ));
+                    });
+            }
+        });
+}
+
+/// interactions with the buttons of the settings screen
+type SettingsButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut BackgroundColor,
+        &'static SettingsButton,
+    ),
+    (Changed<Interaction>, With<Button>),
+>;
+
+pub fn button_system(
+    mut commands: Commands,
+    mut interaction_query: SettingsButtonQuery,
+    mut settings: ResMut<Settings>,
+    audio: Res<Audio>,
+    sound_sources: Res<GameSoundSources>,
+    transition_entity: Query<Entity, With<DelayedStateChange>>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+
+                audio.play(sound_sources.click.cast_weak());
+
+                match button {
+                    SettingsButton::FeelIntensity => {
+                        // cycle through 0%, 25%, ..., 100%
+                        settings.feel_intensity = if settings.feel_intensity >= 1. {
+                            0.
+                        } else {
+                            (settings.feel_intensity + 0.25).min(1.)
+                        };
+                    }
+                    SettingsButton::ReducedMotion => {
+                        settings.reduced_motion = !settings.reduced_motion;
+                    }
+                    SettingsButton::ControlScheme => {
+                        settings.control_scheme = match settings.control_scheme {
+                            ControlScheme::Tap => ControlScheme::HoldToSteer,
+                            ControlScheme::HoldToSteer => ControlScheme::Tap,
+                        };
+                    }
+                    SettingsButton::Language => {
+                        settings.language = settings.language.next();
+                    }
+                    SettingsButton::Palette => {
+                        settings.palette = settings.palette.next();
+                    }
+                    SettingsButton::LargeText => {
+                        settings.large_text = !settings.large_text;
+                    }
+                    SettingsButton::Back => {
+                        // ensure that we don't spawn more than one
+                        if transition_entity.get_single().is_err() {
+                            // schedule app state transition
+                            let e = spawn_fade_in_black_screen(
+                                &mut commands,
+                                Duration::from_millis(400),
+                            );
+                            commands.entity(e).insert(DelayedStateChange::new(
+                                AppState::MainMenu,
+                                Duration::from_millis(750),
+                            ));
+                        }
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVER_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// system: keep button labels in sync with the settings
+pub fn update_labels(
+    settings: Res<Settings>,
+    query_buttons: Query<(&SettingsButton, &Children)>,
+    mut query_text: Query<&mut LocalizedText>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (button, children) in &query_buttons {
+        for child in children {
+            if let Ok(mut text) = query_text.get_mut(*child) {
+                let label = button.label(&settings);
+                if *text != label {
+                    *text = label;
+                }
+            }
+        }
+    }
+}
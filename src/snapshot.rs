@@ -0,0 +1,177 @@
+//! snapshots of the play field, to come back to a moment of a wave.
+//!
+//! Press F5 during a wave to save everything on the play field
+//! (the guys, the items lying around or in the air,
+//! the spawners and the events they have scheduled)
+//! as a scene, and F9 to put the play field back as it was.
+//! Only the play field is saved:
+//! the run itself (the wave number, the scores, ...) carries on as it is.
+//!
+//! Visuals made of meshes (progress bars, the faces of obstacles, ...)
+//! are left out, and rebuilt from the components they are made for.
+//! A run in which a snapshot was loaded counts as assisted.
+//!
+//! Only compiled in with the `debug` cargo feature.
+
+use std::any::TypeId;
+
+use bevy::{
+    ecs::entity::EntityMap,
+    hierarchy::despawn_with_children_recursive,
+    prelude::*,
+    reflect::{TypeRegistration, TypeRegistryInternal},
+    scene::{serde::SceneDeserializer, DynamicSceneBuilder},
+    sprite::Mesh2dHandle,
+};
+use serde::de::DeserializeSeed;
+
+use crate::{achievements::RunRecord, ghost::Ghost, particles::Particle, AppState};
+
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(save_snapshot)
+                .with_system(load_snapshot),
+        );
+    }
+}
+
+/// The key under which the snapshot is saved
+const SNAPSHOT_KEY: &str = "snapshot";
+
+/// The engine's components kept in snapshots, to show the game's own
+fn engine_components() -> [TypeId; 8] {
+    [
+        TypeId::of::<Transform>(),
+        TypeId::of::<GlobalTransform>(),
+        TypeId::of::<Visibility>(),
+        TypeId::of::<ComputedVisibility>(),
+        TypeId::of::<Sprite>(),
+        TypeId::of::<TextureAtlasSprite>(),
+        TypeId::of::<Handle<Image>>(),
+        TypeId::of::<Handle<TextureAtlas>>(),
+    ]
+}
+
+/// Whether this is one of the game's own components
+fn is_own_component(registration: &TypeRegistration) -> bool {
+    registration.data::<ReflectComponent>().is_some()
+        && registration
+            .type_name()
+            .starts_with(concat!(env!("CARGO_CRATE_NAME"), "::"))
+}
+
+/// The registry of the components which go into a snapshot.
+/// Hierarchies are left out, as children are only ever visuals.
+fn snapshot_registry(registry: &TypeRegistryInternal) -> AppTypeRegistry {
+    let engine_components = engine_components();
+    let snapshot_registry = AppTypeRegistry::default();
+    {
+        let mut snapshot_registry = snapshot_registry.write();
+        for registration in registry.iter() {
+            if is_own_component(registration) || engine_components.contains(&registration.type_id())
+            {
+                snapshot_registry.add_registration(registration.clone());
+            }
+        }
+    }
+    snapshot_registry
+}
+
+/// The entities which make up the play field:
+/// those with any of the game's own components,
+/// except for the UI, visuals, particles and the ghost
+fn field_entities(world: &mut World, registry: &TypeRegistryInternal) -> Vec<Entity> {
+    let mut query = world.query_filtered::<Entity, (
+        Without<Parent>,
+        Without<Node>,
+        Without<Mesh2dHandle>,
+        Without<Particle>,
+        Without<Ghost>,
+    )>();
+    let entities: Vec<Entity> = query.iter(world).collect();
+    entities
+        .into_iter()
+        .filter(|&entity| {
+            world
+                .entity(entity)
+                .archetype()
+                .components()
+                .any(|component_id| {
+                    world
+                        .components()
+                        .get_info(component_id)
+                        .and_then(|info| info.type_id())
+                        .and_then(|type_id| registry.get(type_id))
+                        .is_some_and(is_own_component)
+                })
+        })
+        .collect()
+}
+
+/// system: save a snapshot of the play field on F5
+pub fn save_snapshot(world: &mut World) {
+    if !world.resource::<Input<KeyCode>>().just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let entities = field_entities(world, &registry.read());
+    let snapshot_registry = snapshot_registry(&registry.read());
+
+    let mut builder = DynamicSceneBuilder::from_world_with_type_registry(world, snapshot_registry);
+    builder.extract_entities(entities.iter().copied());
+    let scene = builder.build();
+
+    match scene.serialize_ron(&registry) {
+        Ok(data) => {
+            crate::storage::save_text(SNAPSHOT_KEY, &data);
+            info!("Saved a snapshot of {} entities", entities.len());
+        }
+        Err(e) => error!("Could not serialize the snapshot: {}", e),
+    }
+}
+
+/// system: put the play field back as in the snapshot on F9
+pub fn load_snapshot(world: &mut World) {
+    if !world.resource::<Input<KeyCode>>().just_pressed(KeyCode::F9) {
+        return;
+    }
+    let Some(data) = crate::storage::load_text(SNAPSHOT_KEY) else {
+        info!("No snapshot to load");
+        return;
+    };
+
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let scene = {
+        let registry = registry.read();
+        let deserializer = SceneDeserializer {
+            type_registry: &registry,
+        };
+        let result = ron::Deserializer::from_str(&data)
+            .map_err(ron::Error::from)
+            .and_then(|mut ron| deserializer.deserialize(&mut ron));
+        match result {
+            Ok(scene) => scene,
+            Err(e) => {
+                error!("Could not read the snapshot: {}", e);
+                return;
+            }
+        }
+    };
+
+    // clear the play field first
+    for entity in field_entities(world, &registry.read()) {
+        despawn_with_children_recursive(world, entity);
+    }
+
+    match scene.write_to_world(world, &mut EntityMap::default()) {
+        Ok(()) => info!("Loaded a snapshot of {} entities", scene.entities.len()),
+        Err(e) => error!("Could not load the snapshot: {}", e),
+    }
+    // going back in time is cheating
+    world.resource_mut::<RunRecord>().assisted = true;
+}
@@ -1,28 +1,109 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
 use bevy::prelude::*;
 use bevy::utils::Duration;
 
-use rand::{self, Rng as _, SeedableRng};
-use rand_pcg;
+use rand::{self, Rng as _};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     audio::GameSoundSources,
     bomb::BombTextureAtlas,
     coffee::CoffeeTexture,
+    daily::{Modifier, Modifiers},
     dynamite::DynamiteTextureAtlas,
     events::{BombThrownEvent, CoffeeThrownEvent, DynamiteThrownEvent},
     helper::ScheduledEvent,
+    resume::ResumeRun,
 };
 
-#[derive(Debug, Resource, Deref, DerefMut)]
-pub struct Rng(rand_pcg::Pcg32);
+/// The random number generators of a run.
+/// Each use draws from a stream of its own,
+/// so that a run played again on the same seed throws the same things
+/// at the same places, whatever the frame rate or the order of systems.
+#[derive(Debug, Clone, Resource, Serialize, Deserialize)]
+pub struct Rng {
+    seed: u64,
+    streams: BTreeMap<Cow<'static, str>, Pcg32>,
+}
 
 impl Default for Rng {
     fn default() -> Self {
-        Self(rand_pcg::Pcg32::from_seed(rand::random()))
+        Self::from_seed(rand::random())
     }
 }
 
-#[derive(Debug, Component)]
+impl Rng {
+    pub fn from_seed(seed: u64) -> Self {
+        Rng {
+            seed,
+            streams: BTreeMap::new(),
+        }
+    }
+
+    /// The generator of the stream with the given name
+    pub fn stream(&mut self, name: &'static str) -> &mut Pcg32 {
+        let seed = self.seed;
+        self.streams
+            .entry(Cow::Borrowed(name))
+            .or_insert_with(|| Pcg32::new(seed, stream_id(name)))
+    }
+}
+
+/// A number for a stream name, the same on every build (FNV-1a)
+fn stream_id(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The seed of the run being played
+#[derive(Debug, Copy, Clone, Resource, Serialize, Deserialize)]
+pub struct RunSeed {
+    pub seed: u64,
+    /// whether others play this same seed too,
+    /// in which case nothing else should make the run differ
+    pub shared: bool,
+}
+
+/// A seed to play the next run on, shared with other players
+#[derive(Debug, Copy, Clone, Resource)]
+pub struct NextSeed(pub u64);
+
+/// system: seed the random number generators for a new run,
+/// on the seed asked for if any, or a fresh one otherwise
+pub fn seed_run(
+    mut commands: Commands,
+    next_seed: Option<Res<NextSeed>>,
+    resume: Option<Res<ResumeRun>>,
+) {
+    // a resumed run carries on with the generators as they were
+    if let Some(resume) = resume {
+        commands.insert_resource(resume.0.rng.clone());
+        commands.insert_resource(resume.0.run_seed);
+        return;
+    }
+    let run_seed = match next_seed {
+        Some(next_seed) => {
+            commands.remove_resource::<NextSeed>();
+            RunSeed {
+                seed: next_seed.0,
+                shared: true,
+            }
+        }
+        None => RunSeed {
+            seed: rand::random(),
+            shared: false,
+        },
+    };
+    commands.insert_resource(Rng::from_seed(run_seed.seed));
+    commands.insert_resource(run_seed);
+}
+
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct Spawner {
     /// how many items left to spawn
     pub remaining: u32,
@@ -51,47 +132,75 @@ impl Spawner {
 
 /// If present, the spawner has already scheduled an event
 /// and should wait until it is triggered
-#[derive(Component)]
+#[derive(Default, Component, Reflect)]
 #[component(storage = "SparseSet")]
+#[reflect(Component)]
 pub struct SpawnerCooldown;
 
-#[derive(Component)]
-pub struct RandomEventProducer<E> {
-    pub distribution: rand_distr::Exp<f32>,
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct RandomEventProducer<E: Reflect + Default> {
+    /// the rate of the exponential distribution of the time between events
+    pub lambda: f32,
     pub event: E,
 }
 
-impl<E: Send> RandomEventProducer<E> {
+impl<E: Reflect + Default> RandomEventProducer<E> {
     pub fn new(lambda: f32, event: E) -> Self {
-        RandomEventProducer {
-            distribution: rand_distr::Exp::new(lambda).unwrap(),
-            event,
-        }
+        RandomEventProducer { lambda, event }
     }
 }
 
-impl<E: Send + Clone> RandomEventProducer<E> {
-    pub fn sample(&self, rng: &mut Rng) -> ScheduledEvent<E> {
-        let after: f32 = rng.0.sample(self.distribution);
+impl<E: Reflect + Default + Clone> RandomEventProducer<E> {
+    pub fn sample(&self, rng: &mut Pcg32) -> ScheduledEvent<E> {
+        let after: f32 = match rand_distr::Exp::new(self.lambda) {
+            Ok(distribution) => rng.sample(distribution),
+            Err(e) => {
+                // wait as long as a throw can be waited for
+                warn!("Cannot sample spawner rate {}: {}", self.lambda, e);
+                f32::INFINITY
+            }
+        };
         let after = after.clamp(0.5, 25.);
         ScheduledEvent::new(self.event.clone(), Duration::from_secs_f32(after))
     }
 }
 
 #[derive(Bundle)]
-pub struct SpawnerBundle<E: 'static + Send + Sync> {
+pub struct SpawnerBundle<E: Reflect + Default> {
     spawner: Spawner,
     event_producer: RandomEventProducer<E>,
 }
 
+/// An event which spawners produce at random times
+pub trait SpawnedEvent {
+    /// The name of the random stream which the times are drawn from.
+    /// It is written out rather than derived from the type,
+    /// so that a seed plays the same on every build.
+    const STREAM: &'static str;
+}
+
+impl SpawnedEvent for DynamiteThrownEvent {
+    const STREAM: &'static str = "spawn_dynamite";
+}
+
+impl SpawnedEvent for BombThrownEvent {
+    const STREAM: &'static str = "spawn_bomb";
+}
+
+impl SpawnedEvent for CoffeeThrownEvent {
+    const STREAM: &'static str = "spawn_coffee";
+}
+
 /// Used alongisde a scheduled event
 /// to mean that the event is to throw something
-#[derive(Component)]
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct PendingThrow;
 
 /// system: handle spawner logic of
 /// producing randomly timed throwing events
-pub fn handle_spawners<E: 'static + Send + Sync + Clone>(
+pub fn handle_spawners<E: Reflect + Default + Clone + SpawnedEvent>(
     mut commands: Commands,
     mut rng: ResMut<Rng>,
     mut query: Query<(Entity, &mut Spawner, &RandomEventProducer<E>), Without<SpawnerCooldown>>,
@@ -99,7 +208,7 @@ pub fn handle_spawners<E: 'static + Send + Sync + Clone>(
     for (entity, mut spawner, event_producer) in &mut query {
         if spawner.remaining > 0 {
             // sample
-            let scheduled_event = event_producer.sample(&mut rng);
+            let scheduled_event = event_producer.sample(rng.stream(E::STREAM));
 
             commands
                 .entity(entity)
@@ -113,6 +222,47 @@ pub fn handle_spawners<E: 'static + Send + Sync + Clone>(
     }
 }
 
+/// The things which can be thrown onto the play field
+#[derive(
+    Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Reflect, FromReflect,
+)]
+pub enum Throwable {
+    #[default]
+    Dynamite,
+    Bomb,
+    Coffee,
+}
+
+/// A throw aimed at a given spot rather than at random,
+/// once its timer is up.
+/// Used alongside [`PendingThrow`], like scheduled events.
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct AimedThrow {
+    pub item: Throwable,
+    pub position: Vec2,
+    pub timer: Timer,
+}
+
+impl AimedThrow {
+    pub fn new(item: Throwable, position: Vec2, after: Duration) -> Self {
+        AimedThrow {
+            item,
+            position,
+            timer: Timer::new(after, TimerMode::Once),
+        }
+    }
+}
+
+/// The seconds bombs tick for before going off
+fn bomb_ticks(modifiers: &Modifiers) -> u32 {
+    if modifiers.contains(Modifier::ShortFuses) {
+        9
+    } else {
+        12
+    }
+}
+
 /// system: on dynamite thrown, spawn it
 pub fn throw_dynamite(
     mut commands: Commands,
@@ -121,15 +271,16 @@ pub fn throw_dynamite(
     sound_sources: Res<GameSoundSources>,
     mut event_reader: EventReader<DynamiteThrownEvent>,
 ) {
+    let rng = rng.stream("throw_dynamite");
     for _ in event_reader.iter() {
-        let pos = random_xy_position(&mut rng);
+        let pos = random_xy_position(rng);
 
         crate::dynamite::spawn_dynamite(
             &mut commands,
             texture_atlas.0.clone(),
             sound_sources.thwack3.clone(),
             pos.extend(1200.),
-            random_velocity_variations(&mut rng),
+            random_velocity_variations(rng),
         );
     }
     event_reader.clear();
@@ -141,18 +292,21 @@ pub fn throw_bomb(
     mut rng: ResMut<Rng>,
     texture_atlas: Res<BombTextureAtlas>,
     sound_sources: Res<GameSoundSources>,
+    modifiers: Res<Modifiers>,
     mut event_reader: EventReader<BombThrownEvent>,
 ) {
+    let rng = rng.stream("throw_bomb");
+    let ticks = bomb_ticks(&modifiers);
     for _ in event_reader.iter() {
-        let pos = random_xy_position(&mut rng);
+        let pos = random_xy_position(rng);
 
         crate::bomb::spawn_bomb(
             &mut commands,
             texture_atlas.clone(),
             sound_sources.thwack10.clone(),
             pos.extend(1200.),
-            random_velocity_variations(&mut rng),
-            12,
+            random_velocity_variations(rng),
+            ticks,
         );
     }
     event_reader.clear();
@@ -163,33 +317,93 @@ pub fn throw_coffee(
     mut commands: Commands,
     mut rng: ResMut<Rng>,
     texture: Res<CoffeeTexture>,
+    modifiers: Res<Modifiers>,
     mut event_reader: EventReader<CoffeeThrownEvent>,
 ) {
+    if modifiers.contains(Modifier::NoCoffee) {
+        event_reader.clear();
+        return;
+    }
+    let rng = rng.stream("throw_coffee");
     for _ in event_reader.iter() {
-        let pos = random_xy_position(&mut rng);
+        let pos = random_xy_position(rng);
 
         crate::coffee::spawn_coffee(
             &mut commands,
             texture.clone(),
             pos.extend(1200.),
-            random_velocity_variations(&mut rng),
+            random_velocity_variations(rng),
         );
     }
     event_reader.clear();
 }
 
-fn random_xy_position(rng: &mut Rng) -> Vec2 {
+/// system: throw aimed throws once they are due, right onto their spot
+pub fn throw_aimed(
+    mut commands: Commands,
+    time: Res<Time>,
+    (dynamite_texture_atlas, bomb_texture_atlas, coffee_texture): (
+        Res<DynamiteTextureAtlas>,
+        Res<BombTextureAtlas>,
+        Res<CoffeeTexture>,
+    ),
+    sound_sources: Res<GameSoundSources>,
+    modifiers: Res<Modifiers>,
+    mut query: Query<(Entity, &mut AimedThrow)>,
+) {
+    for (entity, mut throw) in &mut query {
+        if !throw.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        commands.entity(entity).despawn();
+
+        let pos = throw.position.extend(1200.);
+        match throw.item {
+            Throwable::Dynamite => {
+                crate::dynamite::spawn_dynamite(
+                    &mut commands,
+                    dynamite_texture_atlas.0.clone(),
+                    sound_sources.thwack3.clone(),
+                    pos,
+                    Vec3::ZERO,
+                );
+            }
+            Throwable::Bomb => {
+                crate::bomb::spawn_bomb(
+                    &mut commands,
+                    bomb_texture_atlas.clone(),
+                    sound_sources.thwack10.clone(),
+                    pos,
+                    Vec3::ZERO,
+                    bomb_ticks(&modifiers),
+                );
+            }
+            Throwable::Coffee => {
+                if !modifiers.contains(Modifier::NoCoffee) {
+                    crate::coffee::spawn_coffee(
+                        &mut commands,
+                        coffee_texture.clone(),
+                        pos,
+                        Vec3::ZERO,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn random_xy_position(rng: &mut Pcg32) -> Vec2 {
     let x_pos = rand_distr::Uniform::new(12., 348.);
     let y_pos = rand_distr::Uniform::new(22., 360.);
-    let x = rng.0.sample(x_pos);
-    let y = rng.0.sample(y_pos);
+    let x = rng.sample(x_pos);
+    let y = rng.sample(y_pos);
     Vec2::new(x, y)
 }
 
-fn random_velocity_variations(rng: &mut Rng) -> Vec3 {
+fn random_velocity_variations(rng: &mut Pcg32) -> Vec3 {
     let vel_variations_dist = rand_distr::Uniform::new(-12., 12.);
-    let vel_x = rng.0.sample(vel_variations_dist);
-    let vel_y = rng.0.sample(vel_variations_dist);
+    let vel_x = rng.sample(vel_variations_dist);
+    let vel_y = rng.sample(vel_variations_dist);
     let vel_variations_z_dist = rand_distr::Uniform::new(-20., 0.);
     let vel_z = rng.sample(vel_variations_z_dist);
 
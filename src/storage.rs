@@ -0,0 +1,177 @@
+//! persistent storage for small bits of game data (settings, progress, ...),
+//! kept as RON files on desktop and in `localStorage` on the web
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Load a value stored under the given key, if any.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let data = read(key)?;
+    match ron::from_str(&data) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Could not read stored `{}`: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Store a value under the given key, replacing the previous one.
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    match ron::to_string(value) {
+        Ok(data) => write(key, &data),
+        Err(e) => error!("Could not serialize `{}`: {}", key, e),
+    }
+}
+
+/// Load the text stored under the given key as is, if any.
+pub fn load_text(key: &str) -> Option<String> {
+    read(key)
+}
+
+/// Store text under the given key as is, replacing the previous one.
+// only snapshots, which are a debugging tool, store text as is
+#[cfg_attr(not(feature = "debug"), allow(dead_code))]
+pub fn save_text(key: &str, data: &str) {
+    write(key, data);
+}
+
+/// The file which the value under the given key is stored in,
+/// for loading it as an asset
+/// (there is none on the web).
+#[cfg_attr(not(feature = "editor"), allow(dead_code))]
+pub fn path(key: &str) -> Option<std::path::PathBuf> {
+    file_path(key)
+}
+
+/// The keys stored in the given folder, such as `waves`, in order
+/// (they are not listed on the web).
+#[cfg_attr(not(feature = "editor"), allow(dead_code))]
+pub fn keys_in(folder: &str) -> Vec<String> {
+    list(folder)
+}
+
+/// Forget the value stored under the given key, if any.
+pub fn remove(key: &str) {
+    delete(key);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn data_dir() -> Option<std::path::PathBuf> {
+    use std::env::var_os;
+    use std::path::PathBuf;
+
+    let base = if cfg!(target_os = "windows") {
+        var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    base.map(|dir| dir.join("timely-defuse"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn file_path(key: &str) -> Option<std::path::PathBuf> {
+    Some(data_dir()?.join(format!("{}.ron", key)))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn list(folder: &str) -> Vec<String> {
+    let Some(entries) = data_dir().and_then(|dir| std::fs::read_dir(dir.join(folder)).ok()) else {
+        return vec![];
+    };
+    let mut keys: Vec<String> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            Some(format!("{}/{}", folder, name.strip_suffix(".ron")?))
+        })
+        .collect();
+    keys.sort();
+    keys
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read(key: &str) -> Option<String> {
+    let path = data_dir()?.join(format!("{}.ron", key));
+    std::fs::read_to_string(path).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(key: &str, data: &str) {
+    let Some(dir) = data_dir() else {
+        warn!("No place to store `{}`", key);
+        return;
+    };
+    // keys such as `waves/<name>` are kept in folders of their own
+    let path = dir.join(format!("{}.ron", key));
+    let result = std::fs::create_dir_all(path.parent().unwrap_or(&dir))
+        .and_then(|_| std::fs::write(&path, data));
+    if let Err(e) = result {
+        error!("Could not store `{}`: {}", key, e);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn delete(key: &str) {
+    let Some(dir) = data_dir() else {
+        return;
+    };
+    let result = std::fs::remove_file(dir.join(format!("{}.ron", key)));
+    if let Err(e) = result {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            error!("Could not remove `{}`: {}", key, e);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn file_path(_key: &str) -> Option<std::path::PathBuf> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn list(_folder: &str) -> Vec<String> {
+    vec![]
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read(key: &str) -> Option<String> {
+    local_storage()?
+        .get_item(&format!("timely-defuse.{}", key))
+        .ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(key: &str, data: &str) {
+    let Some(storage) = local_storage() else {
+        warn!("No place to store `{}`", key);
+        return;
+    };
+    if storage
+        .set_item(&format!("timely-defuse.{}", key), data)
+        .is_err()
+    {
+        error!("Could not store `{}`", key);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn delete(key: &str) {
+    let Some(storage) = local_storage() else {
+        return;
+    };
+    if storage
+        .remove_item(&format!("timely-defuse.{}", key))
+        .is_err()
+    {
+        error!("Could not remove `{}`", key);
+    }
+}
@@ -0,0 +1,182 @@
+//! locking onto specific bombs and items by clicking or tapping on them
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    bomb::BombState,
+    guy::{GuyDestination, GuyTarget},
+    helper::BaseTranslation,
+    movement::SpatialPosition,
+};
+
+/// Marker for things which the guy can be sent to
+#[derive(Debug, Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct Targetable;
+
+/// how much bigger than the sprite the clickable area is
+/// (in sprite pixels), to be forgiving with fingers
+const PICK_MARGIN: f32 = 4.;
+
+const OUTLINE_COLOR: Color = Color::rgb(1., 0.9, 0.2);
+
+/// the parts of a targetable entity needed to know its sprite bounds
+type TargetSprite<'a> = (
+    Entity,
+    &'a GlobalTransform,
+    Option<&'a TextureAtlasSprite>,
+    Option<&'a Handle<TextureAtlas>>,
+    Option<&'a Sprite>,
+    Option<&'a Handle<Image>>,
+);
+
+/// Finds targetable entities by their sprite bounds
+#[derive(SystemParam)]
+pub struct TargetPicker<'w, 's> {
+    query: Query<'w, 's, TargetSprite<'static>, With<Targetable>>,
+    atlases: Res<'w, Assets<TextureAtlas>>,
+    images: Res<'w, Assets<Image>>,
+}
+
+impl<'w, 's> TargetPicker<'w, 's> {
+    /// The size of the entity's sprite, before any scaling,
+    /// if it is targetable and its textures are loaded
+    pub fn sprite_size(&self, entity: Entity) -> Option<Vec2> {
+        let (_, _, atlas_sprite, atlas, sprite, image) = self.query.get(entity).ok()?;
+        if let (Some(atlas_sprite), Some(atlas)) = (atlas_sprite, atlas) {
+            return atlas_sprite.custom_size.or_else(|| {
+                let atlas = self.atlases.get(atlas)?;
+                atlas
+                    .textures
+                    .get(atlas_sprite.index)
+                    .map(|rect| rect.size())
+            });
+        }
+        if let (Some(sprite), Some(image)) = (sprite, image) {
+            return sprite
+                .custom_size
+                .or_else(|| self.images.get(image).map(|image| image.size()));
+        }
+        None
+    }
+
+    /// The targetable entity under the given world position, if any.
+    /// The one in front wins when sprites overlap.
+    pub fn pick(&self, pos: Vec2) -> Option<Entity> {
+        self.query
+            .iter()
+            .filter_map(|(entity, transform, ..)| {
+                let size = self.sprite_size(entity)?;
+                let local = transform
+                    .affine()
+                    .inverse()
+                    .transform_point3(pos.extend(transform.translation().z));
+                let half = size / 2. + PICK_MARGIN;
+                (local.x.abs() <= half.x && local.y.abs() <= half.y)
+                    .then(|| (entity, transform.translation().z))
+            })
+            .max_by(|(_, z1), (_, z2)| z1.total_cmp(z2))
+            .map(|(entity, _)| entity)
+    }
+}
+
+/// system: keep walking towards the locked target,
+/// dropping it once it is gone or dealt with
+pub fn follow_target(
+    mut query_guy: Query<(&mut GuyTarget, &mut GuyDestination, &BaseTranslation)>,
+    query_targets: Query<
+        (&SpatialPosition, &BaseTranslation, Option<&BombState>),
+        With<Targetable>,
+    >,
+) {
+    for (mut target, mut destination, guy_base_translation) in &mut query_guy {
+        let Some(entity) = target.0 else {
+            continue;
+        };
+        match query_targets.get(entity) {
+            Ok((pos, base_translation, bomb_state)) if bomb_state != Some(&BombState::Disarmed) => {
+                // same alignment as for bombs in `disarming_bomb`
+                let to = pos.0.truncate() - base_translation.0 - guy_base_translation.0;
+                if destination.0 != to {
+                    destination.0 = to;
+                }
+            }
+            _ => {
+                target.0 = None;
+            }
+        }
+    }
+}
+
+/// Highlights the target of the guy
+#[derive(Debug, Component)]
+pub struct TargetOutline(Entity);
+
+/// system: draw an outline around each locked target
+pub fn update_target_outline(
+    mut commands: Commands,
+    picker: TargetPicker,
+    query_guy: Query<&GuyTarget>,
+    query_transforms: Query<&Transform, Without<TargetOutline>>,
+    mut query_outlines: Query<(Entity, &TargetOutline, &mut Transform)>,
+) {
+    let targets: Vec<Entity> = query_guy.iter().filter_map(|target| target.0).collect();
+
+    let mut outlined: Vec<Entity> = vec![];
+    for (entity, outline, mut transform) in &mut query_outlines {
+        let target_transform = query_transforms.get(outline.0);
+        match target_transform {
+            Ok(target_transform)
+                if targets.contains(&outline.0) && !outlined.contains(&outline.0) =>
+            {
+                outlined.push(outline.0);
+                // follow the target, just in front of it
+                *transform = *target_transform;
+                transform.translation.z += 0.01;
+            }
+            _ => {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+
+    for target in targets {
+        if outlined.contains(&target) {
+            continue;
+        }
+        let (Some(size), Ok(target_transform)) =
+            (picker.sprite_size(target), query_transforms.get(target))
+        else {
+            continue;
+        };
+        // two guys may be after the same target
+        outlined.push(target);
+
+        // four thin bars around the sprite, in sprite pixels
+        let half = size / 2. + 1.;
+        let bars = [
+            (Vec2::new(0., half.y), Vec2::new(size.x + 3., 1.)),
+            (Vec2::new(0., -half.y), Vec2::new(size.x + 3., 1.)),
+            (Vec2::new(-half.x, 0.), Vec2::new(1., size.y + 3.)),
+            (Vec2::new(half.x, 0.), Vec2::new(1., size.y + 3.)),
+        ];
+        commands
+            .spawn((
+                SpatialBundle::from_transform(*target_transform),
+                TargetOutline(target),
+            ))
+            .with_children(|parent| {
+                for (pos, size) in bars {
+                    parent.spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: OUTLINE_COLOR,
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(pos.extend(0.)),
+                        ..default()
+                    });
+                }
+            });
+    }
+}
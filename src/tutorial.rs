@@ -0,0 +1,433 @@
+//! the guided tutorial played on wave 0.
+//!
+//! Each step tells the player what to do with an on-screen prompt
+//! and an arrow pointing at where to go,
+//! and waits for them to do it before moving on.
+//! Once completed (or skipped), wave 0 is just a quick warm-up.
+
+use bevy::utils::Duration;
+use bevy::{ecs::system::SystemParam, prelude::*, sprite::MaterialMesh2dBundle};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility::HudText,
+    audio::GameSoundSources,
+    autopilot::ComputerPlayer,
+    bomb::BombState,
+    coffee::Coffee,
+    dynamite::Dynamite,
+    events::{
+        BombDisarmedEvent, BombThrownEvent, CoffeePickedUpEvent, CoffeeThrownEvent,
+        DynamiteDefusedEvent, DynamiteThrownEvent,
+    },
+    guy::GuyState,
+    helper::BaseTranslation,
+    i18n::LocalizedText,
+    menu::{HOVER_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON},
+    movement::SpatialPosition,
+    screen::InField,
+    settings::Settings,
+    spawner::{PendingThrow, RunSeed},
+    DefaultFont,
+};
+
+/// The key under which tutorial progress is stored
+const TUTORIAL_KEY: &str = "tutorial";
+
+/// Where the guy is first asked to walk to
+const FIRST_SPOT: Vec2 = Vec2::new(190., 220.);
+
+/// How close the guy needs to get to the first spot
+const SPOT_REACH: f32 = 28.;
+
+/// Whether the player has been through the tutorial
+#[derive(Debug, Default, Clone, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TutorialProgress {
+    pub completed: bool,
+}
+
+impl TutorialProgress {
+    /// Load tutorial progress from storage
+    pub fn load() -> Self {
+        crate::storage::load(TUTORIAL_KEY).unwrap_or_default()
+    }
+
+    fn complete(&mut self) {
+        if !self.completed {
+            self.completed = true;
+            crate::storage::save(TUTORIAL_KEY, self);
+        }
+    }
+}
+
+/// The steps of the tutorial, in order
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+enum TutorialStep {
+    #[default]
+    Move,
+    Dynamite,
+    Bomb,
+    Coffee,
+    Done,
+}
+
+impl TutorialStep {
+    fn next(self) -> Self {
+        match self {
+            TutorialStep::Move => TutorialStep::Dynamite,
+            TutorialStep::Dynamite => TutorialStep::Bomb,
+            TutorialStep::Bomb => TutorialStep::Coffee,
+            TutorialStep::Coffee | TutorialStep::Done => TutorialStep::Done,
+        }
+    }
+
+    /// the key of the prompt text
+    fn prompt(self) -> &'static str {
+        match self {
+            TutorialStep::Move => "tutorial_move",
+            TutorialStep::Dynamite => "tutorial_dynamite",
+            TutorialStep::Bomb => "tutorial_bomb",
+            TutorialStep::Coffee => "tutorial_coffee",
+            TutorialStep::Done => "tutorial_done",
+        }
+    }
+}
+
+/// Drives the tutorial.
+/// Counts as a pending throw, so that the wave goes on until it is over.
+#[derive(Debug, Default, Component)]
+pub struct TutorialScript {
+    step: TutorialStep,
+    /// whether the item for this step was thrown
+    thrown: bool,
+    /// whether the item for this step was seen in the field
+    seen: bool,
+    /// how long the last prompt stays up
+    done_timer: Option<Timer>,
+}
+
+/// Marker for everything shown during the tutorial
+#[derive(Debug, Component)]
+pub struct TutorialUi;
+
+/// Marker for the tutorial prompt text
+#[derive(Debug, Component)]
+pub struct TutorialPrompt;
+
+/// Marker for the arrow pointing at where to go
+#[derive(Debug, Component)]
+pub struct TutorialArrow;
+
+/// The button for skipping the tutorial
+#[derive(Debug, Component)]
+pub struct SkipTutorialButton;
+
+/// Start the tutorial for wave 0
+pub fn spawn_tutorial(commands: &mut Commands) {
+    commands.spawn((TutorialScript::default(), PendingThrow));
+}
+
+/// system: show the tutorial, or go straight for the quick warm-up
+/// if it was done before (or the computer is playing,
+/// as it does not follow the tutorial's steps,
+/// or the run is on a seed which others play as well)
+pub fn begin(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    font: Res<DefaultFont>,
+    (progress, run_seed): (Res<TutorialProgress>, Res<RunSeed>),
+    computer: ComputerPlayer,
+    query: Query<Entity, Added<TutorialScript>>,
+) {
+    let Ok(script) = query.get_single() else {
+        return;
+    };
+
+    if progress.completed || computer.is_playing() || run_seed.shared {
+        commands.entity(script).despawn();
+        crate::waves::spawn_warm_up(&mut commands);
+        return;
+    }
+
+    // prompt
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 26.,
+                color: Color::rgb(1., 1., 0.6),
+            },
+        )
+        .with_text_alignment(TextAlignment::CENTER)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(96.),
+                left: Val::Px(0.),
+                right: Val::Px(0.),
+                ..default()
+            },
+            justify_content: JustifyContent::Center,
+            ..default()
+        }),
+        TutorialPrompt,
+        TutorialUi,
+        InField,
+        HudText { base_size: 26. },
+        LocalizedText::new(TutorialStep::Move.prompt()),
+    ));
+
+    // skip button
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(12.),
+                        right: Val::Px(12.),
+                        ..default()
+                    },
+                    size: Size::new(Val::Px(96.), Val::Px(40.)),
+                    // horizontally center child text
+                    justify_content: JustifyContent::Center,
+                    // vertically center child text
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: NORMAL_BUTTON.into(),
+                ..default()
+            },
+            SkipTutorialButton,
+            TutorialUi,
+            InField,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 24.,
+                        color: Color::rgba(1., 1., 0.8, 1.0),
+                    },
+                ),
+                LocalizedText::new("skip"),
+            ));
+        });
+
+    // arrow, pointing down
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes
+                .add(Mesh::from(shape::RegularPolygon::new(12., 3)))
+                .into(),
+            material: materials.add(ColorMaterial::from(Color::rgb(1., 0.9, 0.2))),
+            transform: Transform::from_translation(FIRST_SPOT.extend(500.))
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::PI)),
+            ..default()
+        },
+        TutorialArrow,
+        TutorialUi,
+    ));
+}
+
+/// The events which complete tutorial steps
+#[derive(SystemParam)]
+pub struct StepEvents<'w, 's> {
+    dynamite_defused: EventReader<'w, 's, DynamiteDefusedEvent>,
+    bomb_disarmed: EventReader<'w, 's, BombDisarmedEvent>,
+    coffee_picked_up: EventReader<'w, 's, CoffeePickedUpEvent>,
+}
+
+/// system: move on to the next step once the player did what was asked
+pub fn advance(
+    time: Res<Time>,
+    mut progress: ResMut<TutorialProgress>,
+    mut query: Query<&mut TutorialScript>,
+    query_guy: Query<(&SpatialPosition, &BaseTranslation), With<GuyState>>,
+    mut query_prompt: Query<&mut LocalizedText, With<TutorialPrompt>>,
+    mut events: StepEvents,
+) {
+    // always drain the events, so that old ones do not count
+    let dynamite_defused = events.dynamite_defused.iter().count() > 0;
+    let bomb_disarmed = events.bomb_disarmed.iter().count() > 0;
+    let coffee_picked_up = events.coffee_picked_up.iter().count() > 0;
+
+    let Ok(mut script) = query.get_single_mut() else {
+        return;
+    };
+
+    let step_done = match script.step {
+        TutorialStep::Move => query_guy
+            .iter()
+            .any(|(pos, base)| (pos.0.truncate() + base.0).distance(FIRST_SPOT) < SPOT_REACH),
+        TutorialStep::Dynamite => dynamite_defused,
+        TutorialStep::Bomb => bomb_disarmed,
+        TutorialStep::Coffee => coffee_picked_up,
+        TutorialStep::Done => {
+            if let Some(timer) = &mut script.done_timer {
+                timer.tick(time.delta());
+            }
+            false
+        }
+    };
+    if !step_done {
+        return;
+    }
+
+    let step = script.step.next();
+    info!("Tutorial step: {:?}", step);
+    script.step = step;
+    script.thrown = false;
+    script.seen = false;
+    if step == TutorialStep::Done {
+        progress.complete();
+        script.done_timer = Some(Timer::new(Duration::from_secs(2), TimerMode::Once));
+    }
+    if let Ok(mut text) = query_prompt.get_single_mut() {
+        *text = LocalizedText::new(step.prompt());
+    }
+}
+
+/// system: throw the item for the current step,
+/// and throw another one if it was missed
+pub fn throw_items(
+    mut query: Query<&mut TutorialScript>,
+    query_dynamite: Query<(), With<Dynamite>>,
+    query_bombs: Query<&BombState>,
+    query_coffee: Query<(), With<Coffee>>,
+    mut dynamite_event_writer: EventWriter<DynamiteThrownEvent>,
+    mut bomb_event_writer: EventWriter<BombThrownEvent>,
+    mut coffee_event_writer: EventWriter<CoffeeThrownEvent>,
+) {
+    let Ok(mut script) = query.get_single_mut() else {
+        return;
+    };
+
+    let items_left = match script.step {
+        TutorialStep::Move | TutorialStep::Done => return,
+        TutorialStep::Dynamite => query_dynamite.iter().count(),
+        TutorialStep::Bomb => query_bombs
+            .iter()
+            .filter(|state| **state == BombState::Idle)
+            .count(),
+        TutorialStep::Coffee => query_coffee.iter().count(),
+    };
+
+    if script.thrown {
+        if items_left > 0 {
+            script.seen = true;
+        } else if script.seen {
+            // it blew up or went away, try again
+            script.thrown = false;
+            script.seen = false;
+        }
+        return;
+    }
+
+    match script.step {
+        TutorialStep::Dynamite => dynamite_event_writer.send(DynamiteThrownEvent),
+        TutorialStep::Bomb => bomb_event_writer.send(BombThrownEvent),
+        TutorialStep::Coffee => coffee_event_writer.send(CoffeeThrownEvent),
+        TutorialStep::Move | TutorialStep::Done => {}
+    }
+    script.thrown = true;
+}
+
+/// system: point the arrow at where the player should go
+pub fn point_arrow(
+    (time, settings): (Res<Time>, Res<Settings>),
+    query: Query<&TutorialScript>,
+    mut query_arrow: Query<(&mut Transform, &mut Visibility), With<TutorialArrow>>,
+    query_dynamite: Query<&Transform, (With<Dynamite>, Without<TutorialArrow>)>,
+    query_bombs: Query<(&Transform, &BombState), Without<TutorialArrow>>,
+    query_coffee: Query<&Transform, (With<Coffee>, Without<TutorialArrow>)>,
+) {
+    let (Ok(script), Ok((mut transform, mut visibility))) =
+        (query.get_single(), query_arrow.get_single_mut())
+    else {
+        return;
+    };
+
+    let target = match script.step {
+        TutorialStep::Move => Some(FIRST_SPOT),
+        TutorialStep::Dynamite => query_dynamite
+            .iter()
+            .next()
+            .map(|t| t.translation.truncate()),
+        TutorialStep::Bomb => query_bombs
+            .iter()
+            .find(|(_, state)| **state == BombState::Idle)
+            .map(|(t, _)| t.translation.truncate()),
+        TutorialStep::Coffee => query_coffee.iter().next().map(|t| t.translation.truncate()),
+        TutorialStep::Done => None,
+    };
+
+    visibility.is_visible = target.is_some();
+    if let Some(target) = target {
+        // bob up and down above the target, unless motion is reduced
+        let bob = if settings.reduced_motion {
+            0.
+        } else {
+            (time.elapsed_seconds() * 6.).sin() * 4.
+        };
+        transform.translation = (target + Vec2::new(0., 30. + bob)).extend(500.);
+    }
+}
+
+/// interactions with the skip button
+type SkipButtonChanged = (Changed<Interaction>, With<SkipTutorialButton>);
+
+/// system: wrap up the tutorial once it is over,
+/// or when the player skips it
+pub fn finish(
+    mut commands: Commands,
+    audio: Res<Audio>,
+    sound_sources: Res<GameSoundSources>,
+    mut progress: ResMut<TutorialProgress>,
+    query: Query<(Entity, &TutorialScript)>,
+    mut query_skip: Query<(&Interaction, &mut BackgroundColor), SkipButtonChanged>,
+    query_ui: Query<Entity, With<TutorialUi>>,
+) {
+    let Ok((entity, script)) = query.get_single() else {
+        return;
+    };
+
+    let mut skipped = false;
+    for (interaction, mut color) in &mut query_skip {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                audio.play(sound_sources.click.cast_weak());
+                skipped = true;
+            }
+            Interaction::Hovered => {
+                *color = HOVER_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+
+    let over = script
+        .done_timer
+        .as_ref()
+        .is_some_and(|timer| timer.finished());
+    if !skipped && !over {
+        return;
+    }
+
+    if skipped {
+        info!("Tutorial skipped");
+    }
+    progress.complete();
+    commands.entity(entity).despawn();
+    for e in &query_ui {
+        commands.entity(e).despawn_recursive();
+    }
+}
@@ -1,40 +1,156 @@
 //! Module containing all wave descriptors
 //!
+//! The waves shipped with the game are laid out in code,
+//! and those saved from the wave editor as data.
 
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy::utils::Duration;
-use bevy_ecs_tilemap::{
-    prelude::TilemapId,
-    tiles::{TilePos, TileTextureIndex},
-};
+use bevy::reflect::TypeUuid;
+use bevy::{asset::LoadState, utils::Duration};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
 
 use crate::{
-    background::Background,
+    animation::spawn_fade_in_black_screen,
     events::{
         BombThrownEvent, CoffeeThrownEvent, DynamiteThrownEvent, NextWaveEvent, WaveFinishedEvent,
     },
-    guy::{GuyDestination, GuyState},
+    guy::{GuyDestination, GuyState, Player},
     helper::ScheduledEvent,
-    ingame::{Wave, WaveUi},
+    i18n::LocalizedText,
+    ingame::{GameMode, Wave, WaveUi},
+    level::{CurrentLevel, LevelLayouts, LEVELS},
     menu::NORMAL_BUTTON,
     movement::{SpatialPosition, SpatialVelocity},
+    obstacle::{spawn_obstacle, ObstacleKind},
+    particles::Particle,
     scores::{spawn_stats, GameScores, RemarkUi},
-    spawner::{PendingThrow, RandomEventProducer, Spawner, SpawnerCooldown},
-    DefaultFont,
+    spawner::{AimedThrow, PendingThrow, RandomEventProducer, Spawner, SpawnerCooldown, Throwable},
+    AppState, DefaultFont, DelayedStateChange,
 };
 
-pub static WAVE_DESCRIPTORS: &[fn(Commands) -> ()] = &[
-    spawn_wave_0,
-    spawn_wave_1,
-    spawn_wave_2,
-    spawn_wave_3,
-    spawn_wave_4,
-    spawn_wave_5,
-    spawn_wave_6,
+/// The waves shipped with the game, in order
+pub static WAVE_DESCRIPTORS: &[WaveDescriptor] = &[
+    WaveDescriptor::Code(spawn_wave_0),
+    WaveDescriptor::Code(spawn_wave_1),
+    WaveDescriptor::Code(spawn_wave_2),
+    WaveDescriptor::Code(spawn_wave_3),
+    WaveDescriptor::Code(spawn_wave_4),
+    WaveDescriptor::Code(spawn_wave_5),
+    WaveDescriptor::Code(spawn_wave_6),
 ];
 
+/// How a wave is laid out
+#[derive(Clone)]
+pub enum WaveDescriptor {
+    /// by a function spawning what the wave has
+    Code(fn(Commands)),
+    /// by a [`WaveDefinition`] saved from the wave editor
+    #[cfg_attr(not(feature = "editor"), allow(dead_code))]
+    Data(Handle<WaveDefinition>),
+}
+
+impl WaveDescriptor {
+    /// Spawn what the wave has
+    /// (or, for a wave laid out as data, wait for it to be loaded)
+    pub fn spawn(&self, mut commands: Commands) {
+        match self {
+            WaveDescriptor::Code(spawn) => spawn(commands),
+            WaveDescriptor::Data(handle) => {
+                commands.spawn(AwaitedWave(handle.clone()));
+            }
+        }
+    }
+}
+
+/// The waves of a run, in order:
+/// those shipped with the game,
+/// followed by those saved from the wave editor in builds which have it
+#[derive(Clone, Resource)]
+pub struct WaveList(Vec<WaveDescriptor>);
+
+impl Default for WaveList {
+    fn default() -> Self {
+        WaveList(WAVE_DESCRIPTORS.to_vec())
+    }
+}
+
+impl WaveList {
+    pub fn get(&self, wave: u16) -> Option<&WaveDescriptor> {
+        self.0.get(wave as usize)
+    }
+
+    /// How many waves there are in a run
+    pub fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Add a wave laid out as data at the end of the run,
+    /// unless it is there already.
+    /// Returns whether it was added.
+    #[cfg_attr(not(feature = "editor"), allow(dead_code))]
+    pub fn add_data(&mut self, handle: Handle<WaveDefinition>) -> bool {
+        let listed = self
+            .0
+            .iter()
+            .any(|wave| matches!(wave, WaveDescriptor::Data(listed) if *listed == handle));
+        if !listed {
+            self.0.push(WaveDescriptor::Data(handle));
+        }
+        !listed
+    }
+}
+
+/// Stands in for a wave laid out as data until its definition is loaded,
+/// so that the wave does not end before it starts
+#[derive(Debug, Component)]
+pub struct AwaitedWave(Handle<WaveDefinition>);
+
+/// system: start the waves laid out as data once they are loaded,
+/// leaving the run if one could not be
+pub fn start_awaited_waves(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    (definitions, layouts): (Res<Assets<WaveDefinition>>, Res<LevelLayouts>),
+    query_awaited: Query<(Entity, &AwaitedWave)>,
+    transition_entity: Query<Entity, With<DelayedStateChange>>,
+) {
+    for (entity, awaited) in &query_awaited {
+        if let Some(definition) = definitions.get(&awaited.0) {
+            commands.entity(entity).despawn();
+            commands.insert_resource(CurrentLevel(layouts.get(&definition.level)));
+            definition.spawn(&mut commands);
+        } else if asset_server.get_load_state(&awaited.0) == LoadState::Failed {
+            let path = asset_server.get_handle_path(&awaited.0);
+            error!("Could not load wave {:?}, leaving the run", path);
+            commands.entity(entity).despawn();
+            if transition_entity.is_empty() {
+                let e = spawn_fade_in_black_screen(&mut commands, Duration::from_millis(400));
+                commands.entity(e).insert(DelayedStateChange::new(
+                    AppState::MainMenu,
+                    Duration::from_millis(750),
+                ));
+            }
+        }
+    }
+}
+
+/// The level layout the given wave is played on
+/// (waves laid out as data switch to their own once they start)
+pub fn wave_level(wave: u16) -> &'static str {
+    WAVE_LEVELS
+        .get(wave as usize)
+        .or_else(|| WAVE_LEVELS.last())
+        .unwrap()
+}
+
 // wave 0 to serve as tutorial
 pub fn spawn_wave_0(mut commands: Commands) {
+    crate::tutorial::spawn_tutorial(&mut commands);
+}
+
+/// wave 0 for those who went through the tutorial already:
+/// one dynamite and one bomb
+pub fn spawn_warm_up(commands: &mut Commands) {
     commands.spawn((
         ScheduledEvent::new(DynamiteThrownEvent, Duration::from_millis(750)),
         PendingThrow,
@@ -46,6 +162,8 @@ pub fn spawn_wave_0(mut commands: Commands) {
 }
 
 pub fn spawn_wave_1(mut commands: Commands) {
+    spawn_obstacle(&mut commands, ObstacleKind::Crate, Vec2::new(96., 180.));
+    spawn_obstacle(&mut commands, ObstacleKind::Crate, Vec2::new(290., 120.));
     commands.spawn((
         Spawner::new_essential(10),
         RandomEventProducer::new(0.42, DynamiteThrownEvent),
@@ -57,6 +175,12 @@ pub fn spawn_wave_1(mut commands: Commands) {
 }
 
 pub fn spawn_wave_2(mut commands: Commands) {
+    spawn_obstacle(
+        &mut commands,
+        ObstacleKind::CementMixer,
+        Vec2::new(100., 120.),
+    );
+    spawn_obstacle(&mut commands, ObstacleKind::Crate, Vec2::new(280., 330.));
     commands.spawn((
         Spawner::new_essential(18),
         RandomEventProducer::new(0.45, DynamiteThrownEvent),
@@ -76,6 +200,13 @@ pub fn spawn_wave_2(mut commands: Commands) {
 }
 
 pub fn spawn_wave_3(mut commands: Commands) {
+    spawn_obstacle(
+        &mut commands,
+        ObstacleKind::Scaffolding,
+        Vec2::new(190., 400.),
+    );
+    spawn_obstacle(&mut commands, ObstacleKind::Crate, Vec2::new(70., 150.));
+    spawn_obstacle(&mut commands, ObstacleKind::Crate, Vec2::new(310., 150.));
     commands.spawn((
         Spawner::new_essential(22),
         RandomEventProducer::new(0.5, DynamiteThrownEvent),
@@ -91,6 +222,16 @@ pub fn spawn_wave_3(mut commands: Commands) {
 }
 
 pub fn spawn_wave_4(mut commands: Commands) {
+    spawn_obstacle(
+        &mut commands,
+        ObstacleKind::CementMixer,
+        Vec2::new(80., 360.),
+    );
+    spawn_obstacle(
+        &mut commands,
+        ObstacleKind::CementMixer,
+        Vec2::new(300., 110.),
+    );
     commands.spawn((
         Spawner::new_essential(12),
         RandomEventProducer::new(0.4, DynamiteThrownEvent),
@@ -106,6 +247,16 @@ pub fn spawn_wave_4(mut commands: Commands) {
 }
 
 pub fn spawn_wave_5(mut commands: Commands) {
+    spawn_obstacle(
+        &mut commands,
+        ObstacleKind::Scaffolding,
+        Vec2::new(110., 200.),
+    );
+    spawn_obstacle(
+        &mut commands,
+        ObstacleKind::Scaffolding,
+        Vec2::new(270., 360.),
+    );
     commands.spawn((
         Spawner::new_essential(26),
         RandomEventProducer::new(0.3, DynamiteThrownEvent),
@@ -121,6 +272,22 @@ pub fn spawn_wave_5(mut commands: Commands) {
 }
 
 pub fn spawn_wave_6(mut commands: Commands) {
+    spawn_obstacle(
+        &mut commands,
+        ObstacleKind::Scaffolding,
+        Vec2::new(190., 430.),
+    );
+    spawn_obstacle(
+        &mut commands,
+        ObstacleKind::CementMixer,
+        Vec2::new(70., 240.),
+    );
+    spawn_obstacle(
+        &mut commands,
+        ObstacleKind::CementMixer,
+        Vec2::new(310., 240.),
+    );
+    spawn_obstacle(&mut commands, ObstacleKind::Crate, Vec2::new(190., 90.));
     commands.spawn((
         Spawner::new_essential(96),
         RandomEventProducer::new(0.64, DynamiteThrownEvent),
@@ -135,6 +302,134 @@ pub fn spawn_wave_6(mut commands: Commands) {
     ));
 }
 
+/// A wave laid out as data rather than code,
+/// as made in the wave editor. In RON:
+///
+/// ```ron
+/// (
+///     level: "site",
+///     obstacles: [(Crate, (96., 180.))],
+///     throws: [(item: Bomb, after: 16., position: (190., 240.))],
+///     spawners: [(item: Dynamite, lambda: 0.42, count: 10, essential: true)],
+/// )
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TypeUuid)]
+#[uuid = "dad0b4c4-0c5c-4aad-8b6c-cb5f47295fef"]
+pub struct WaveDefinition {
+    /// the level layout the wave is played on
+    pub level: String,
+    #[serde(default)]
+    pub obstacles: Vec<(ObstacleKind, (f32, f32))>,
+    /// one-off throws aimed at a given spot
+    #[serde(default)]
+    pub throws: Vec<ThrowDefinition>,
+    /// throws at random spots and times
+    #[serde(default)]
+    pub spawners: Vec<SpawnerDefinition>,
+}
+
+impl Default for WaveDefinition {
+    fn default() -> Self {
+        WaveDefinition {
+            level: LEVELS[0].to_string(),
+            obstacles: vec![],
+            throws: vec![],
+            spawners: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThrowDefinition {
+    pub item: Throwable,
+    /// seconds from the start of the wave
+    pub after: f32,
+    pub position: (f32, f32),
+}
+
+/// A spawner, as in [`Spawner`] and [`RandomEventProducer`]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpawnerDefinition {
+    pub item: Throwable,
+    /// the rate of the exponential distribution of the time between throws
+    #[serde(deserialize_with = "deserialize_rate")]
+    pub lambda: f32,
+    /// how many items to throw
+    pub count: u32,
+    /// whether all items must be thrown for the wave to end
+    pub essential: bool,
+}
+
+/// Whether the rate of a spawner can be sampled from
+pub fn is_valid_rate(lambda: f32) -> bool {
+    lambda.is_finite() && lambda > 0.
+}
+
+/// Read the rate of a spawner, rejecting rates which cannot be sampled from
+fn deserialize_rate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let lambda = f32::deserialize(deserializer)?;
+    if !is_valid_rate(lambda) {
+        return Err(D::Error::custom(format!(
+            "the rate of a spawner must be a positive number, not {}",
+            lambda
+        )));
+    }
+    Ok(lambda)
+}
+
+impl WaveDefinition {
+    /// Whether all of the wave's spawners have rates which can be sampled from
+    #[cfg_attr(not(feature = "editor"), allow(dead_code))]
+    pub fn has_valid_rates(&self) -> bool {
+        self.spawners
+            .iter()
+            .all(|spawner| is_valid_rate(spawner.lambda))
+    }
+
+    /// Spawn the obstacles, throws and spawners of the wave
+    pub fn spawn(&self, commands: &mut Commands) {
+        for (kind, (x, y)) in &self.obstacles {
+            spawn_obstacle(commands, *kind, Vec2::new(*x, *y));
+        }
+        for throw in &self.throws {
+            let (x, y) = throw.position;
+            commands.spawn((
+                AimedThrow::new(
+                    throw.item,
+                    Vec2::new(x, y),
+                    Duration::from_secs_f32(throw.after.max(0.)),
+                ),
+                PendingThrow,
+            ));
+        }
+        for spawner in &self.spawners {
+            let component = Spawner {
+                remaining: spawner.count,
+                essential: spawner.essential,
+            };
+            match spawner.item {
+                Throwable::Dynamite => commands.spawn((
+                    component,
+                    RandomEventProducer::new(spawner.lambda, DynamiteThrownEvent),
+                )),
+                Throwable::Bomb => commands.spawn((
+                    component,
+                    RandomEventProducer::new(spawner.lambda, BombThrownEvent),
+                )),
+                Throwable::Coffee => commands.spawn((
+                    component,
+                    RandomEventProducer::new(spawner.lambda, CoffeeThrownEvent),
+                )),
+            };
+        }
+    }
+}
+
+/// Present while a wave is played from the editor
+#[derive(Debug, Resource)]
+#[cfg_attr(not(feature = "editor"), allow(dead_code))]
+pub struct PreviewWave(pub WaveDefinition);
+
 /// Marker component for entities representing the end of the wave.
 #[derive(Component)]
 pub struct WaveFinished;
@@ -153,17 +448,34 @@ impl TickCounter {
     }
 }
 
+/// all scheduled throws, and waves yet to be loaded
+type PendingThrowFilter = Or<(With<PendingThrow>, With<AwaitedWave>)>;
+
+/// all active entities in the world save for guy
+type ActiveEntityFilter = (With<SpatialPosition>, Without<GuyState>, Without<Particle>);
+
+/// Whatever keeps the current wave going, besides its spawners
+#[derive(SystemParam)]
+pub struct WaveLeftovers<'w, 's> {
+    query_throws: Query<'w, 's, (), PendingThrowFilter>,
+    query_active_entities: Query<'w, 's, (), ActiveEntityFilter>,
+}
+
+impl WaveLeftovers<'_, '_> {
+    /// Whether there is nothing left to throw or to deal with
+    fn is_empty(&self) -> bool {
+        self.query_throws.is_empty() && self.query_active_entities.is_empty()
+    }
+}
+
 /// system: grab existing spawners, see if they're done
 pub fn detect_wave_finish(
     mut commands: Commands,
     query_wave_finished: Query<(), With<WaveFinished>>,
-    // find all scheduled throws
-    query_throws: Query<(), With<PendingThrow>>,
-    // find all active entities in the world save for guy
-    query_active_entities: Query<(), (With<SpatialPosition>, Without<GuyState>)>,
+    leftovers: WaveLeftovers,
     // find all spawners
     mut query_spawners: Query<(Entity, &Spawner, Option<&SpawnerCooldown>)>,
-    mut query_wave_ui: Query<&mut Text, With<WaveUi>>,
+    mut query_wave_ui: Query<&mut LocalizedText, With<WaveUi>>,
     mut event_writer: EventWriter<WaveFinishedEvent>,
     mut ticks: Local<TickCounter>,
 ) {
@@ -171,36 +483,30 @@ pub fn detect_wave_finish(
         return;
     }
 
-    // no pending throws
-    let c1 = query_throws.is_empty();
+    // no pending throws, no active entities but guy
+    let c1 = leftovers.is_empty();
     if !c1 {
         return;
     }
 
-    // no active entities but guy
-    let c2 = query_active_entities.is_empty();
-    if !c2 {
-        return;
-    }
-
     // spawners are down to 0 and not cooling down
-    let c3 = query_spawners
+    let c2 = query_spawners
         .iter_mut()
         .all(|(_, spawner, cooldown)| cooldown.is_none() && spawner.remaining == 0);
-    if !c3 {
+    if !c2 {
         return;
     }
 
     // if wave is already finished, we don't want to repeat this
-    let c4 = query_wave_finished.is_empty();
-    if !c4 {
+    let c3 = query_wave_finished.is_empty();
+    if !c3 {
         return;
     }
 
     info!("Wave finished");
 
     if let Ok(mut wave_ui_text) = query_wave_ui.get_single_mut() {
-        wave_ui_text.sections[0].value += "\nCOMPLETE";
+        wave_ui_text.key += "_complete";
     }
 
     // emit end of wave event
@@ -218,51 +524,67 @@ pub fn detect_wave_finish(
     ));
 }
 
+/// What the game over screen is made of
+#[derive(SystemParam)]
+pub struct GameOver<'w, 's> {
+    scores: Res<'w, GameScores>,
+    mode: Res<'w, GameMode>,
+    font: Res<'w, DefaultFont>,
+    query_guy:
+        Query<'w, 's, (Entity, &'static mut GuyState, &'static mut SpatialVelocity), With<Player>>,
+}
+
 /// system: on next wave event
 pub fn on_next_wave(
     mut commands: Commands,
     mut wave: ResMut<Wave>,
-    scores: Res<GameScores>,
-    font: Res<DefaultFont>,
+    waves: Res<WaveList>,
     mut event_reader: EventReader<NextWaveEvent>,
-    mut query_wave_ui: Query<&mut Text, With<WaveUi>>,
-    query_guy: Query<(Entity, &mut GuyState, &mut SpatialVelocity)>,
+    mut query_wave_ui: Query<&mut LocalizedText, With<WaveUi>>,
     query_wave_finished: Query<Entity, With<WaveFinished>>,
+    game_over: GameOver,
 ) {
     if let Some(_) = event_reader.iter().next() {
         wave.0 += 1;
 
-        if let Some(wave_fn) = WAVE_DESCRIPTORS.get(wave.0 as usize) {
+        if let Some(descriptor) = waves.get(wave.0) {
             info!("Next wave: {}", wave.0);
 
             if let Ok(mut wave_ui_text) = query_wave_ui.get_single_mut() {
-                wave_ui_text.sections[0].value = if wave.0 as usize == WAVE_DESCRIPTORS.len() - 1 {
-                    "FINAL WAVE".to_string()
-                } else {
-                    format!("WAVE {}", wave.0)
-                };
+                *wave_ui_text = wave_text(wave.0, waves.count());
             }
 
             for e in &query_wave_finished {
                 commands.entity(e).despawn();
             }
 
-            (wave_fn)(commands);
+            descriptor.spawn(commands);
         } else {
             // The end!
             info!("Game over");
-            spawn_game_over(&mut commands, scores, font, query_guy);
+            spawn_game_over(&mut commands, game_over);
         }
     }
 }
 
-fn spawn_game_over(
-    commands: &mut Commands,
-    scores: Res<GameScores>,
-    font: Res<DefaultFont>,
-    mut query_guy: Query<(Entity, &mut GuyState, &mut SpatialVelocity)>,
-) {
-    if let Ok((guy_entity, mut guy_state, mut guy_velocity)) = query_guy.get_single_mut() {
+/// The wave number, as shown in the HUD
+pub fn wave_text(wave: u16, wave_count: usize) -> LocalizedText {
+    if wave as usize == wave_count - 1 {
+        LocalizedText::new("final_wave")
+    } else {
+        LocalizedText::new("wave").with_arg("n", wave)
+    }
+}
+
+fn spawn_game_over(commands: &mut Commands, game_over: GameOver) {
+    let GameOver {
+        scores,
+        mode,
+        font,
+        mut query_guy,
+    } = game_over;
+    // the whole team wins or loses together
+    for (guy_entity, mut guy_state, mut guy_velocity) in &mut query_guy {
         match scores.score {
             -999_999..=255 => {
                 *guy_state = GuyState::Loser;
@@ -301,6 +623,35 @@ fn spawn_game_over(
             // spawn game stats thingy
             spawn_stats(&mut parent, font.0.clone());
 
+            // in co-op, how each player did
+            if scores.players.len() > 1 {
+                for (i, player_scores) in scores.players.iter().enumerate() {
+                    let player = Player(i as u8);
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 18.,
+                                color: player.tint(),
+                            },
+                        )
+                        .with_text_alignment(TextAlignment::CENTER)
+                        .with_style(Style {
+                            // wrap long lines
+                            max_size: Size::new(Val::Px(340.), Val::Undefined),
+                            ..default()
+                        }),
+                        LocalizedText::new("player_summary")
+                            .with_arg("player", i + 1)
+                            .with_arg("score", player_scores.score)
+                            .with_arg("bombs", player_scores.bombs_disarmed)
+                            .with_arg("dynamites", player_scores.dynamites_disarmed)
+                            .with_arg("blasts", player_scores.blasts_taken),
+                    ));
+                }
+            }
+
             // spawn remark
             parent
                 .spawn(TextBundle {
@@ -348,38 +699,95 @@ fn spawn_game_over(
                     ..default()
                 })
                 .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Main Menu",
-                        TextStyle {
-                            font: font.0.clone(),
-                            font_size: 34.0,
-                            color: Color::rgba(1., 1., 0.8, 1.0),
-                        },
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 34.0,
+                                color: Color::rgba(1., 1., 0.8, 1.0),
+                            },
+                        ),
+                        // somewhere else to go than the main menu,
+                        // such as the next player's turn
+                        LocalizedText::new(if mode.after_run() != AppState::MainMenu {
+                            "continue"
+                        } else {
+                            "main_menu"
+                        }),
                     ));
                 });
         });
 }
 
-/// system: change the background depending on wave
+/// The level layout of each wave
+/// (the last one also stays for the game over screen)
+pub static WAVE_LEVELS: &[&str] = &[
+    "site",
+    "site",
+    "site",
+    "site",
+    "plaza",
+    "plaza",
+    "warehouse",
+];
+
+/// system: change the level layout depending on wave
 pub fn change_background_per_wave(
+    mut commands: Commands,
     wave: Res<Wave>,
-    query: Query<(&TilemapId, &TilePos, &mut TileTextureIndex)>,
-    query_background: Query<(Entity, &mut Transform), With<Background>>,
+    layouts: Res<LevelLayouts>,
     mut event_reader: EventReader<NextWaveEvent>,
 ) {
-    for _ in event_reader.iter() {
-        let (upper_i, lower_i) = match wave.0 {
-            0 => (0, 1),
-            1 => (0, 1),
-            2 => (0, 1),
-            3 => (0, 2),
-            4 => (0, 2),
-            5 => (3, 1),
-            6 => (3, 1),
-            _ => (0, 1),
-        };
-
-        crate::background::reset_background(query_background, query, upper_i, lower_i);
-        break;
+    if event_reader.iter().next().is_some() {
+        // this runs before the wave counter is incremented
+        let next_wave = wave.0 + 1;
+        let level = wave_level(next_wave);
+
+        commands.insert_resource(CurrentLevel(layouts.get(level)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wave_with_rate(lambda: &str) -> String {
+        format!(
+            "(level: \"site\", spawners: [(item: Bomb, lambda: {}, count: 3, essential: true)])",
+            lambda
+        )
+    }
+
+    #[test]
+    fn reads_positive_rates() {
+        let wave: WaveDefinition = ron::from_str(&wave_with_rate("0.25")).unwrap();
+        assert_eq!(wave.spawners[0].lambda, 0.25);
+        assert!(wave.has_valid_rates());
+    }
+
+    #[test]
+    fn rejects_rates_which_cannot_be_sampled_from() {
+        for lambda in ["0.0", "-0.5", "NaN", "inf"] {
+            assert!(
+                ron::from_str::<WaveDefinition>(&wave_with_rate(lambda)).is_err(),
+                "rate {} was read",
+                lambda
+            );
+        }
+    }
+
+    #[test]
+    fn checks_rates_before_saving() {
+        let mut wave = WaveDefinition::default();
+        wave.spawners.push(SpawnerDefinition {
+            item: Throwable::Dynamite,
+            lambda: 0.,
+            count: 1,
+            essential: true,
+        });
+        assert!(!wave.has_valid_rates());
+        wave.spawners[0].lambda = 0.3;
+        assert!(wave.has_valid_rates());
     }
 }